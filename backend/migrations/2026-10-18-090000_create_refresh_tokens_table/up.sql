@@ -0,0 +1,12 @@
+create table if not exists refresh_tokens (
+    id uuid primary key,
+    user_id uuid not null references users (id) on delete cascade,
+    family_id uuid not null,
+    token_hash bytea unique not null,
+    expires_at timestamptz not null,
+    created_at timestamptz not null,
+    rotated_at timestamptz,
+    revoked_at timestamptz
+);
+
+create index if not exists refresh_tokens_family_id_idx on refresh_tokens (family_id);
\ No newline at end of file
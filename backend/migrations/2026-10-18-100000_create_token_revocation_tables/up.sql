@@ -0,0 +1,10 @@
+create table if not exists revoked_tokens (
+    jti uuid primary key,
+    user_id uuid not null references users (id) on delete cascade,
+    expires_at timestamptz not null
+);
+
+create table if not exists user_token_revocations (
+    user_id uuid primary key references users (id) on delete cascade,
+    revoked_before timestamptz not null
+);
\ No newline at end of file
@@ -0,0 +1,7 @@
+create table if not exists user_totp (
+    user_id uuid primary key references users (id) on delete cascade,
+    secret_ciphertext bytea not null,
+    confirmed_at timestamptz,
+    last_used_step bigint,
+    created_at timestamptz not null
+);
\ No newline at end of file
@@ -0,0 +1 @@
+drop table if exists recovery_codes;
\ No newline at end of file
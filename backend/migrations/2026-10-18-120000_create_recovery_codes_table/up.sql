@@ -0,0 +1,9 @@
+create table if not exists recovery_codes (
+    id uuid primary key,
+    user_id uuid not null references users (id) on delete cascade,
+    code_hash bytea not null,
+    used_at timestamptz,
+    created_at timestamptz not null
+);
+
+create index if not exists recovery_codes_user_id_idx on recovery_codes (user_id);
\ No newline at end of file
@@ -0,0 +1,10 @@
+create table if not exists password_reset_tokens (
+    id uuid primary key,
+    user_id uuid not null references users (id) on delete cascade,
+    token_hash bytea unique not null,
+    expires_at timestamptz not null,
+    created_at timestamptz not null,
+    used_at timestamptz
+);
+
+create index if not exists password_reset_tokens_user_id_idx on password_reset_tokens (user_id);
\ No newline at end of file
@@ -0,0 +1 @@
+alter table users add column if not exists email_verified_at timestamptz;
\ No newline at end of file
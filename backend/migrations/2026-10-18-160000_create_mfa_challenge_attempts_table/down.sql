@@ -0,0 +1 @@
+drop table if exists mfa_challenge_attempts;
\ No newline at end of file
@@ -0,0 +1,6 @@
+create table if not exists mfa_challenge_attempts (
+    jti uuid primary key,
+    user_id uuid not null references users (id) on delete cascade,
+    attempts integer not null,
+    expires_at timestamptz not null
+);
\ No newline at end of file
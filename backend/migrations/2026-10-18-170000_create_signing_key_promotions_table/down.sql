@@ -0,0 +1 @@
+drop table if exists signing_key_promotions;
\ No newline at end of file
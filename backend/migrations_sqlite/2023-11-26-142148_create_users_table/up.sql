@@ -0,0 +1,6 @@
+create table if not exists users (
+    id text primary key,
+    name text not null,
+    email text unique not null,
+    password_hash blob not null
+);
\ No newline at end of file
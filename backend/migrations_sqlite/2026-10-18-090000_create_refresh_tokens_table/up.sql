@@ -0,0 +1,12 @@
+create table if not exists refresh_tokens (
+    id text primary key,
+    user_id text not null references users (id) on delete cascade,
+    family_id text not null,
+    token_hash blob unique not null,
+    expires_at text not null,
+    created_at text not null,
+    rotated_at text,
+    revoked_at text
+);
+
+create index if not exists refresh_tokens_family_id_idx on refresh_tokens (family_id);
\ No newline at end of file
@@ -0,0 +1,10 @@
+create table if not exists revoked_tokens (
+    jti text primary key,
+    user_id text not null references users (id) on delete cascade,
+    expires_at text not null
+);
+
+create table if not exists user_token_revocations (
+    user_id text primary key references users (id) on delete cascade,
+    revoked_before text not null
+);
\ No newline at end of file
@@ -0,0 +1,7 @@
+create table if not exists user_totp (
+    user_id text primary key references users (id) on delete cascade,
+    secret_ciphertext blob not null,
+    confirmed_at text,
+    last_used_step bigint,
+    created_at text not null
+);
\ No newline at end of file
@@ -0,0 +1,9 @@
+create table if not exists recovery_codes (
+    id text primary key,
+    user_id text not null references users (id) on delete cascade,
+    code_hash blob not null,
+    used_at text,
+    created_at text not null
+);
+
+create index if not exists recovery_codes_user_id_idx on recovery_codes (user_id);
\ No newline at end of file
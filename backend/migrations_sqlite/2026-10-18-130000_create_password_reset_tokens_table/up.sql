@@ -0,0 +1,10 @@
+create table if not exists password_reset_tokens (
+    id text primary key,
+    user_id text not null references users (id) on delete cascade,
+    token_hash blob unique not null,
+    expires_at text not null,
+    created_at text not null,
+    used_at text
+);
+
+create index if not exists password_reset_tokens_user_id_idx on password_reset_tokens (user_id);
\ No newline at end of file
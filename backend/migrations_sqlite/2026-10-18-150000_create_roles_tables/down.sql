@@ -0,0 +1,2 @@
+drop table if exists user_roles;
+drop table if exists roles;
\ No newline at end of file
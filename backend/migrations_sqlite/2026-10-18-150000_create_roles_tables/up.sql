@@ -0,0 +1,15 @@
+create table if not exists roles (
+    name text primary key,
+    description text not null
+);
+
+create table if not exists user_roles (
+    user_id text not null references users (id) on delete cascade,
+    role text not null references roles (name) on delete cascade,
+    created_at text not null,
+    primary key (user_id, role)
+);
+
+insert into roles (name, description)
+values ('admin', 'Can list every user')
+on conflict (name) do nothing;
\ No newline at end of file
@@ -0,0 +1,6 @@
+create table if not exists mfa_challenge_attempts (
+    jti text primary key,
+    user_id text not null references users (id) on delete cascade,
+    attempts integer not null,
+    expires_at text not null
+);
\ No newline at end of file
@@ -0,0 +1,4 @@
+create table if not exists signing_key_promotions (
+    kid text primary key,
+    promoted_at text not null
+);
\ No newline at end of file
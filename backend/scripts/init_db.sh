@@ -24,6 +24,10 @@ DB_PASSWORD="${POSTGRES_PASSWORD:=password}"
 DB_NAME="${POSTGRES_DB:=users}"
 DB_PORT="${POSTGRES_PORT:=5432}"
 DB_HOST="${POSTGRES_HOST:=localhost}"
+# with POSTGRES_SSL=true, Postgres only accepts TLS connections from clients presenting a certificate.
+# the self-signed CA, server and client certificates are generated into CERTS_DIR
+DB_SSL="${POSTGRES_SSL:=false}"
+CERTS_DIR="${CERTS_DIR:=./certs}"
 
 RUNNING_POSTGRES_CONTAINER=$(docker ps --filter 'name=postgres' --format '{{.ID}}')
 if [[ -n $RUNNING_POSTGRES_CONTAINER ]]; then
@@ -32,14 +36,60 @@ if [[ -n $RUNNING_POSTGRES_CONTAINER ]]; then
   exit 1
 fi
 
-docker run \
-    -e POSTGRES_USER=${DB_USER} \
-    -e POSTGRES_PASSWORD=${DB_PASSWORD} \
-    -e POSTGRES_DB=${DB_NAME} \
-    -p "${DB_PORT}":5432 \
-    -d \
-    --name "postgres_$(date '+%s')" \
-    postgres -N 75
+if [[ "${DB_SSL}" == "true" ]]; then
+  if ! [ -x "$(command -v openssl)" ]; then
+    echo >&2 "Error: openssl is not installed."
+    exit 1
+  fi
+
+  mkdir -p "${CERTS_DIR}"
+  CERTS_DIR=$(cd "${CERTS_DIR}" && pwd)
+
+  if ! [ -f "${CERTS_DIR}/ca.crt" ]; then
+    openssl req -x509 -newkey rsa:2048 -nodes -days 365 -subj "/CN=task-dev-ca" \
+        -keyout "${CERTS_DIR}/ca.key" -out "${CERTS_DIR}/ca.crt"
+    openssl req -newkey rsa:2048 -nodes -subj "/CN=${DB_HOST}" \
+        -keyout "${CERTS_DIR}/server.key" -out "${CERTS_DIR}/server.csr"
+    openssl x509 -req -days 365 -in "${CERTS_DIR}/server.csr" \
+        -CA "${CERTS_DIR}/ca.crt" -CAkey "${CERTS_DIR}/ca.key" -CAcreateserial \
+        -extfile <(printf "subjectAltName=DNS:localhost,IP:127.0.0.1") \
+        -out "${CERTS_DIR}/server.crt"
+    openssl req -newkey rsa:2048 -nodes -subj "/CN=${DB_USER}" \
+        -keyout "${CERTS_DIR}/client.key" -out "${CERTS_DIR}/client.csr"
+    openssl x509 -req -days 365 -in "${CERTS_DIR}/client.csr" \
+        -CA "${CERTS_DIR}/ca.crt" -CAkey "${CERTS_DIR}/ca.key" -CAcreateserial \
+        -out "${CERTS_DIR}/client.crt"
+    chmod 600 "${CERTS_DIR}"/*.key
+  fi
+
+  printf "local all all trust\nhostssl all all all scram-sha-256 clientcert=verify-ca\n" > "${CERTS_DIR}/pg_hba.conf"
+
+  # Postgres refuses to use a server key that is readable by anyone but its owner
+  docker run \
+      -e POSTGRES_USER=${DB_USER} \
+      -e POSTGRES_PASSWORD=${DB_PASSWORD} \
+      -e POSTGRES_DB=${DB_NAME} \
+      -p "${DB_PORT}":5432 \
+      -v "${CERTS_DIR}":/certs:ro \
+      -d \
+      --name "postgres_$(date '+%s')" \
+      --entrypoint bash \
+      postgres -c "install -o postgres -m 600 /certs/server.key /tmp/server.key && exec docker-entrypoint.sh postgres -N 75 \
+          -c ssl=on -c ssl_cert_file=/certs/server.crt -c ssl_key_file=/tmp/server.key \
+          -c ssl_ca_file=/certs/ca.crt -c hba_file=/certs/pg_hba.conf"
+
+  export PGSSLMODE=verify-full PGSSLROOTCERT="${CERTS_DIR}/ca.crt" \
+      PGSSLCERT="${CERTS_DIR}/client.crt" PGSSLKEY="${CERTS_DIR}/client.key"
+else
+  docker run \
+      -e POSTGRES_USER=${DB_USER} \
+      -e POSTGRES_PASSWORD=${DB_PASSWORD} \
+      -e POSTGRES_DB=${DB_NAME} \
+      -p "${DB_PORT}":5432 \
+      -d \
+      --name "postgres_$(date '+%s')" \
+      postgres -N 75
+fi
 
 until PGPASSWORD="${DB_PASSWORD}" psql -h "${DB_HOST}" -U "${DB_USER}" -p "${DB_PORT}" -d "postgres" -c '\q'; do
   >&2 echo "Postgres is still unavailable - sleeping"
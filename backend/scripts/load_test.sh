@@ -0,0 +1,53 @@
+#!/usr/bin/env bash
+# Measures the latency of an endpoint that does no hashing or database work, first on an idle
+# server and then while logins are hammering it. With hashing and queries running on the
+# blocking pool, both runs should report roughly the same numbers.
+set -eo pipefail
+
+if ! [ -x "$(command -v curl)" ]; then
+  echo >&2 "Error: curl is not installed."
+  exit 1
+fi
+
+BASE_URL="${BASE_URL:=http://127.0.0.1:8000}"
+CONCURRENT_LOGINS="${CONCURRENT_LOGINS:=16}"
+SAMPLES="${SAMPLES:=200}"
+
+EMAIL="load-test-$(date '+%s')@example.com"
+PASSWORD="load-test-password"
+
+# prints the p50, p95 and max latency in milliseconds of $SAMPLES requests to the JWKS endpoint
+measure() {
+  for _ in $(seq "${SAMPLES}"); do
+    curl -s -o /dev/null -w '%{time_total}\n' "${BASE_URL}/.well-known/jwks.json"
+  done | sort -n | awk '
+    { latencies[NR] = $1 * 1000 }
+    END {
+      printf "p50: %.1fms, p95: %.1fms, max: %.1fms\n",
+        latencies[int(NR * 0.5)], latencies[int(NR * 0.95)], latencies[NR]
+    }'
+}
+
+curl -s -o /dev/null --fail -X POST "${BASE_URL}/api/auth/register" \
+  -H 'Content-Type: application/json' \
+  -d "{\"name\": \"Load Test\", \"email\": \"${EMAIL}\", \"password\": \"${PASSWORD}\"}"
+
+echo "idle:       $(measure)"
+
+LOGIN_PIDS=()
+for _ in $(seq "${CONCURRENT_LOGINS}"); do
+  (
+    while true; do
+      curl -s -o /dev/null -X POST "${BASE_URL}/api/auth/login" \
+        -H 'Content-Type: application/json' \
+        -d "{\"email\": \"${EMAIL}\", \"password\": \"${PASSWORD}\"}"
+    done
+  ) &
+  LOGIN_PIDS+=($!)
+done
+trap 'kill "${LOGIN_PIDS[@]}" 2>/dev/null' EXIT
+
+# give the logins a moment to saturate the server
+sleep 1
+
+echo "under load: $(measure)"
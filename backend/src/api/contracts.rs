@@ -1,6 +1,8 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Response<T: Serialize> {
@@ -53,11 +55,38 @@ pub struct RegisterUserRequest {
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RegisterUserResponse {
     pub user: User,
 }
 
+/// Fields that are left out keep their current value.
+#[derive(Debug, Default, Serialize, Deserialize, Validate)]
+pub struct UpdateUserRequest {
+    #[validate(length(min = 1, message = "name must be at least 1 character long"))]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateUserResponse {
+    pub user: User,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "please enter your current password"))]
+    pub current_password: String,
+    #[validate(length(min = 8, message = "password must be at least 8 characters long"))]
+    pub new_password: String,
+    /// Keeps the session of the token the request was made with, every other one is signed out.
+    #[serde(default = "default_keep_current_session")]
+    pub keep_current_session: bool,
+}
+
+fn default_keep_current_session() -> bool {
+    true
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct LoginUserRequest {
     #[validate(email(message = "email must be in a valid format"))]
@@ -69,9 +98,140 @@ pub struct LoginUserRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginUserResponse {
     pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_mfa_login_request"))]
+pub struct MfaLoginRequest {
+    #[validate(length(min = 1, message = "please enter an MFA token"))]
+    pub mfa_token: String,
+    #[validate(length(equal = 6, message = "authentication code must be 6 digits long"))]
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnrollTotpResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ConfirmTotpRequest {
+    #[validate(length(equal = 6, message = "authentication code must be 6 digits long"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_disable_totp_request"))]
+pub struct DisableTotpRequest {
+    #[validate(length(equal = 6, message = "authentication code must be 6 digits long"))]
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct RegenerateRecoveryCodesRequest {
+    #[validate(length(equal = 6, message = "authentication code must be 6 digits long"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetRecoveryCodesResponse {
+    pub remaining: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct RefreshTokenRequest {
+    #[validate(length(min = 1, message = "please enter a refresh token"))]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenResponse {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogoutEverywhereRequest {
+    pub before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1, message = "please enter an email verification token"))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ResendVerificationEmailRequest {
+    #[validate(email(message = "email must be in a valid format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "email must be in a valid format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1, message = "please enter a password reset token"))]
+    pub token: String,
+    #[validate(length(min = 8, message = "password must be at least 8 characters long"))]
+    pub password: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetUsersResponse {
     pub users: Vec<User>,
 }
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PromoteSigningKeyRequest {
+    #[validate(length(min = 1, message = "please enter a key id"))]
+    pub kid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromoteSigningKeyResponse {
+    pub active_kid: String,
+}
+
+fn validate_mfa_login_request(request: &MfaLoginRequest) -> Result<(), ValidationError> {
+    validate_single_second_factor(&request.code, &request.recovery_code)
+}
+
+fn validate_disable_totp_request(request: &DisableTotpRequest) -> Result<(), ValidationError> {
+    validate_single_second_factor(&request.code, &request.recovery_code)
+}
+
+fn validate_single_second_factor(
+    code: &Option<String>,
+    recovery_code: &Option<String>,
+) -> Result<(), ValidationError> {
+    if code.is_some() == recovery_code.is_some() {
+        let mut err = ValidationError::new("second_factor");
+        err.message = Some(Cow::from(
+            "please enter either an authentication code or a recovery code",
+        ));
+        return Err(err);
+    }
+
+    Ok(())
+}
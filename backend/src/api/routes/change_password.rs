@@ -0,0 +1,23 @@
+use crate::api::contracts::ChangePasswordRequest;
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::{AuthService, UserService};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn change_password(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+    request: Json<ChangePasswordRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    user_service
+        .change_password(auth_service.into_inner(), user.claims(), request)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
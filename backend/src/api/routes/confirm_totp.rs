@@ -0,0 +1,23 @@
+use crate::api::contracts::ConfirmTotpRequest;
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::{AuthService, UserService};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn confirm_totp(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+    request: Json<ConfirmTotpRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    user_service
+        .confirm_totp(auth_service.into_inner(), user.id(), request)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,13 @@
+use crate::errors::ServerError;
+use crate::service::UserService;
+use actix_web::{web, HttpResponse, Responder};
+use uuid::Uuid;
+
+pub async fn delete_user(
+    user_service: web::Data<UserService>,
+    user_id: web::Path<Uuid>,
+) -> Result<impl Responder, ServerError> {
+    user_service.delete_user(user_id.into_inner()).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
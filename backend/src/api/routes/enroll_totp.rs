@@ -0,0 +1,24 @@
+use crate::api::contracts;
+use crate::api::contracts::EnrollTotpResponse;
+use crate::errors::ServerError;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::{AuthService, UserService};
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn enroll_totp(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+) -> Result<impl Responder, ServerError> {
+    let enrollment = user_service
+        .enroll_totp(auth_service.into_inner(), user.id())
+        .await?;
+
+    Ok(
+        HttpResponse::Ok().json(contracts::Response::ok(EnrollTotpResponse {
+            secret: enrollment.secret,
+            otpauth_uri: enrollment.otpauth_uri,
+            recovery_codes: enrollment.recovery_codes,
+        })),
+    )
+}
@@ -0,0 +1,30 @@
+use crate::api::contracts::ForgotPasswordRequest;
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::service::{AuthService, UserService};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use log::error;
+
+pub async fn forgot_password(
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+    request: Json<ForgotPasswordRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    // the request is handled in the background and its outcome is never reported,
+    // so neither the response nor its timing tells whether the email is registered
+    actix_web::rt::spawn(async move {
+        let result = user_service
+            .request_password_reset(auth_service.into_inner(), request)
+            .await;
+
+        if let Err(e) = result {
+            error!("failed to handle password reset request. reason: {}", e);
+        }
+    });
+
+    Ok(HttpResponse::Accepted().finish())
+}
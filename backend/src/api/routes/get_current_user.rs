@@ -0,0 +1,17 @@
+use crate::api::contracts;
+use crate::api::contracts::GetUserResponse;
+use crate::errors::ServerError;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::UserService;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn get_current_user(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+) -> Result<impl Responder, ServerError> {
+    let user = user_service.get_user(user.id()).await?;
+
+    let response = contracts::Response::ok(GetUserResponse { user: user.into() });
+
+    Ok(HttpResponse::Ok().json(response))
+}
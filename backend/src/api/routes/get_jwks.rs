@@ -0,0 +1,6 @@
+use crate::service::AuthService;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn get_jwks(auth_service: web::Data<AuthService>) -> impl Responder {
+    HttpResponse::Ok().json(auth_service.get_jwks())
+}
@@ -0,0 +1,19 @@
+use crate::api::contracts;
+use crate::api::contracts::GetRecoveryCodesResponse;
+use crate::errors::ServerError;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::UserService;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn get_recovery_codes(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+) -> Result<impl Responder, ServerError> {
+    let remaining = user_service.count_recovery_codes(user.id()).await?;
+
+    Ok(
+        HttpResponse::Ok().json(contracts::Response::ok(GetRecoveryCodesResponse {
+            remaining,
+        })),
+    )
+}
@@ -0,0 +1,17 @@
+use crate::api::contracts;
+use crate::api::contracts::GetUserResponse;
+use crate::errors::ServerError;
+use crate::service::UserService;
+use actix_web::{web, HttpResponse, Responder};
+use uuid::Uuid;
+
+pub async fn get_user(
+    user_service: web::Data<UserService>,
+    user_id: web::Path<Uuid>,
+) -> Result<impl Responder, ServerError> {
+    let user = user_service.get_user(user_id.into_inner()).await?;
+
+    let response = contracts::Response::ok(GetUserResponse { user: user.into() });
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -5,7 +5,7 @@ use crate::service::UserService;
 use actix_web::{web, HttpResponse, Responder};
 
 pub async fn get_users(service: web::Data<UserService>) -> Result<impl Responder, ServerError> {
-    let users = service.get_users()?;
+    let users = service.get_users().await?;
 
     let response = contracts::Response::ok(GetUsersResponse {
         users: users.iter().map(Into::into).collect(),
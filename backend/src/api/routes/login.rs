@@ -1,7 +1,8 @@
 use crate::api::contracts;
-use crate::api::contracts::{LoginUserRequest, LoginUserResponse};
+use crate::api::contracts::{LoginUserRequest, LoginUserResponse, MfaChallengeResponse};
 use crate::errors::ServerError;
 use crate::helpers::validate_request;
+use crate::models::refresh_token::LoginOutcome;
 use crate::service::{AuthService, UserService};
 use actix_web::web::Json;
 use actix_web::{web, HttpResponse, Responder};
@@ -14,7 +15,23 @@ pub async fn login(
     let request = request.into_inner();
     validate_request(&request)?;
 
-    let token = user_service.login(auth_service.into_inner(), request)?;
+    let response = match user_service
+        .login(auth_service.into_inner(), request)
+        .await?
+    {
+        LoginOutcome::Authenticated(tokens) => {
+            HttpResponse::Ok().json(contracts::Response::ok(LoginUserResponse {
+                token: tokens.token,
+                refresh_token: tokens.refresh_token,
+            }))
+        }
+        LoginOutcome::MfaRequired(mfa_token) => {
+            HttpResponse::Ok().json(contracts::Response::ok(MfaChallengeResponse {
+                mfa_required: true,
+                mfa_token,
+            }))
+        }
+    };
 
-    Ok(HttpResponse::Ok().json(contracts::Response::ok(LoginUserResponse { token })))
+    Ok(response)
 }
@@ -0,0 +1,27 @@
+use crate::api::contracts;
+use crate::api::contracts::{LoginUserResponse, MfaLoginRequest};
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::service::{AuthService, UserService};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn login_mfa(
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+    request: Json<MfaLoginRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    let tokens = user_service
+        .login_mfa(auth_service.into_inner(), request)
+        .await?;
+
+    Ok(
+        HttpResponse::Ok().json(contracts::Response::ok(LoginUserResponse {
+            token: tokens.token,
+            refresh_token: tokens.refresh_token,
+        })),
+    )
+}
@@ -0,0 +1,13 @@
+use crate::errors::ServerError;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::UserService;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn logout(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+) -> Result<impl Responder, ServerError> {
+    user_service.logout(user.into_claims()).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
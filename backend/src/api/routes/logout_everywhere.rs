@@ -0,0 +1,18 @@
+use crate::api::contracts::LogoutEverywhereRequest;
+use crate::errors::ServerError;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::UserService;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn logout_everywhere(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    request: Json<LogoutEverywhereRequest>,
+) -> Result<impl Responder, ServerError> {
+    user_service
+        .logout_everywhere(user.id(), request.into_inner().before)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,7 +1,49 @@
+mod change_password;
+mod confirm_totp;
+mod delete_user;
+mod disable_totp;
+mod enroll_totp;
+mod forgot_password;
+mod get_current_user;
+mod get_jwks;
+mod get_recovery_codes;
+mod get_user;
 mod get_users;
 mod login;
+mod login_mfa;
+mod logout;
+mod logout_everywhere;
+mod promote_signing_key;
+mod refresh;
+mod regenerate_recovery_codes;
 mod register;
+mod resend_verification_email;
+mod reset_password;
+mod update_current_user;
+mod update_user;
+mod verify_email;
 
+pub use change_password::change_password;
+pub use confirm_totp::confirm_totp;
+pub use delete_user::delete_user;
+pub use disable_totp::disable_totp;
+pub use enroll_totp::enroll_totp;
+pub use forgot_password::forgot_password;
+pub use get_current_user::get_current_user;
+pub use get_jwks::get_jwks;
+pub use get_recovery_codes::get_recovery_codes;
+pub use get_user::get_user;
 pub use get_users::get_users;
 pub use login::login;
+pub use login_mfa::login_mfa;
+pub use logout::logout;
+pub use logout_everywhere::logout_everywhere;
+pub use promote_signing_key::promote_signing_key;
+pub use refresh::refresh;
+pub use regenerate_recovery_codes::regenerate_recovery_codes;
 pub use register::register;
+pub use resend_verification_email::resend_verification_email;
+pub use reset_password::reset_password;
+pub use update_current_user::update_current_user;
+pub use update_user::update_user;
+pub use verify_email::verify_email;
@@ -0,0 +1,38 @@
+use crate::api::contracts;
+use crate::api::contracts::{PromoteSigningKeyRequest, PromoteSigningKeyResponse};
+use crate::errors::{Error, ServerError};
+use crate::helpers::validate_request;
+use crate::service::{AuthService, UserService};
+use actix_web::web::Json;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+
+static ADMIN_KEY_HEADER_NAME: &str = "X-Admin-Key";
+
+pub async fn promote_signing_key(
+    req: HttpRequest,
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+    request: Json<PromoteSigningKeyRequest>,
+) -> Result<impl Responder, ServerError> {
+    let admin_key = req
+        .headers()
+        .get(ADMIN_KEY_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !auth_service.is_admin_key(admin_key) {
+        return Err(Error::Forbidden.into());
+    }
+
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    user_service
+        .promote_signing_key(auth_service.into_inner(), &request.kid)
+        .await?;
+
+    Ok(
+        HttpResponse::Ok().json(contracts::Response::ok(PromoteSigningKeyResponse {
+            active_kid: request.kid,
+        })),
+    )
+}
@@ -0,0 +1,28 @@
+use crate::api::contracts;
+use crate::api::contracts::{RecoveryCodesResponse, RegenerateRecoveryCodesRequest};
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::{AuthService, UserService};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn regenerate_recovery_codes(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+    request: Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    let recovery_codes = user_service
+        .regenerate_recovery_codes(auth_service.into_inner(), user.id(), request)
+        .await?;
+
+    Ok(
+        HttpResponse::Ok().json(contracts::Response::ok(RecoveryCodesResponse {
+            recovery_codes,
+        })),
+    )
+}
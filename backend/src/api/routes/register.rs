@@ -14,7 +14,9 @@ pub async fn register(
     let request = request.into_inner();
     validate_request(&request)?;
 
-    let user = user_service.register(auth_service.into_inner(), request)?;
+    let user = user_service
+        .register(auth_service.into_inner(), request)
+        .await?;
     let response = contracts::Response::ok(RegisterUserResponse { user: user.into() });
 
     Ok(HttpResponse::Ok().json(response))
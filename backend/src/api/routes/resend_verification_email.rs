@@ -0,0 +1,29 @@
+use crate::api::contracts::ResendVerificationEmailRequest;
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::service::{AuthService, UserService};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use log::error;
+
+pub async fn resend_verification_email(
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+    request: Json<ResendVerificationEmailRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    // same as with password resets, the outcome is never reported to the caller
+    actix_web::rt::spawn(async move {
+        let result = user_service
+            .resend_verification_email(auth_service.into_inner(), request)
+            .await;
+
+        if let Err(e) = result {
+            error!("failed to resend verification email. reason: {}", e);
+        }
+    });
+
+    Ok(HttpResponse::Accepted().finish())
+}
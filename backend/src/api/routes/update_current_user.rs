@@ -0,0 +1,23 @@
+use crate::api::contracts;
+use crate::api::contracts::{UpdateUserRequest, UpdateUserResponse};
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::service::UserService;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn update_current_user(
+    user: AuthenticatedUser,
+    user_service: web::Data<UserService>,
+    request: Json<UpdateUserRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    let user = user_service.update_profile(user.id(), request).await?;
+
+    let response = contracts::Response::ok(UpdateUserResponse { user: user.into() });
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -0,0 +1,25 @@
+use crate::api::contracts;
+use crate::api::contracts::{UpdateUserRequest, UpdateUserResponse};
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::service::UserService;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use uuid::Uuid;
+
+pub async fn update_user(
+    user_service: web::Data<UserService>,
+    user_id: web::Path<Uuid>,
+    request: Json<UpdateUserRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    let user = user_service
+        .update_profile(user_id.into_inner(), request)
+        .await?;
+
+    let response = contracts::Response::ok(UpdateUserResponse { user: user.into() });
+
+    Ok(HttpResponse::Ok().json(response))
+}
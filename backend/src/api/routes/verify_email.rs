@@ -0,0 +1,21 @@
+use crate::api::contracts::VerifyEmailRequest;
+use crate::errors::ServerError;
+use crate::helpers::validate_request;
+use crate::service::{AuthService, UserService};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+
+pub async fn verify_email(
+    user_service: web::Data<UserService>,
+    auth_service: web::Data<AuthService>,
+    request: Json<VerifyEmailRequest>,
+) -> Result<impl Responder, ServerError> {
+    let request = request.into_inner();
+    validate_request(&request)?;
+
+    user_service
+        .verify_email(auth_service.into_inner(), request)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
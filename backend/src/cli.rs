@@ -0,0 +1,137 @@
+use crate::migrations::MigrationCommand;
+use anyhow::anyhow;
+
+pub const USAGE: &str =
+    "usage: backend [migrate up|down|status | config check | roles grant|revoke <email> <role>]";
+
+/// What the binary was asked to do, parsed from its arguments.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Starts the server, which is what happens without any arguments.
+    Serve,
+    Migrate(MigrationCommand),
+    /// Validates the configuration without starting the server.
+    CheckConfig,
+    Roles(RoleCommand),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RoleCommand {
+    Grant {
+        email: String,
+        role: String,
+    },
+    /// Also signs the user out everywhere, so that the role stops working right away.
+    Revoke {
+        email: String,
+        role: String,
+    },
+}
+
+impl Command {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, anyhow::Error> {
+        let args = args.into_iter().collect::<Vec<_>>();
+
+        match args
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [] => Ok(Self::Serve),
+            ["migrate", command] => Ok(Self::Migrate(command.parse()?)),
+            ["config", "check"] => Ok(Self::CheckConfig),
+            ["roles", "grant", email, role] => Ok(Self::Roles(RoleCommand::Grant {
+                email: email.to_string(),
+                role: role.to_string(),
+            })),
+            ["roles", "revoke", email, role] => Ok(Self::Roles(RoleCommand::Revoke {
+                email: email.to_string(),
+                role: role.to_string(),
+            })),
+            _ => Err(anyhow!(USAGE)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_should_return_serve_when_no_arguments_given() {
+        // Act
+
+        let actual = Command::parse(Vec::new()).unwrap();
+
+        // Assert
+
+        assert_eq!(actual, Command::Serve);
+    }
+
+    #[test]
+    fn parse_should_return_migration_command() {
+        // Arrange
+
+        let args = vec!["migrate".to_string(), "status".to_string()];
+
+        // Act
+
+        let actual = Command::parse(args).unwrap();
+
+        // Assert
+
+        assert_eq!(actual, Command::Migrate(MigrationCommand::Status));
+    }
+
+    #[test]
+    fn parse_should_return_check_config_command() {
+        // Arrange
+
+        let args = vec!["config".to_string(), "check".to_string()];
+
+        // Act
+
+        let actual = Command::parse(args).unwrap();
+
+        // Assert
+
+        assert_eq!(actual, Command::CheckConfig);
+    }
+
+    #[test]
+    fn parse_should_return_role_command() {
+        // Arrange
+
+        let args = ["roles", "grant", "john.doe@example.com", "admin"].map(String::from);
+
+        // Act
+
+        let actual = Command::parse(args).unwrap();
+
+        // Assert
+
+        assert_eq!(
+            actual,
+            Command::Roles(RoleCommand::Grant {
+                email: "john.doe@example.com".to_string(),
+                role: "admin".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_should_return_error_when_migration_command_is_unknown() {
+        // Arrange
+
+        let args = vec!["migrate".to_string(), "sideways".to_string()];
+
+        // Act
+
+        let actual = Command::parse(args);
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+}
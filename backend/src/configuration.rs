@@ -1,54 +0,0 @@
-use serde::Deserialize;
-
-#[derive(Deserialize, Clone)]
-pub struct Settings {
-    pub application: ApplicationSettings,
-    pub database: DatabaseSettings,
-    pub authentication: AuthenticationSettings,
-}
-
-#[derive(Deserialize, Clone)]
-pub struct ApplicationSettings {
-    pub port: u16,
-    pub host: String,
-}
-
-#[derive(Deserialize, Clone)]
-pub struct AuthenticationSettings {
-    pub secret_key: String,
-    pub audience: String,
-    pub token_expiration_in_seconds: u64,
-}
-
-#[derive(Deserialize, Clone)]
-pub struct DatabaseSettings {
-    pub username: String,
-    pub password: String,
-    pub port: u16,
-    pub host: String,
-    pub database_name: String,
-    pub require_ssl: bool,
-}
-
-impl DatabaseSettings {
-    pub fn get_connection_string(&self) -> String {
-        format!(
-            "postgres://{}:{}@{}:{}/{}",
-            self.username, self.password, self.host, self.port, self.database_name
-        )
-    }
-}
-
-pub fn get_configuration() -> Result<Settings, config::ConfigError> {
-    let base_path =
-        std::env::current_dir().expect("configuration.rs - unable to determine current directory");
-    let configuration_directory = base_path.join("configuration");
-
-    let settings = config::Config::builder()
-        .add_source(config::File::from(
-            configuration_directory.join("base.yaml"),
-        ))
-        .build()?;
-
-    settings.try_deserialize::<Settings>()
-}
@@ -0,0 +1,636 @@
+use crate::mail::file::FileMailer;
+use crate::mail::in_memory::InMemoryMailer;
+use crate::mail::smtp::{SmtpMailer, SmtpTls};
+use crate::mail::Mailer;
+use crate::repository::postgres::{tls, PostgresRepository};
+#[cfg(feature = "sqlite")]
+use crate::repository::sqlite::SqliteRepository;
+use crate::repository::Repository;
+use crate::service::keyring::Keyring;
+use crate::service::keys::SigningKey;
+use crate::service::passwords::PasswordHashScheme;
+use crate::service::revocation::{DatabaseRevocationStore, RevocationStore};
+use anyhow::{anyhow, bail, Context};
+use config::{Config, ConfigError, Value, ValueKind};
+use diesel_async::pooled_connection::bb8::Pool;
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use diesel_async::AsyncPgConnection;
+use futures::FutureExt;
+use jsonwebtoken::Algorithm;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use rustls::ClientConfig;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod validation;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Settings {
+    pub application: ApplicationSettings,
+    pub database: DatabaseSettings,
+    pub authentication: AuthenticationSettings,
+    pub mail: MailSettings,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApplicationSettings {
+    pub port: u16,
+    pub host: String,
+    /// How many passwords may be hashed or verified at once.
+    pub max_hashing_tasks: usize,
+    /// How many emails may be sent at once. Kept apart from hashing, so that a slow mail server
+    /// doesn't hold up logins and the other way around.
+    pub max_mail_tasks: usize,
+    /// Serves HTTPS instead of plain HTTP when set.
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TlsSettings {
+    /// PEM file with the certificate, followed by its intermediate certificates.
+    pub certificate_path: PathBuf,
+    pub private_key_path: PathBuf,
+    /// How often both files are checked for changes. New connections get the changed certificate
+    /// without a restart.
+    pub reload_interval_in_seconds: u64,
+    /// Also listens for plain HTTP on this port, redirecting every request to HTTPS.
+    #[serde(default)]
+    pub redirect_http_port: Option<u16>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthenticationSettings {
+    pub active_signing_key: String,
+    pub signing_keys: Vec<SigningKeySettings>,
+    /// How often promotions made by other instances are picked up.
+    pub signing_key_reload_interval_in_seconds: u64,
+    pub admin_api_key: Option<SecretString>,
+    pub totp_encryption_key: SecretString,
+    pub totp_issuer: String,
+    pub mfa_challenge_expiration_in_seconds: u64,
+    pub password_reset_url: String,
+    pub password_reset_expiration_in_seconds: u64,
+    pub email_verification_url: String,
+    pub email_verification_expiration_in_seconds: u64,
+    #[serde(default)]
+    pub require_verified_email: bool,
+    pub audience: String,
+    pub token_expiration_in_seconds: u64,
+    pub refresh_token_expiration_in_seconds: u64,
+    #[serde(default)]
+    pub password_hashing: PasswordHashScheme,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SigningKeySettings {
+    pub kid: String,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    pub secret_key: Option<SecretString>,
+    pub private_key_path: Option<PathBuf>,
+    pub public_key_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MailSettings {
+    pub from: String,
+    pub transport: MailTransportSettings,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MailTransportSettings {
+    Smtp {
+        host: String,
+        port: u16,
+        username: Option<String>,
+        password: Option<SecretString>,
+        #[serde(default)]
+        tls: SmtpTls,
+    },
+    File {
+        directory: PathBuf,
+    },
+    InMemory,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DatabaseSettings {
+    #[serde(default)]
+    pub backend: DatabaseBackendSettings,
+    /// Applies pending migrations before the server starts. SQLite databases are always migrated.
+    #[serde(default)]
+    pub migrate_on_startup: bool,
+    pub username: String,
+    pub password: SecretString,
+    pub port: u16,
+    pub host: String,
+    pub database_name: String,
+    /// Connects over TLS and verifies the server's certificate. Connections are unencrypted otherwise.
+    pub require_ssl: bool,
+    /// PEM file with the CA certificates the server's certificate is verified against. The Mozilla
+    /// root certificates are used when it isn't set.
+    #[serde(default)]
+    pub ssl_root_cert_path: Option<PathBuf>,
+    /// PEM files with the certificate and private key the client authenticates itself with, if the server asks for one.
+    #[serde(default)]
+    pub ssl_client_cert_path: Option<PathBuf>,
+    #[serde(default)]
+    pub ssl_client_key_path: Option<PathBuf>,
+    pub max_connections: u32,
+    /// How long a request waits for a free connection before it fails.
+    pub connection_timeout_in_seconds: u64,
+    /// Idle connections are closed after this long, down to `min_idle_connections`.
+    pub idle_timeout_in_seconds: u64,
+    #[serde(default)]
+    pub min_idle_connections: Option<u32>,
+    /// Queries running longer than this are cancelled by Postgres. `0` disables the timeout.
+    pub statement_timeout_in_milliseconds: u64,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DatabaseBackendSettings {
+    /// Connects to Postgres with the remaining `DatabaseSettings`.
+    #[default]
+    Postgres,
+    /// Keeps everything in the SQLite database at `path`, which is created when missing.
+    /// `:memory:` keeps it in memory until the service stops.
+    Sqlite { path: String },
+}
+
+impl AuthenticationSettings {
+    pub fn get_keyring(&self) -> Result<Keyring, anyhow::Error> {
+        let keys = self
+            .signing_keys
+            .iter()
+            .map(|key| {
+                key.get_signing_key()
+                    .with_context(|| format!("failed to load signing key '{}'", key.kid))
+                    .map(|signing_key| (key.kid.clone(), signing_key))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Keyring::new(
+            self.active_signing_key.clone(),
+            keys,
+            self.get_signing_key_retirement_period(),
+        )
+    }
+
+    /// A retired key has to keep verifying every kind of token it signed until the longest lived
+    /// of them expires. Instances that haven't reloaded the promotion yet keep signing with it for
+    /// up to another reload interval.
+    fn get_signing_key_retirement_period(&self) -> Duration {
+        let longest_expiration_in_seconds = [
+            self.token_expiration_in_seconds,
+            self.mfa_challenge_expiration_in_seconds,
+            self.email_verification_expiration_in_seconds,
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or_default();
+
+        Duration::from_secs(longest_expiration_in_seconds)
+            + Duration::from_secs(self.signing_key_reload_interval_in_seconds)
+    }
+}
+
+impl SigningKeySettings {
+    pub fn get_signing_key(&self) -> Result<SigningKey, anyhow::Error> {
+        match (
+            &self.secret_key,
+            &self.private_key_path,
+            &self.public_key_path,
+        ) {
+            (Some(secret_key), None, None) => {
+                SigningKey::from_secret(self.algorithm, secret_key.expose_secret().as_bytes())
+            }
+            (None, Some(private_key_path), Some(public_key_path)) => {
+                SigningKey::from_pem_files(self.algorithm, private_key_path, public_key_path)
+            }
+            _ => Err(anyhow!(
+                "either secret_key or both private_key_path and public_key_path must be set"
+            )),
+        }
+    }
+}
+
+impl MailSettings {
+    pub fn get_mailer(&self) -> Result<Arc<dyn Mailer>, anyhow::Error> {
+        let from = self
+            .from
+            .parse::<Mailbox>()
+            .with_context(|| format!("'{}' is not a valid sender", self.from))?;
+
+        let mailer: Arc<dyn Mailer> = match &self.transport {
+            MailTransportSettings::Smtp {
+                host,
+                port,
+                username,
+                password,
+                tls,
+            } => {
+                let credentials = match (username, password) {
+                    (Some(username), Some(password)) => Some(Credentials::new(
+                        username.clone(),
+                        password.expose_secret().to_string(),
+                    )),
+                    (None, None) => None,
+                    _ => bail!("either both or none of username and password must be set"),
+                };
+                Arc::new(SmtpMailer::new(from, host, *port, *tls, credentials)?)
+            }
+            MailTransportSettings::File { directory } => {
+                Arc::new(FileMailer::new(from, directory)?)
+            }
+            MailTransportSettings::InMemory => Arc::new(InMemoryMailer::default()),
+        };
+
+        Ok(mailer)
+    }
+}
+
+impl DatabaseSettings {
+    pub fn get_connection_string(&self) -> SecretString {
+        format!(
+            "postgres://{}:{}@{}:{}/{}?sslmode={}",
+            self.username,
+            self.password.expose_secret(),
+            self.host,
+            self.port,
+            self.database_name,
+            if self.require_ssl {
+                "require"
+            } else {
+                "disable"
+            }
+        )
+        .into()
+    }
+
+    /// `None` unless `require_ssl` is set.
+    pub fn get_tls_config(&self) -> Result<Option<ClientConfig>, anyhow::Error> {
+        if !self.require_ssl {
+            return Ok(None);
+        }
+
+        let client_cert_paths = match (&self.ssl_client_cert_path, &self.ssl_client_key_path) {
+            (Some(cert_path), Some(key_path)) => Some((cert_path.as_path(), key_path.as_path())),
+            (None, None) => None,
+            _ => bail!(
+                "either both or none of ssl_client_cert_path and ssl_client_key_path must be set"
+            ),
+        };
+
+        tls::get_client_config(self.ssl_root_cert_path.as_deref(), client_cert_paths).map(Some)
+    }
+
+    /// Opens a single connection outside of the pool, e.g. to run migrations.
+    pub async fn establish_connection(&self) -> Result<AsyncPgConnection, anyhow::Error> {
+        tls::establish(
+            self.get_connection_string().expose_secret(),
+            self.get_tls_config()?,
+        )
+        .await
+        .context("failed to connect to the database")
+    }
+
+    /// Connects to the configured backend. The revocation store works on the same database as the repository.
+    pub async fn get_repositories(
+        &self,
+    ) -> Result<(Arc<dyn Repository>, Arc<dyn RevocationStore>), anyhow::Error> {
+        match &self.backend {
+            DatabaseBackendSettings::Postgres => {
+                let repo = Arc::new(PostgresRepository::new(self.get_connection_pool().await?));
+                let revocation_store = Arc::new(DatabaseRevocationStore::new(repo.clone()));
+                Ok((repo, revocation_store))
+            }
+            #[cfg(feature = "sqlite")]
+            DatabaseBackendSettings::Sqlite { path } => {
+                let repo = Arc::new(SqliteRepository::connect(path)?);
+                let revocation_store = Arc::new(DatabaseRevocationStore::new(repo.clone()));
+                Ok((repo, revocation_store))
+            }
+            #[cfg(not(feature = "sqlite"))]
+            DatabaseBackendSettings::Sqlite { .. } => {
+                bail!("the sqlite backend is only available when built with `--features sqlite`")
+            }
+        }
+    }
+
+    pub async fn get_connection_pool(&self) -> Result<Pool<AsyncPgConnection>, anyhow::Error> {
+        // the statement timeout is passed as a startup parameter, so every pooled connection has it
+        let connection_string = format!(
+            "{}&options=-c%20statement_timeout%3D{}",
+            self.get_connection_string().expose_secret(),
+            self.statement_timeout_in_milliseconds
+        );
+        let tls_config = self.get_tls_config()?;
+        let mut manager_config = ManagerConfig::default();
+        manager_config.custom_setup =
+            Box::new(move |url| tls::establish(url, tls_config.clone()).boxed());
+        let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new_with_config(
+            connection_string,
+            manager_config,
+        );
+
+        Pool::builder()
+            .max_size(self.max_connections)
+            .min_idle(self.min_idle_connections)
+            .connection_timeout(Duration::from_secs(self.connection_timeout_in_seconds))
+            .idle_timeout(Some(Duration::from_secs(self.idle_timeout_in_seconds)))
+            .build(manager)
+            .await
+            .context("failed to connect to the database")
+    }
+}
+
+/// Fields that can be read from the file at `<field>_file` instead, e.g. a mounted Docker or Kubernetes secret.
+const SECRET_FIELDS: [&str; 4] = [
+    "password",
+    "secret_key",
+    "admin_api_key",
+    "totp_encryption_key",
+];
+
+/// Picks the overlay file that is layered on top of `base.yaml`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Environment {
+    Local,
+    Production,
+}
+
+impl Environment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Environment::Local => "local",
+            Environment::Production => "production",
+        }
+    }
+}
+
+impl TryFrom<String> for Environment {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "local" => Ok(Environment::Local),
+            "production" => Ok(Environment::Production),
+            other => Err(format!(
+                "'{}' is not a supported environment, use either 'local' or 'production'",
+                other
+            )),
+        }
+    }
+}
+
+/// Reads `base.yaml` and the overlay of the environment named by `APP_ENVIRONMENT` (`local` by default)
+/// from `APP_CONFIGURATION_DIRECTORY`, or `./configuration` when it isn't set. Environment variables
+/// such as `APP__DATABASE__PORT=5433` override both files.
+pub fn get_configuration() -> Result<Settings, ConfigError> {
+    let configuration_directory = match std::env::var_os("APP_CONFIGURATION_DIRECTORY") {
+        Some(directory) => PathBuf::from(directory),
+        None => std::env::current_dir()
+            .map_err(|e| {
+                ConfigError::Message(format!("unable to determine current directory: {}", e))
+            })?
+            .join("configuration"),
+    };
+    let environment: Environment = std::env::var("APP_ENVIRONMENT")
+        .unwrap_or_else(|_| Environment::Local.as_str().to_string())
+        .try_into()
+        .map_err(ConfigError::Message)?;
+
+    let settings = Config::builder()
+        .add_source(config::File::from(
+            configuration_directory.join("base.yaml"),
+        ))
+        .add_source(config::File::from(
+            configuration_directory.join(format!("{}.yaml", environment.as_str())),
+        ))
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("__")
+                .separator("__"),
+        )
+        .build()?;
+
+    deserialize_settings(settings)
+}
+
+fn deserialize_settings(settings: Config) -> Result<Settings, ConfigError> {
+    let mut value = settings.try_deserialize::<Value>()?;
+    load_secret_files(&mut value, "")?;
+
+    value.try_deserialize::<Settings>()
+}
+
+/// Replaces every `<field>_file` of the `SECRET_FIELDS` with `<field>` set to the contents of that file.
+fn load_secret_files(value: &mut Value, path: &str) -> Result<(), ConfigError> {
+    match &mut value.kind {
+        ValueKind::Table(table) => {
+            for field in SECRET_FIELDS {
+                let file_field = format!("{}_file", field);
+                let Some(file_path) = table.remove(&file_field) else {
+                    continue;
+                };
+                if table
+                    .get(field)
+                    .is_some_and(|value| !matches!(value.kind, ValueKind::Nil))
+                {
+                    return Err(ConfigError::Message(format!(
+                        "only one of `{0}{1}` and `{0}{2}` can be set",
+                        path, field, file_field
+                    )));
+                }
+
+                let secret =
+                    read_secret_file(Path::new(&file_path.into_string()?)).map_err(|e| {
+                        ConfigError::Message(format!("`{}{}`: {:#}", path, file_field, e))
+                    })?;
+                table.insert(
+                    field.to_string(),
+                    Value::new(None, ValueKind::String(secret)),
+                );
+            }
+
+            for (key, value) in table.iter_mut() {
+                load_secret_files(value, &format!("{}{}.", path, key))?;
+            }
+        }
+        ValueKind::Array(array) => {
+            for (i, value) in array.iter_mut().enumerate() {
+                load_secret_files(value, &format!("{}[{}].", path.trim_end_matches('.'), i))?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Trailing newlines, which most editors and `echo` add, are not part of the secret.
+fn read_secret_file(path: &Path) -> Result<String, anyhow::Error> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+
+    Ok(contents.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// The local configuration with `overlay` on top, without the environment variables `get_configuration`
+/// would read as well.
+#[cfg(test)]
+fn get_test_config(overlay: &str) -> Config {
+    Config::builder()
+        .add_source(config::File::with_name("configuration/base.yaml"))
+        .add_source(config::File::with_name("configuration/local.yaml"))
+        .add_source(config::File::from_str(overlay, config::FileFormat::Yaml))
+        .build()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::signing_key_promotion::SigningKeyPromotion;
+    use crate::service::{get_test_auth_options, AuthOptions, AuthService};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    #[test]
+    fn deserialize_settings_should_read_secrets_from_files() {
+        // Arrange
+
+        let file = std::env::temp_dir().join(format!("secret-{}", Uuid::new_v4()));
+        std::fs::write(&file, "from a file\n").unwrap();
+        let overlay = format!(
+            "database:\n  password: null\n  password_file: \"{}\"\nauthentication:\n  signing_keys:\n    - kid: \"2023-11-26\"\n      secret_key_file: \"{}\"",
+            file.display(),
+            file.display()
+        );
+
+        // Act
+
+        let actual = deserialize_settings(get_test_config(&overlay));
+
+        // Assert
+
+        std::fs::remove_file(&file).unwrap();
+        let actual = actual.unwrap();
+        assert_eq!(actual.database.password.expose_secret(), "from a file");
+        assert_eq!(
+            actual.authentication.signing_keys[0]
+                .secret_key
+                .as_ref()
+                .unwrap()
+                .expose_secret(),
+            "from a file"
+        );
+    }
+
+    #[test]
+    fn deserialize_settings_should_return_error_when_secret_and_its_file_are_both_set() {
+        // Arrange
+
+        let config =
+            get_test_config("database:\n  password_file: \"/run/secrets/database_password\"");
+
+        // Act
+
+        let actual = deserialize_settings(config);
+
+        // Assert
+
+        assert!(actual
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("`database.password` and `database.password_file`"));
+    }
+
+    #[test]
+    fn debug_should_not_reveal_secrets() {
+        // Arrange
+
+        let settings = deserialize_settings(get_test_config(
+            "database:\n  password: \"database password\"\nauthentication:\n  admin_api_key: \"admin api key\"",
+        ))
+        .unwrap();
+
+        // Act
+
+        let actual = format!("{:?}", settings);
+
+        // Assert
+
+        assert!(!actual.contains(
+            settings.authentication.signing_keys[0]
+                .secret_key
+                .as_ref()
+                .unwrap()
+                .expose_secret()
+        ));
+        assert!(!actual.contains(settings.database.password.expose_secret()));
+        assert!(!actual.contains(
+            settings
+                .authentication
+                .admin_api_key
+                .as_ref()
+                .unwrap()
+                .expose_secret()
+        ));
+        assert!(!actual.contains(settings.authentication.totp_encryption_key.expose_secret()));
+    }
+
+    #[test]
+    fn get_keyring_should_keep_retired_key_until_email_verification_tokens_expire() {
+        // Arrange
+
+        let settings = deserialize_settings(get_test_config(
+            "authentication:\n  token_expiration_in_seconds: 0\n  mfa_challenge_expiration_in_seconds: 0\n  signing_key_reload_interval_in_seconds: 0\n  signing_keys:\n    - kid: \"first\"\n      secret_key: \"first secret\"\n    - kid: \"second\"\n      secret_key: \"second secret\"\n  active_signing_key: \"first\"",
+        ))
+        .unwrap();
+        let keyring = settings.authentication.get_keyring().unwrap();
+        let auth_service = AuthService::new(AuthOptions {
+            keyring: keyring.clone(),
+            ..get_test_auth_options()
+        });
+        let token = auth_service
+            .generate_email_verification_token(Uuid::new_v4(), "john.doe@example.com".to_string())
+            .unwrap();
+        keyring.apply_promotions(&[SigningKeyPromotion {
+            kid: "second".to_string(),
+            promoted_at: Utc::now(),
+        }]);
+
+        // Act
+
+        let actual = auth_service.verify_email_verification_token(&token);
+
+        // Assert
+
+        assert!(actual.is_ok());
+    }
+
+    #[test]
+    fn get_connection_string_should_require_ssl_when_enabled() {
+        // Arrange
+
+        let mut settings = deserialize_settings(get_test_config("")).unwrap();
+        settings.database.require_ssl = true;
+
+        // Act
+
+        let actual = settings.database.get_connection_string();
+
+        // Assert
+
+        assert!(actual.expose_secret().ends_with("?sslmode=require"));
+    }
+}
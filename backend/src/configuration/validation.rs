@@ -0,0 +1,360 @@
+use super::{
+    ApplicationSettings, AuthenticationSettings, DatabaseBackendSettings, DatabaseSettings,
+    MailSettings, MailTransportSettings, Settings,
+};
+use crate::service::cipher::SecretCipher;
+use crate::tls::server::ReloadingCertificate;
+use jsonwebtoken::Algorithm;
+use lettre::message::Mailbox;
+use secrecy::ExposeSecret;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+/// Every problem found in the settings, so that they can all be fixed at once.
+#[derive(Debug)]
+pub struct InvalidSettings {
+    problems: Vec<String>,
+}
+
+impl InvalidSettings {
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+}
+
+impl Display for InvalidSettings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the configuration has {} problem(s):",
+            self.problems.len()
+        )?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidSettings {}
+
+#[derive(Default)]
+struct Problems(Vec<String>);
+
+impl Problems {
+    fn add(&mut self, field: &str, message: impl Display) {
+        self.0.push(format!("{}: {}", field, message));
+    }
+
+    fn check(&mut self, is_valid: bool, field: &str, message: impl Display) {
+        if !is_valid {
+            self.add(field, message);
+        }
+    }
+
+    fn check_not_empty(&mut self, value: &str, field: &str) {
+        self.check(!value.trim().is_empty(), field, "must not be empty");
+    }
+
+    fn check_port(&mut self, port: u16, field: &str) {
+        self.check(port != 0, field, "must be between 1 and 65535");
+    }
+
+    fn check_positive(&mut self, value: u64, field: &str) {
+        self.check(value > 0, field, "must be greater than 0");
+    }
+
+    fn check_url(&mut self, value: &str, field: &str) {
+        self.check(
+            value.starts_with("http://") || value.starts_with("https://"),
+            field,
+            "must be an http:// or https:// URL",
+        );
+    }
+}
+
+impl Settings {
+    /// Checks the values that deserialize fine but would make the service misbehave or fail
+    /// later on, e.g. a signing key that is too short or a token that expires immediately.
+    pub fn validate(&self) -> Result<(), InvalidSettings> {
+        let mut problems = Problems::default();
+
+        validate_application(&self.application, &mut problems);
+        validate_database(&self.database, &mut problems);
+        validate_authentication(&self.authentication, &mut problems);
+        validate_mail(&self.mail, &mut problems);
+
+        if problems.0.is_empty() {
+            return Ok(());
+        }
+
+        Err(InvalidSettings {
+            problems: problems.0,
+        })
+    }
+}
+
+fn validate_application(settings: &ApplicationSettings, problems: &mut Problems) {
+    problems.check_not_empty(&settings.host, "application.host");
+    problems.check_port(settings.port, "application.port");
+    problems.check_positive(
+        settings.max_hashing_tasks as u64,
+        "application.max_hashing_tasks",
+    );
+    problems.check_positive(settings.max_mail_tasks as u64, "application.max_mail_tasks");
+
+    if let Some(tls) = &settings.tls {
+        if let Err(e) = ReloadingCertificate::new(tls) {
+            problems.add("application.tls", format!("{:#}", e));
+        }
+        problems.check_positive(
+            tls.reload_interval_in_seconds,
+            "application.tls.reload_interval_in_seconds",
+        );
+        if let Some(redirect_http_port) = tls.redirect_http_port {
+            problems.check_port(redirect_http_port, "application.tls.redirect_http_port");
+            problems.check(
+                redirect_http_port != settings.port,
+                "application.tls.redirect_http_port",
+                "must not be the same as application.port",
+            );
+        }
+    }
+}
+
+fn validate_database(settings: &DatabaseSettings, problems: &mut Problems) {
+    match &settings.backend {
+        DatabaseBackendSettings::Postgres => {
+            problems.check_not_empty(&settings.host, "database.host");
+            problems.check_port(settings.port, "database.port");
+            problems.check_not_empty(&settings.database_name, "database.database_name");
+            problems.check_not_empty(&settings.username, "database.username");
+            problems.check_positive(settings.max_connections as u64, "database.max_connections");
+            if let Some(min_idle_connections) = settings.min_idle_connections {
+                problems.check(
+                    min_idle_connections <= settings.max_connections,
+                    "database.min_idle_connections",
+                    "must not be greater than max_connections",
+                );
+            }
+            problems.check_positive(
+                settings.connection_timeout_in_seconds,
+                "database.connection_timeout_in_seconds",
+            );
+            problems.check(
+                settings.require_ssl
+                    || (settings.ssl_root_cert_path.is_none()
+                        && settings.ssl_client_cert_path.is_none()
+                        && settings.ssl_client_key_path.is_none()),
+                "database.require_ssl",
+                "must be enabled for the ssl_* certificates to be used",
+            );
+            if let Err(e) = settings.get_tls_config() {
+                problems.add("database", format!("{:#}", e));
+            }
+        }
+        DatabaseBackendSettings::Sqlite { path } => {
+            problems.check_not_empty(path, "database.backend.path");
+        }
+    }
+}
+
+fn validate_authentication(settings: &AuthenticationSettings, problems: &mut Problems) {
+    problems.check(
+        !settings.signing_keys.is_empty(),
+        "authentication.signing_keys",
+        "at least one signing key must be configured",
+    );
+    problems.check(
+        settings
+            .signing_keys
+            .iter()
+            .any(|key| key.kid == settings.active_signing_key),
+        "authentication.active_signing_key",
+        format!(
+            "'{}' is not one of the signing keys",
+            settings.active_signing_key
+        ),
+    );
+
+    let mut kids = HashSet::new();
+    for (i, key) in settings.signing_keys.iter().enumerate() {
+        let field = format!("authentication.signing_keys[{}]", i);
+
+        problems.check_not_empty(&key.kid, &format!("{}.kid", field));
+        problems.check(
+            kids.insert(key.kid.as_str()),
+            &format!("{}.kid", field),
+            format!("'{}' is used by more than one key", key.kid),
+        );
+
+        if let Err(e) = key.get_signing_key() {
+            problems.add(&field, format!("{:#}", e));
+        } else if let (Some(secret_key), Some(min_length)) =
+            (&key.secret_key, get_min_secret_length(key.algorithm))
+        {
+            problems.check(
+                secret_key.expose_secret().len() >= min_length,
+                &format!("{}.secret_key", field),
+                format!(
+                    "must be at least {} bytes long for {:?}",
+                    min_length, key.algorithm
+                ),
+            );
+        }
+    }
+
+    if let Some(admin_api_key) = &settings.admin_api_key {
+        problems.check_not_empty(
+            admin_api_key.expose_secret(),
+            "authentication.admin_api_key",
+        );
+    }
+    if let Err(e) = SecretCipher::from_base64(settings.totp_encryption_key.expose_secret()) {
+        problems.add("authentication.totp_encryption_key", format!("{:#}", e));
+    }
+    if let Err(e) = settings.password_hashing.validate() {
+        problems.add("authentication.password_hashing", format!("{:#}", e));
+    }
+
+    problems.check_not_empty(&settings.audience, "authentication.audience");
+    problems.check_not_empty(&settings.totp_issuer, "authentication.totp_issuer");
+    problems.check_url(
+        &settings.password_reset_url,
+        "authentication.password_reset_url",
+    );
+    problems.check_url(
+        &settings.email_verification_url,
+        "authentication.email_verification_url",
+    );
+
+    problems.check_positive(
+        settings.token_expiration_in_seconds,
+        "authentication.token_expiration_in_seconds",
+    );
+    problems.check_positive(
+        settings.refresh_token_expiration_in_seconds,
+        "authentication.refresh_token_expiration_in_seconds",
+    );
+    problems.check(
+        settings.refresh_token_expiration_in_seconds >= settings.token_expiration_in_seconds,
+        "authentication.refresh_token_expiration_in_seconds",
+        "must not be shorter than token_expiration_in_seconds",
+    );
+    problems.check_positive(
+        settings.mfa_challenge_expiration_in_seconds,
+        "authentication.mfa_challenge_expiration_in_seconds",
+    );
+    problems.check_positive(
+        settings.password_reset_expiration_in_seconds,
+        "authentication.password_reset_expiration_in_seconds",
+    );
+    problems.check_positive(
+        settings.email_verification_expiration_in_seconds,
+        "authentication.email_verification_expiration_in_seconds",
+    );
+    problems.check_positive(
+        settings.signing_key_reload_interval_in_seconds,
+        "authentication.signing_key_reload_interval_in_seconds",
+    );
+}
+
+/// HMAC keys should be at least as long as the hash they are used with (RFC 7518, section 3.2).
+fn get_min_secret_length(algorithm: Algorithm) -> Option<usize> {
+    match algorithm {
+        Algorithm::HS256 => Some(32),
+        Algorithm::HS384 => Some(48),
+        Algorithm::HS512 => Some(64),
+        _ => None,
+    }
+}
+
+fn validate_mail(settings: &MailSettings, problems: &mut Problems) {
+    if let Err(e) = settings.from.parse::<Mailbox>() {
+        problems.add(
+            "mail.from",
+            format!("'{}' is not a valid sender: {}", settings.from, e),
+        );
+    }
+
+    match &settings.transport {
+        MailTransportSettings::Smtp {
+            host,
+            port,
+            username,
+            password,
+            ..
+        } => {
+            problems.check_not_empty(host, "mail.transport.host");
+            problems.check_port(*port, "mail.transport.port");
+            problems.check(
+                username.is_some() == password.is_some(),
+                "mail.transport",
+                "either both or none of username and password must be set",
+            );
+        }
+        MailTransportSettings::File { directory } => {
+            problems.check(
+                !directory.as_os_str().is_empty(),
+                "mail.transport.directory",
+                "must not be empty",
+            );
+        }
+        MailTransportSettings::InMemory => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{deserialize_settings, get_test_config, SigningKeySettings};
+
+    #[test]
+    fn validate_should_accept_local_configuration() {
+        // Arrange
+
+        let settings = deserialize_settings(get_test_config("")).unwrap();
+
+        // Act
+
+        let actual = settings.validate();
+
+        // Assert
+
+        assert!(actual.is_ok(), "{}", actual.unwrap_err());
+    }
+
+    #[test]
+    fn validate_should_collect_every_problem() {
+        // Arrange
+
+        let mut settings = deserialize_settings(get_test_config("")).unwrap();
+        settings.authentication.signing_keys = vec![SigningKeySettings {
+            kid: settings.authentication.active_signing_key.clone(),
+            algorithm: Algorithm::HS256,
+            secret_key: Some("abc".to_string().into()),
+            private_key_path: None,
+            public_key_path: None,
+        }];
+        settings.authentication.token_expiration_in_seconds = 0;
+        settings.authentication.audience = String::new();
+        settings.database.port = 0;
+
+        // Act
+
+        let actual = settings.validate().unwrap_err();
+
+        // Assert
+
+        assert_eq!(
+            actual.problems(),
+            [
+                "database.port: must be between 1 and 65535",
+                "authentication.signing_keys[0].secret_key: must be at least 32 bytes long for HS256",
+                "authentication.audience: must not be empty",
+                "authentication.token_expiration_in_seconds: must be greater than 0",
+            ]
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use crate::api::contracts;
 use crate::helpers;
 use actix_web::body::BoxBody;
+use actix_web::error::PathError;
 use actix_web::http::StatusCode;
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use log::warn;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -17,6 +19,36 @@ pub enum Error {
     EmailAlreadyExists,
     #[error("supplied credentials are invalid")]
     InvalidCredentials,
+    #[error("supplied current password is incorrect")]
+    IncorrectCurrentPassword,
+    #[error("password was changed concurrently")]
+    PasswordChangedConcurrently,
+    #[error("supplied refresh token is invalid")]
+    InvalidRefreshToken,
+    #[error("user's email is not verified")]
+    EmailNotVerified,
+    #[error("supplied email verification token is invalid")]
+    InvalidEmailVerificationToken,
+    #[error("supplied password reset token is invalid")]
+    InvalidPasswordResetToken,
+    #[error("supplied TOTP code is invalid")]
+    InvalidTotpCode,
+    #[error("supplied recovery code is invalid")]
+    InvalidRecoveryCode,
+    #[error("TOTP is already enabled")]
+    TotpAlreadyEnabled,
+    #[error("TOTP is not enrolled")]
+    TotpNotEnrolled,
+    #[error("caller is not allowed to perform this action")]
+    Forbidden,
+    #[error("signing key was not found")]
+    SigningKeyNotFound,
+    #[error("user was not found")]
+    UserNotFound,
+    #[error("role was not found")]
+    RoleNotFound,
+    #[error("path parameter is malformed")]
+    InvalidPathParameter,
     #[error("request validation failed")]
     Validation(#[from] helpers::ValidationError),
     #[error(transparent)]
@@ -29,6 +61,21 @@ impl ResponseError for ServerError {
             ServerError::Error(error) => match error {
                 Error::EmailAlreadyExists => StatusCode::CONFLICT,
                 Error::InvalidCredentials => StatusCode::UNAUTHORIZED,
+                Error::IncorrectCurrentPassword => StatusCode::FORBIDDEN,
+                Error::PasswordChangedConcurrently => StatusCode::CONFLICT,
+                Error::InvalidRefreshToken => StatusCode::UNAUTHORIZED,
+                Error::EmailNotVerified => StatusCode::FORBIDDEN,
+                Error::InvalidEmailVerificationToken => StatusCode::BAD_REQUEST,
+                Error::InvalidPasswordResetToken => StatusCode::BAD_REQUEST,
+                Error::InvalidTotpCode => StatusCode::UNAUTHORIZED,
+                Error::InvalidRecoveryCode => StatusCode::UNAUTHORIZED,
+                Error::TotpAlreadyEnabled => StatusCode::CONFLICT,
+                Error::TotpNotEnrolled => StatusCode::NOT_FOUND,
+                Error::Forbidden => StatusCode::FORBIDDEN,
+                Error::SigningKeyNotFound => StatusCode::NOT_FOUND,
+                Error::UserNotFound => StatusCode::NOT_FOUND,
+                Error::RoleNotFound => StatusCode::NOT_FOUND,
+                Error::InvalidPathParameter => StatusCode::BAD_REQUEST,
                 Error::Validation(_) => StatusCode::BAD_REQUEST,
                 Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             },
@@ -49,6 +96,51 @@ fn build_error_response(err: &ServerError) -> contracts::Response<()> {
             Error::InvalidCredentials => vec![contracts::Error {
                 message: "invalid credentials supplied".to_string(),
             }],
+            Error::IncorrectCurrentPassword => vec![contracts::Error {
+                message: "current password is incorrect".to_string(),
+            }],
+            Error::PasswordChangedConcurrently => vec![contracts::Error {
+                message: "password was changed in the meantime, please try again".to_string(),
+            }],
+            Error::InvalidRefreshToken => vec![contracts::Error {
+                message: "invalid refresh token supplied".to_string(),
+            }],
+            Error::EmailNotVerified => vec![contracts::Error {
+                message: "please verify your email before logging in".to_string(),
+            }],
+            Error::InvalidEmailVerificationToken => vec![contracts::Error {
+                message: "invalid or expired email verification token supplied".to_string(),
+            }],
+            Error::InvalidPasswordResetToken => vec![contracts::Error {
+                message: "invalid or expired password reset token supplied".to_string(),
+            }],
+            Error::InvalidTotpCode => vec![contracts::Error {
+                message: "invalid authentication code supplied".to_string(),
+            }],
+            Error::InvalidRecoveryCode => vec![contracts::Error {
+                message: "invalid recovery code supplied".to_string(),
+            }],
+            Error::TotpAlreadyEnabled => vec![contracts::Error {
+                message: "two-factor authentication is already enabled".to_string(),
+            }],
+            Error::TotpNotEnrolled => vec![contracts::Error {
+                message: "two-factor authentication is not set up".to_string(),
+            }],
+            Error::Forbidden => vec![contracts::Error {
+                message: "you are not allowed to perform this action".to_string(),
+            }],
+            Error::SigningKeyNotFound => vec![contracts::Error {
+                message: "signing key not found".to_string(),
+            }],
+            Error::UserNotFound => vec![contracts::Error {
+                message: "user not found".to_string(),
+            }],
+            Error::RoleNotFound => vec![contracts::Error {
+                message: "role not found".to_string(),
+            }],
+            Error::InvalidPathParameter => vec![contracts::Error {
+                message: "invalid path parameter supplied".to_string(),
+            }],
             Error::Validation(err) => err.get_validation_errors(),
             Error::Internal(_) => vec![contracts::Error {
                 message: "something went wrong".to_string(),
@@ -58,3 +150,10 @@ fn build_error_response(err: &ServerError) -> contracts::Response<()> {
 
     contracts::Response::<()>::err(errors)
 }
+
+/// Replaces actix's plain text response to a path that can't be deserialized, like a malformed
+/// UUID, with the usual error body. Registered through `web::PathConfig`.
+pub fn handle_path_error(err: PathError, req: &HttpRequest) -> actix_web::Error {
+    warn!("failed to parse path {}. reason: {}", req.path(), err);
+    ServerError::from(Error::InvalidPathParameter).into()
+}
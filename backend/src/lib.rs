@@ -1,9 +1,14 @@
 pub mod api;
+pub mod cli;
 pub mod configuration;
 pub mod errors;
 pub mod helpers;
+pub mod mail;
 pub mod middleware;
+pub mod migrations;
 pub mod models;
 pub mod repository;
 pub mod schema;
 pub mod service;
+pub mod startup;
+pub mod tls;
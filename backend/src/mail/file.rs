@@ -0,0 +1,74 @@
+use crate::mail::{build_message, Email, Mailer};
+use anyhow::Context;
+use lettre::message::Mailbox;
+use lettre::{FileTransport, Transport};
+use std::path::Path;
+
+/// Writes every email as an `.eml` file to a directory instead of delivering it.
+/// Meant for local development, as the files can be opened with any mail client.
+#[derive(Debug)]
+pub struct FileMailer {
+    from: Mailbox,
+    transport: FileTransport,
+}
+
+impl FileMailer {
+    pub fn new(from: Mailbox, directory: &Path) -> Result<Self, anyhow::Error> {
+        std::fs::create_dir_all(directory).with_context(|| {
+            format!("failed to create mail directory '{}'", directory.display())
+        })?;
+
+        Ok(Self {
+            from,
+            transport: FileTransport::new(directory),
+        })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, email: Email) -> Result<(), anyhow::Error> {
+        let message = build_message(&self.from, email)?;
+        self.transport
+            .send(&message)
+            .context("failed to write email to file")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn send_should_write_multipart_eml_file_to_directory() {
+        // Arrange
+
+        let directory = std::env::temp_dir().join(format!("mail-{}", Uuid::new_v4()));
+        let mailer =
+            FileMailer::new("task <no-reply@test.com>".parse().unwrap(), &directory).unwrap();
+
+        // Act
+
+        mailer
+            .send(Email {
+                to: "test@test.com".to_string(),
+                subject: "subject".to_string(),
+                html_body: "<p>html</p>".to_string(),
+                text_body: "text".to_string(),
+            })
+            .unwrap();
+
+        // Assert
+
+        let files: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+    }
+}
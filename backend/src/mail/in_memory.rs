@@ -0,0 +1,60 @@
+use crate::mail::{Email, Mailer};
+use std::sync::Mutex;
+
+/// Keeps every email in memory instead of delivering it. Meant for tests.
+#[derive(Debug, Default)]
+pub struct InMemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl InMemoryMailer {
+    pub fn get_sent(&self) -> Vec<Email> {
+        self.sent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl Mailer for InMemoryMailer {
+    fn send(&self, email: Email) -> Result<(), anyhow::Error> {
+        self.sent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mail::templates::MailTemplate;
+
+    #[test]
+    fn send_template_should_keep_rendered_email() {
+        // Arrange
+
+        let mailer = InMemoryMailer::default();
+
+        // Act
+
+        mailer
+            .send_template(
+                "test@test.com",
+                MailTemplate::VerifyEmail {
+                    name: "test".to_string(),
+                    link: "http://localhost/verify-email?token=abc".to_string(),
+                    expires_in_hours: 24,
+                },
+            )
+            .unwrap();
+
+        // Assert
+
+        let sent = mailer.get_sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "test@test.com");
+        assert_eq!(sent[0].subject, "Verify your email");
+    }
+}
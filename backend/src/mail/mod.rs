@@ -0,0 +1,45 @@
+use crate::mail::templates::MailTemplate;
+use anyhow::Context;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+use std::fmt::Debug;
+
+pub mod file;
+pub mod in_memory;
+pub mod smtp;
+pub mod templates;
+
+/// A rendered email with an HTML and a plain-text part.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+/// Delivers the emails the application sends, e.g. password reset links.
+pub trait Mailer: Debug + Send + Sync {
+    fn send(&self, email: Email) -> Result<(), anyhow::Error>;
+
+    fn send_template(&self, to: &str, template: MailTemplate) -> Result<(), anyhow::Error> {
+        self.send(template.render(to)?)
+    }
+}
+
+fn build_message(from: &Mailbox, email: Email) -> Result<Message, anyhow::Error> {
+    let to = email
+        .to
+        .parse::<Mailbox>()
+        .with_context(|| format!("'{}' is not a valid recipient", email.to))?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject)
+        .multipart(MultiPart::alternative_plain_html(
+            email.text_body,
+            email.html_body,
+        ))
+        .context("failed to build email")
+}
@@ -0,0 +1,63 @@
+use crate::mail::{build_message, Email, Mailer};
+use anyhow::Context;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plain text, e.g. for a relay on localhost.
+    None,
+    /// Upgrades a plain text connection, usually on port 587.
+    StartTls,
+    /// Connects over TLS right away, usually on port 465.
+    #[default]
+    Tls,
+}
+
+/// Delivers emails through an SMTP server.
+#[derive(Debug)]
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: SmtpTransport,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        from: Mailbox,
+        host: &str,
+        port: u16,
+        tls: SmtpTls,
+        credentials: Option<Credentials>,
+    ) -> Result<Self, anyhow::Error> {
+        let builder = match tls {
+            SmtpTls::None => SmtpTransport::builder_dangerous(host),
+            SmtpTls::StartTls => SmtpTransport::starttls_relay(host)
+                .context("failed to configure STARTTLS for SMTP")?,
+            SmtpTls::Tls => {
+                SmtpTransport::relay(host).context("failed to configure TLS for SMTP")?
+            }
+        };
+
+        let builder = builder.port(port);
+        let transport = match credentials {
+            Some(credentials) => builder.credentials(credentials).build(),
+            None => builder.build(),
+        };
+
+        Ok(Self { from, transport })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: Email) -> Result<(), anyhow::Error> {
+        let message = build_message(&self.from, email)?;
+        self.transport
+            .send(&message)
+            .context("failed to send email over SMTP")?;
+
+        Ok(())
+    }
+}
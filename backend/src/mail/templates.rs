@@ -0,0 +1,151 @@
+use crate::mail::Email;
+use anyhow::Context;
+use minijinja::{context, Environment, Value};
+use serde::Serialize;
+use std::sync::OnceLock;
+
+static TEMPLATES: OnceLock<Environment<'static>> = OnceLock::new();
+
+/// Every email the application sends. Each one is rendered from `templates/mail/<name>.html`
+/// and `templates/mail/<name>.txt`, with the fields of the variant available in both.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum MailTemplate {
+    VerifyEmail {
+        name: String,
+        link: String,
+        expires_in_hours: u64,
+    },
+    PasswordReset {
+        name: String,
+        link: String,
+        expires_in_minutes: u64,
+    },
+}
+
+impl MailTemplate {
+    pub fn render(&self, to: &str) -> Result<Email, anyhow::Error> {
+        let subject = self.get_subject();
+        let context = context! { subject, ..Value::from_serialize(self) };
+
+        let render = |extension: &str| {
+            let name = format!("{}.{}", self.get_name(), extension);
+            get_templates()
+                .get_template(&name)
+                .and_then(|template| template.render(&context))
+                .with_context(|| format!("failed to render mail template '{}'", name))
+        };
+
+        Ok(Email {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            html_body: render("html")?,
+            text_body: render("txt")?,
+        })
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            MailTemplate::VerifyEmail { .. } => "verify_email",
+            MailTemplate::PasswordReset { .. } => "password_reset",
+        }
+    }
+
+    fn get_subject(&self) -> &'static str {
+        match self {
+            MailTemplate::VerifyEmail { .. } => "Verify your email",
+            MailTemplate::PasswordReset { .. } => "Reset your password",
+        }
+    }
+}
+
+/// Templates are compiled into the binary, so a broken one is caught by the tests below
+/// rather than at runtime. `.html` templates escape their values.
+fn get_templates() -> &'static Environment<'static> {
+    TEMPLATES.get_or_init(|| {
+        let mut env = Environment::new();
+        let templates = [
+            (
+                "layout.html",
+                include_str!("../../templates/mail/layout.html"),
+            ),
+            (
+                "verify_email.html",
+                include_str!("../../templates/mail/verify_email.html"),
+            ),
+            (
+                "verify_email.txt",
+                include_str!("../../templates/mail/verify_email.txt"),
+            ),
+            (
+                "password_reset.html",
+                include_str!("../../templates/mail/password_reset.html"),
+            ),
+            (
+                "password_reset.txt",
+                include_str!("../../templates/mail/password_reset.txt"),
+            ),
+        ];
+        for (name, source) in templates {
+            env.add_template(name, source)
+                .expect("templates.rs - mail template is invalid");
+        }
+
+        env
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_should_render_html_and_plain_text_parts() {
+        // Arrange
+
+        let template = MailTemplate::PasswordReset {
+            name: "test".to_string(),
+            link: "http://localhost/reset-password?token=abc".to_string(),
+            expires_in_minutes: 60,
+        };
+
+        // Act
+
+        let actual = template.render("test@test.com").unwrap();
+
+        // Assert
+
+        assert_eq!(actual.to, "test@test.com");
+        assert_eq!(actual.subject, "Reset your password");
+        assert!(actual.html_body.contains("token=abc\">Reset password</a>"));
+        assert!(actual
+            .html_body
+            .contains("<title>Reset your password</title>"));
+        assert!(actual
+            .text_body
+            .contains("http://localhost/reset-password?token=abc"));
+        assert!(actual.text_body.contains("60 minutes"));
+    }
+
+    #[test]
+    fn render_should_escape_values_in_html_part_only() {
+        // Arrange
+
+        let template = MailTemplate::VerifyEmail {
+            name: "<b>test</b>".to_string(),
+            link: "http://localhost/verify-email?token=abc".to_string(),
+            expires_in_hours: 24,
+        };
+
+        // Act
+
+        let actual = template.render("test@test.com").unwrap();
+
+        // Assert
+
+        assert!(actual
+            .html_body
+            .contains("Hi &lt;b&gt;test&lt;&#x2f;b&gt;,"));
+        assert!(actual.text_body.contains("Hi <b>test</b>,"));
+    }
+}
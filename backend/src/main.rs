@@ -1,63 +1,122 @@
-use actix_web::middleware::Logger;
-use actix_web::web::Data;
-use actix_web::{web, App, HttpServer};
-use backend::api::routes::{get_users, login};
-use backend::middleware::requires_authentication::RequiresAuthentication;
-use backend::service::{AuthOptions, AuthService};
-use backend::{api::routes::register, configuration, service::UserService};
-use diesel::r2d2::{ConnectionManager, Pool};
-use diesel::PgConnection;
+use backend::cli::{Command, RoleCommand};
+use backend::configuration::{self, DatabaseSettings, Settings};
+use backend::migrations::{self, MigrationCommand};
+use backend::service::blocking::BlockingPool;
+use backend::service::UserService;
+use backend::startup::Application;
 use env_logger::Env;
 use log::info;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let configuration =
-        configuration::get_configuration().expect("main.rs - unable to read configuration file.");
-    let database_url = configuration.database.get_connection_string();
+    let command = match Command::parse(std::env::args().skip(1)) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
 
+    let configuration = match configuration::get_configuration() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("unable to read the configuration. {}", e);
+            std::process::exit(1);
+        }
+    };
     env_logger::init_from_env(Env::default().default_filter_or("info"));
 
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    let db_pool = Pool::builder()
-        .build(manager)
-        .expect("main.rs - cannot build connection pool");
-
-    let user_service = UserService::new(db_pool);
-    let auth_service = AuthService::new(AuthOptions {
-        encoding_key: configuration.authentication.secret_key,
-        audience: configuration.authentication.audience,
-        token_expiration_in_seconds: configuration.authentication.token_expiration_in_seconds,
-    });
-
-    info!(
-        "starting server on {}:{}",
-        configuration.application.host, configuration.application.port
-    );
-
-    HttpServer::new(move || {
-        App::new()
-            .wrap(Logger::default())
-            .service(
-                web::scope("/api")
-                    .service(
-                        web::scope("users")
-                            .wrap(RequiresAuthentication)
-                            .route("/", web::get().to(get_users)),
-                    )
-                    .service(
-                        web::scope("auth")
-                            .route("/register", web::post().to(register))
-                            .route("/login", web::post().to(login)),
-                    ),
-            )
-            .app_data(Data::new(user_service.clone()))
-            .app_data(Data::new(auth_service.clone()))
-    })
-    .bind((
-        configuration.application.host,
-        configuration.application.port,
-    ))?
-    .run()
-    .await
+    match command {
+        Command::Migrate(migration_command) => {
+            return migrate(&configuration.database, migration_command).await;
+        }
+        Command::CheckConfig => return check_config(&configuration),
+        Command::Roles(role_command) => return change_roles(&configuration, role_command).await,
+        Command::Serve => {}
+    }
+
+    if let Err(e) = configuration.validate() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    let application = Application::build(configuration)
+        .await
+        .expect("main.rs - unable to build application");
+
+    info!("starting server on {}", application.address());
+    if let Some(redirect_address) = application.redirect_address() {
+        info!("redirecting plain HTTP on {} to HTTPS", redirect_address);
+    }
+
+    application.run_until_stopped().await
+}
+
+async fn migrate(settings: &DatabaseSettings, command: MigrationCommand) -> std::io::Result<()> {
+    match migrations::run(settings, command).await {
+        Ok(migrations) => {
+            for migration in migrations {
+                println!("{}", migration);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn check_config(configuration: &Settings) -> std::io::Result<()> {
+    match configuration.validate() {
+        Ok(()) => {
+            println!("the configuration is valid");
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn change_roles(configuration: &Settings, command: RoleCommand) -> std::io::Result<()> {
+    let result = async {
+        let (repository, revocation_store) = configuration.database.get_repositories().await?;
+        let user_service = UserService::new(
+            repository,
+            revocation_store,
+            configuration.mail.get_mailer()?,
+            BlockingPool::new(configuration.application.max_hashing_tasks),
+            BlockingPool::new(configuration.application.max_mail_tasks),
+        );
+
+        let message = match command {
+            RoleCommand::Grant { email, role } => {
+                match user_service.grant_role(&email, &role).await? {
+                    true => format!("granted role '{}' to {}", role, email),
+                    false => format!("{} already has role '{}'", email, role),
+                }
+            }
+            RoleCommand::Revoke { email, role } => {
+                match user_service.revoke_role(&email, &role).await? {
+                    true => format!("revoked role '{}' from {} and signed them out", role, email),
+                    false => format!("{} does not have role '{}'", email, role),
+                }
+            }
+        };
+
+        Ok::<_, anyhow::Error>(message)
+    };
+
+    match result.await {
+        Ok(message) => {
+            println!("{}", message);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    }
 }
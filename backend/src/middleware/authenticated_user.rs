@@ -0,0 +1,125 @@
+use crate::errors::{Error, ServerError};
+use crate::models::claims::Claims;
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use log::error;
+use uuid::Uuid;
+
+/// The caller of a route behind `RequiresAuthentication`, as verified from their access token.
+/// Taking it as a handler argument anywhere else fails the request with `401 Unauthorized`.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    claims: Claims,
+}
+
+impl AuthenticatedUser {
+    pub fn id(&self) -> Uuid {
+        self.claims.sub
+    }
+
+    pub fn claims(&self) -> &Claims {
+        &self.claims
+    }
+
+    pub fn into_claims(self) -> Claims {
+        self.claims
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = ServerError;
+    type Future = futures::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let result = match req.extensions().get::<Claims>() {
+            Some(claims) => Ok(Self {
+                claims: claims.clone(),
+            }),
+            None => {
+                error!(
+                    "{} takes AuthenticatedUser, but is not wrapped by RequiresAuthentication",
+                    req.path()
+                );
+                Err(Error::InvalidCredentials.into())
+            }
+        };
+
+        futures::future::ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::contracts;
+    use crate::middleware::requires_authentication::RequiresAuthentication;
+    use crate::service::get_test_auth_options;
+    use crate::service::revocation::{InMemoryRevocationStore, RevocationStore};
+    use crate::service::AuthService;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::{http, test, web, App, HttpResponse};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn authenticated_user_should_return_unauthorized_when_not_wrapped_by_requires_authentication(
+    ) {
+        // Arrange
+
+        let app =
+            test::init_service(App::new().route("/", web::get().to(get_authenticated_user_id)))
+                .await;
+
+        // Act
+
+        let actual = TestRequest::get().send_request(&app).await;
+
+        // Assert
+
+        assert_eq!(actual.status(), StatusCode::UNAUTHORIZED);
+        let body: contracts::Response<()> = test::read_body_json(actual).await;
+        assert_eq!(
+            body.errors.unwrap().first().unwrap().message,
+            "invalid credentials supplied"
+        );
+    }
+
+    #[actix_web::test]
+    async fn authenticated_user_should_return_user_of_verified_token() {
+        // Arrange
+
+        let auth_service = AuthService::new(get_test_auth_options());
+        let user_id = Uuid::new_v4();
+        let token = auth_service
+            .generate_token(user_id, Uuid::new_v4(), Vec::new())
+            .unwrap();
+        let revocation_store: Arc<dyn RevocationStore> =
+            Arc::new(InMemoryRevocationStore::default());
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequiresAuthentication)
+                .route("/", web::get().to(get_authenticated_user_id))
+                .app_data(web::Data::new(auth_service))
+                .app_data(web::Data::from(revocation_store)),
+        )
+        .await;
+
+        // Act
+
+        let actual = TestRequest::get()
+            .append_header((http::header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+
+        // Assert
+
+        assert_eq!(actual.status(), StatusCode::OK);
+        let body = test::read_body(actual).await;
+        assert_eq!(body, user_id.to_string());
+    }
+
+    async fn get_authenticated_user_id(user: AuthenticatedUser) -> HttpResponse {
+        HttpResponse::Ok().body(user.id().to_string())
+    }
+}
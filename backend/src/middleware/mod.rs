@@ -1 +1,36 @@
+use crate::api::contracts;
+use actix_web::error::InternalError;
+use actix_web::HttpResponse;
+use anyhow::anyhow;
+
+pub mod authenticated_user;
 pub mod requires_authentication;
+pub mod requires_role;
+
+fn unauthorized(error_message: String) -> actix_web::Error {
+    let res =
+        HttpResponse::Unauthorized().json(contracts::Response::<()>::err(vec![contracts::Error {
+            message: error_message.to_string(),
+        }]));
+    let e = anyhow!(error_message.to_string());
+    InternalError::from_response(e, res).into()
+}
+
+fn forbidden(error_message: String) -> actix_web::Error {
+    let res =
+        HttpResponse::Forbidden().json(contracts::Response::<()>::err(vec![contracts::Error {
+            message: error_message.to_string(),
+        }]));
+    let e = anyhow!(error_message.to_string());
+    InternalError::from_response(e, res).into()
+}
+
+fn internal_server_error(error_message: String) -> actix_web::Error {
+    let res = HttpResponse::InternalServerError().json(contracts::Response::<()>::err(vec![
+        contracts::Error {
+            message: error_message.to_string(),
+        },
+    ]));
+    let e = anyhow!(error_message.to_string());
+    InternalError::from_response(e, res).into()
+}
@@ -1,11 +1,11 @@
-use crate::api::contracts;
+use super::{internal_server_error, unauthorized};
+use crate::service::revocation::RevocationStore;
 use crate::service::AuthService;
 use actix_service::{Service, Transform};
 use actix_web::body::{EitherBody, MessageBody};
 use actix_web::dev::{ServiceRequest, ServiceResponse};
-use actix_web::error::InternalError;
 use actix_web::http::header::HeaderMap;
-use actix_web::{web, Error, HttpResponse};
+use actix_web::{web, Error, HttpMessage};
 use anyhow::anyhow;
 use std::future::Future;
 use std::pin::Pin;
@@ -56,13 +56,44 @@ where
                 }
             };
 
-            match auth_service.verify_token(token) {
-                Ok(_) => service
-                    .call(req)
-                    .await
-                    .map(ServiceResponse::map_into_left_body),
-                Err(e) => Ok(req
-                    .error_response::<actix_web::Error>(unauthorized(e.to_string()))
+            let token_data = match auth_service.verify_token(token) {
+                Ok(t) => t,
+                Err(e) => {
+                    return Ok(req
+                        .error_response::<actix_web::Error>(unauthorized(e.to_string()))
+                        .map_into_right_body())
+                }
+            };
+
+            let revocation_store = match req.app_data::<web::Data<dyn RevocationStore>>() {
+                None => {
+                    return Ok(req
+                        .error_response::<actix_web::Error>(internal_server_error(
+                            "internal server error".to_string(),
+                        ))
+                        .map_into_right_body());
+                }
+                Some(s) => s,
+            };
+
+            match revocation_store.is_revoked(&token_data.claims).await {
+                Ok(false) => {
+                    // handed on to the guards and handlers behind this middleware
+                    req.extensions_mut().insert(token_data.claims);
+                    service
+                        .call(req)
+                        .await
+                        .map(ServiceResponse::map_into_left_body)
+                }
+                Ok(true) => Ok(req
+                    .error_response::<actix_web::Error>(unauthorized(
+                        "token has been revoked".to_string(),
+                    ))
+                    .map_into_right_body()),
+                Err(_) => Ok(req
+                    .error_response::<actix_web::Error>(internal_server_error(
+                        "internal server error".to_string(),
+                    ))
                     .map_into_right_body()),
             }
         })
@@ -104,33 +135,17 @@ fn parse_auth_token_from_header(headers: &HeaderMap) -> Result<String, anyhow::E
     }
 }
 
-fn unauthorized(error_message: String) -> actix_web::Error {
-    let res =
-        HttpResponse::Unauthorized().json(contracts::Response::<()>::err(vec![contracts::Error {
-            message: error_message.to_string(),
-        }]));
-    let e = anyhow!(error_message.to_string());
-    InternalError::from_response(e, res).into()
-}
-
-fn internal_server_error(error_message: String) -> actix_web::Error {
-    let res = HttpResponse::InternalServerError().json(contracts::Response::<()>::err(vec![
-        contracts::Error {
-            message: error_message.to_string(),
-        },
-    ]));
-    let e = anyhow!(error_message.to_string());
-    InternalError::from_response(e, res).into()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::service::AuthOptions;
+    use crate::api::contracts;
+    use crate::service::get_test_auth_options;
+    use crate::service::revocation::InMemoryRevocationStore;
     use actix_web::body::BoxBody;
     use actix_web::http::StatusCode;
     use actix_web::test::TestRequest;
-    use actix_web::{http, test, App};
+    use actix_web::{http, test, App, HttpResponse};
+    use std::sync::Arc;
     use uuid::Uuid;
 
     #[actix_web::test]
@@ -226,11 +241,12 @@ mod tests {
     }
 
     #[actix_web::test]
-    async fn requires_authentication_should_succeed_when_valid_token_provided() {
+    async fn requires_authentication_should_return_internal_server_error_when_unable_to_resolve_revocation_store(
+    ) {
         // Arrange
 
         let auth_service = get_test_auth_service();
-        let token = match auth_service.generate_token(Uuid::new_v4()) {
+        let token = match auth_service.generate_token(Uuid::new_v4(), Uuid::new_v4(), Vec::new()) {
             Ok(t) => t,
             Err(_) => panic!(),
         };
@@ -252,15 +268,83 @@ mod tests {
 
         // Assert
 
+        assert_eq!(actual.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        deserialize_body_and_match_error(actual, "internal server error").await;
+    }
+
+    #[actix_web::test]
+    async fn requires_authentication_should_return_unauthorized_when_token_was_revoked() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+        let token = match auth_service.generate_token(Uuid::new_v4(), Uuid::new_v4(), Vec::new()) {
+            Ok(t) => t,
+            Err(_) => panic!(),
+        };
+
+        let revocation_store = get_test_revocation_store();
+        let claims = auth_service.verify_token(token.clone()).unwrap().claims;
+        revocation_store.revoke_token(&claims).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequiresAuthentication)
+                .route("/", web::get().to(HttpResponse::Ok))
+                .app_data(web::Data::new(auth_service.clone()))
+                .app_data(web::Data::from(revocation_store)),
+        )
+        .await;
+
+        // Act
+
+        let actual = TestRequest::get()
+            .append_header((http::header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+
+        // Assert
+
+        assert_eq!(actual.status(), StatusCode::UNAUTHORIZED);
+        deserialize_body_and_match_error(actual, "token has been revoked").await;
+    }
+
+    #[actix_web::test]
+    async fn requires_authentication_should_succeed_when_valid_token_provided() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+        let token = match auth_service.generate_token(Uuid::new_v4(), Uuid::new_v4(), Vec::new()) {
+            Ok(t) => t,
+            Err(_) => panic!(),
+        };
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequiresAuthentication)
+                .route("/", web::get().to(HttpResponse::Ok))
+                .app_data(web::Data::new(auth_service.clone()))
+                .app_data(web::Data::from(get_test_revocation_store())),
+        )
+        .await;
+
+        // Act
+
+        let actual = TestRequest::get()
+            .append_header((http::header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+
+        // Assert
+
         assert_eq!(actual.status(), StatusCode::OK);
     }
 
     fn get_test_auth_service() -> AuthService {
-        AuthService::new(AuthOptions {
-            encoding_key: "test".to_string(),
-            audience: "test".to_string(),
-            token_expiration_in_seconds: 100,
-        })
+        AuthService::new(get_test_auth_options())
+    }
+
+    fn get_test_revocation_store() -> Arc<dyn RevocationStore> {
+        Arc::new(InMemoryRevocationStore::default())
     }
 
     async fn deserialize_body_and_match_error(
@@ -269,6 +353,6 @@ mod tests {
     ) {
         let body: contracts::Response<()> = test::read_body_json(response).await;
 
-        assert_eq!(body.errors.unwrap().get(0).unwrap().message, message);
+        assert_eq!(body.errors.unwrap().first().unwrap().message, message);
     }
 }
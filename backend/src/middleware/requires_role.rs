@@ -0,0 +1,200 @@
+use super::{forbidden, internal_server_error};
+use crate::models::claims::Claims;
+use actix_service::{Service, Transform};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::{Error, HttpMessage};
+use log::{error, warn};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+pub struct RoleMiddleware<S> {
+    service: Rc<S>,
+    role: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for RoleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let role = self.role;
+        Box::pin(async move {
+            let has_role = req
+                .extensions()
+                .get::<Claims>()
+                .map(|claims| claims.has_role(role));
+
+            match has_role {
+                Some(true) => service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body),
+                Some(false) => {
+                    warn!("caller of {} is missing role '{}'", req.path(), role);
+                    Ok(req
+                        .error_response::<actix_web::Error>(forbidden(
+                            "you are not allowed to perform this action".to_string(),
+                        ))
+                        .map_into_right_body())
+                }
+                None => {
+                    error!("RequiresRole has to be wrapped by RequiresAuthentication");
+                    Ok(req
+                        .error_response::<actix_web::Error>(internal_server_error(
+                            "internal server error".to_string(),
+                        ))
+                        .map_into_right_body())
+                }
+            }
+        })
+    }
+}
+
+/// Only lets through users whose access token carries the role. It reads the claims verified by
+/// `RequiresAuthentication`, so it has to be wrapped by it, i.e. added with `wrap` before it.
+pub struct RequiresRole(pub &'static str);
+
+impl<S, B> Transform<S, ServiceRequest> for RequiresRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RoleMiddleware<S>;
+    type InitError = ();
+    type Future = futures::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        futures::future::ok(RoleMiddleware {
+            service: Rc::new(service),
+            role: self.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::contracts;
+    use crate::middleware::requires_authentication::RequiresAuthentication;
+    use crate::service::get_test_auth_options;
+    use crate::service::revocation::{InMemoryRevocationStore, RevocationStore};
+    use crate::service::AuthService;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::{http, test, web, App, HttpResponse};
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn requires_role_should_return_internal_server_error_when_not_wrapped_by_requires_authentication(
+    ) {
+        // Arrange
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequiresRole("admin"))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // Act
+
+        let actual = TestRequest::get().send_request(&app).await;
+
+        // Assert
+
+        assert_eq!(actual.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[actix_web::test]
+    async fn requires_role_should_return_forbidden_when_user_does_not_have_role() {
+        // Arrange
+
+        let auth_service = AuthService::new(get_test_auth_options());
+        let token = auth_service
+            .generate_token(Uuid::new_v4(), Uuid::new_v4(), vec!["support".to_string()])
+            .unwrap();
+        let revocation_store: Arc<dyn RevocationStore> =
+            Arc::new(InMemoryRevocationStore::default());
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequiresRole("admin"))
+                .wrap(RequiresAuthentication)
+                .route("/", web::get().to(HttpResponse::Ok))
+                .app_data(web::Data::new(auth_service))
+                .app_data(web::Data::from(revocation_store)),
+        )
+        .await;
+
+        // Act
+
+        let actual = TestRequest::get()
+            .append_header((http::header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+
+        // Assert
+
+        assert_eq!(actual.status(), StatusCode::FORBIDDEN);
+        let body: contracts::Response<()> = test::read_body_json(actual).await;
+        assert_eq!(
+            body.errors.unwrap().first().unwrap().message,
+            "you are not allowed to perform this action"
+        );
+    }
+
+    #[actix_web::test]
+    async fn requires_role_should_succeed_when_user_has_role() {
+        // Arrange
+
+        let auth_service = AuthService::new(get_test_auth_options());
+        let token = auth_service
+            .generate_token(
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+                vec!["admin".to_string(), "support".to_string()],
+            )
+            .unwrap();
+        let revocation_store: Arc<dyn RevocationStore> =
+            Arc::new(InMemoryRevocationStore::default());
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequiresRole("admin"))
+                .wrap(RequiresAuthentication)
+                .route("/", web::get().to(HttpResponse::Ok))
+                .app_data(web::Data::new(auth_service))
+                .app_data(web::Data::from(revocation_store)),
+        )
+        .await;
+
+        // Act
+
+        let actual = TestRequest::get()
+            .append_header((http::header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+
+        // Assert
+
+        assert_eq!(actual.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::configuration::{DatabaseBackendSettings, DatabaseSettings};
+use anyhow::{anyhow, Context};
+use diesel::backend::Backend;
+use diesel::migration::{MigrationSource, MigrationVersion};
+use diesel::sql_types::BigInt;
+use diesel::RunQueryDsl;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use diesel_async::AsyncPgConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const POSTGRES_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+#[cfg(feature = "sqlite")]
+pub(crate) const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations_sqlite");
+
+/// Key of the Postgres advisory lock held while migrating, so that instances starting at the same
+/// time apply the migrations one after another instead of racing each other.
+const MIGRATION_LOCK_KEY: i64 = 0x7461_736b_6d69_6772;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationCommand {
+    /// Applies every pending migration.
+    Up,
+    /// Reverts the most recently applied migration.
+    Down,
+    /// Lists every migration together with whether it was applied.
+    Status,
+}
+
+impl FromStr for MigrationCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(Self::Up),
+            "down" => Ok(Self::Down),
+            "status" => Ok(Self::Status),
+            _ => Err(anyhow!(
+                "unknown migration command '{}', expected up, down or status",
+                s
+            )),
+        }
+    }
+}
+
+pub struct MigrationStatus {
+    pub name: String,
+    pub is_applied: bool,
+}
+
+impl Display for MigrationStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = if self.is_applied {
+            "applied"
+        } else {
+            "pending"
+        };
+        write!(f, "{} {}", state, self.name)
+    }
+}
+
+/// Runs `command` against the configured database. Returns the migrations that were applied or
+/// reverted, or every migration for `MigrationCommand::Status`.
+pub async fn run(
+    settings: &DatabaseSettings,
+    command: MigrationCommand,
+) -> Result<Vec<MigrationStatus>, anyhow::Error> {
+    // diesel only migrates through synchronous connections, so the migrations run on the blocking pool
+    let result = match &settings.backend {
+        DatabaseBackendSettings::Postgres => {
+            // connected beforehand and wrapped, so that it is set up with TLS like the pooled connections
+            let connection = settings.establish_connection().await?;
+            actix_web::web::block(move || {
+                run_on_postgres(&mut AsyncConnectionWrapper::from(connection), command)
+            })
+            .await
+        }
+        #[cfg(feature = "sqlite")]
+        DatabaseBackendSettings::Sqlite { path } => {
+            let path = path.clone();
+            actix_web::web::block(move || {
+                use diesel::Connection;
+
+                let mut conn = diesel::SqliteConnection::establish(&path)
+                    .with_context(|| format!("failed to open SQLite database '{}'", path))?;
+                execute(&mut conn, SQLITE_MIGRATIONS, command)
+            })
+            .await
+        }
+        #[cfg(not(feature = "sqlite"))]
+        DatabaseBackendSettings::Sqlite { .. } => {
+            return Err(anyhow!(
+                "the sqlite backend is only available when built with `--features sqlite`"
+            ))
+        }
+    };
+
+    result.context("failed to run migrations on the blocking pool")?
+}
+
+fn run_on_postgres(
+    conn: &mut AsyncConnectionWrapper<AsyncPgConnection>,
+    command: MigrationCommand,
+) -> Result<Vec<MigrationStatus>, anyhow::Error> {
+    diesel::sql_query("SELECT pg_advisory_lock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK_KEY)
+        .execute(conn)
+        .context("failed to acquire the migration lock")?;
+
+    let result = execute(conn, POSTGRES_MIGRATIONS, command);
+
+    // the lock is released with the connection anyway, unlocking just doesn't wait for that
+    diesel::sql_query("SELECT pg_advisory_unlock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK_KEY)
+        .execute(conn)
+        .context("failed to release the migration lock")?;
+
+    result
+}
+
+fn execute<DB: Backend>(
+    conn: &mut impl MigrationHarness<DB>,
+    migrations: EmbeddedMigrations,
+    command: MigrationCommand,
+) -> Result<Vec<MigrationStatus>, anyhow::Error> {
+    let all_migrations = MigrationSource::<DB>::migrations(&migrations)
+        .map_err(|e| anyhow!(e))
+        .context("failed to load embedded migrations")?;
+    let get_status = |version: &MigrationVersion, is_applied: bool| MigrationStatus {
+        name: all_migrations
+            .iter()
+            .find(|m| m.name().version() == *version)
+            .map_or_else(|| version.to_string(), |m| m.name().to_string()),
+        is_applied,
+    };
+
+    match command {
+        MigrationCommand::Up => {
+            let applied = conn
+                .run_pending_migrations(migrations)
+                .map_err(|e| anyhow!(e))
+                .context("failed to apply migrations")?;
+
+            Ok(applied.iter().map(|v| get_status(v, true)).collect())
+        }
+        MigrationCommand::Down => {
+            let reverted = conn
+                .revert_last_migration(migrations)
+                .map_err(|e| anyhow!(e))
+                .context("failed to revert the last migration")?;
+
+            Ok(vec![get_status(&reverted, false)])
+        }
+        MigrationCommand::Status => {
+            let applied = conn
+                .applied_migrations()
+                .map_err(|e| anyhow!(e))
+                .context("failed to read applied migrations")?;
+
+            Ok(all_migrations
+                .iter()
+                .map(|m| {
+                    let version = m.name().version();
+                    get_status(&version, applied.contains(&version))
+                })
+                .collect())
+        }
+    }
+}
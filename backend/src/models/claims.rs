@@ -1,10 +1,77 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    /// `iat` in microseconds. Whole seconds are too coarse to tell a token issued right after a
+    /// revocation from one issued before it. Tokens issued before it existed don't have it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat_us: Option<i64>,
+    pub sub: Uuid,
+    pub jti: Uuid,
+    pub sid: Uuid,
+    /// The roles the user had when the token was issued. Tokens issued before roles existed have none.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// Claims of the short-lived token handed out between the password and the TOTP step of a login.
+/// It uses its own audience, so it is never accepted as an access token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaChallengeClaims {
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    /// `iat` in microseconds, see `Claims::iat_us`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat_us: Option<i64>,
+    pub sub: Uuid,
+    pub jti: Uuid,
+}
+
+/// Claims of the signed link that confirms a user owns their email address.
+/// The link stops working once the user's email changes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailVerificationClaims {
     pub aud: String,
     pub exp: i64,
     pub iat: i64,
     pub sub: Uuid,
+    pub email: String,
+}
+
+impl Claims {
+    pub fn issued_at(&self) -> DateTime<Utc> {
+        get_issued_at(self.iat, self.iat_us)
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.exp, 0).unwrap_or_default()
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+impl MfaChallengeClaims {
+    pub fn issued_at(&self) -> DateTime<Utc> {
+        get_issued_at(self.iat, self.iat_us)
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.exp, 0).unwrap_or_default()
+    }
+}
+
+/// Prefers the precise issue time and falls back to the whole seconds of `iat` for older tokens.
+fn get_issued_at(iat: i64, iat_us: Option<i64>) -> DateTime<Utc> {
+    iat_us
+        .and_then(DateTime::from_timestamp_micros)
+        .or_else(|| DateTime::from_timestamp(iat, 0))
+        .unwrap_or_default()
 }
@@ -1,2 +1,7 @@
 pub mod claims;
+pub mod password_reset_token;
+pub mod refresh_token;
+pub mod role;
+pub mod signing_key_promotion;
+pub mod totp;
 pub mod user;
@@ -0,0 +1,2 @@
+/// Lists every user. Created by the migrations, assigned with `backend roles grant`.
+pub const ADMIN_ROLE: &str = "admin";
@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+/// The latest promotion of a signing key. The key promoted last signs new tokens on every instance.
+#[derive(Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::signing_key_promotions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SigningKeyPromotion {
+    pub kid: String,
+    pub promoted_at: DateTime<Utc>,
+}
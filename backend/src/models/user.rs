@@ -1,8 +1,9 @@
 use crate::api::contracts;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use uuid::Uuid;
 
-#[derive(Queryable, Selectable, Insertable)]
+#[derive(Queryable, Selectable, Insertable, Clone)]
 #[diesel(table_name = crate::schema::users)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct User {
@@ -10,6 +11,26 @@ pub struct User {
     pub name: String,
     pub email: String,
     pub password_hash: Vec<u8>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+}
+
+/// What users can change about themselves. Fields that are `None` keep their value.
+#[derive(AsChangeset, Debug, Default)]
+#[diesel(table_name = crate::schema::users)]
+pub struct UserProfileChanges {
+    pub name: Option<String>,
+}
+
+impl UserProfileChanges {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+    }
+}
+
+impl From<contracts::UpdateUserRequest> for UserProfileChanges {
+    fn from(value: contracts::UpdateUserRequest) -> Self {
+        Self { name: value.name }
+    }
 }
 
 impl From<User> for contracts::User {
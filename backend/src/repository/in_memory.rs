@@ -0,0 +1,389 @@
+use crate::models::password_reset_token::PasswordResetToken;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::signing_key_promotion::SigningKeyPromotion;
+use crate::models::totp::{RecoveryCode, UserTotp};
+use crate::models::user::{User, UserProfileChanges};
+use crate::repository::{
+    PasswordResetTokenRepository, RecoveryCodeRepository, RefreshTokenRepository, RoleRepository,
+    SigningKeyRepository, TotpRepository, UserRepository,
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// Keeps everything in memory, so the services can be tested without a database.
+/// Every operation holds a single lock, which makes them as atomic as their Postgres counterparts.
+#[derive(Default)]
+pub struct InMemoryRepository {
+    state: Mutex<InMemoryState>,
+}
+
+#[derive(Default)]
+struct InMemoryState {
+    users: Vec<User>,
+    /// Pairs of user id and role. Unlike the database, any role can be assigned.
+    user_roles: Vec<(Uuid, String)>,
+    totps: Vec<UserTotp>,
+    recovery_codes: Vec<RecoveryCode>,
+    refresh_tokens: Vec<RefreshToken>,
+    password_reset_tokens: Vec<PasswordResetToken>,
+    signing_key_promotions: Vec<SigningKeyPromotion>,
+}
+
+impl Debug for InMemoryRepository {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InMemoryRepository").finish_non_exhaustive()
+    }
+}
+
+impl InMemoryRepository {
+    fn lock(&self) -> MutexGuard<'_, InMemoryState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryRepository {
+    async fn insert_user(&self, to_insert: User) -> Result<User, anyhow::Error> {
+        let mut state = self.lock();
+        if state.users.iter().any(|u| u.email == to_insert.email) {
+            // the same error Postgres reports for the unique index on the email column
+            let err = DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new(String::from("email is already taken")),
+            );
+            return Err(anyhow::Error::from(err)).context("failed to insert new user");
+        }
+
+        state.users.push(to_insert.clone());
+        Ok(to_insert)
+    }
+
+    async fn get_user(&self, user_id: Uuid) -> Result<Option<User>, anyhow::Error> {
+        Ok(self.lock().users.iter().find(|u| u.id == user_id).cloned())
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, anyhow::Error> {
+        Ok(self.lock().users.iter().find(|u| u.email == email).cloned())
+    }
+
+    async fn mark_email_verified(&self, user_id: Uuid, email: &str) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        let Some(user) = state
+            .users
+            .iter_mut()
+            .find(|u| u.id == user_id && u.email == email)
+        else {
+            return Ok(false);
+        };
+
+        user.email_verified_at.get_or_insert_with(Utc::now);
+        Ok(true)
+    }
+
+    async fn update_password_hash(
+        &self,
+        user_id: Uuid,
+        old_password_hash: Vec<u8>,
+        new_password_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        let Some(user) = state
+            .users
+            .iter_mut()
+            .find(|u| u.id == user_id && u.password_hash == old_password_hash)
+        else {
+            return Ok(false);
+        };
+
+        user.password_hash = new_password_hash;
+        Ok(true)
+    }
+
+    async fn set_password_hash(
+        &self,
+        user_id: Uuid,
+        password_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        let Some(user) = state.users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+
+        user.password_hash = password_hash;
+        Ok(true)
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        changes: UserProfileChanges,
+    ) -> Result<Option<User>, anyhow::Error> {
+        let mut state = self.lock();
+        let Some(user) = state.users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(None);
+        };
+
+        if let Some(name) = changes.name {
+            user.name = name;
+        }
+        Ok(Some(user.clone()))
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, anyhow::Error> {
+        Ok(self.lock().users.clone())
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        let count = state.users.len();
+        state.users.retain(|u| u.id != user_id);
+        if state.users.len() == count {
+            return Ok(false);
+        }
+
+        // what the foreign keys cascade to in the database
+        state.user_roles.retain(|(u, _)| *u != user_id);
+        state.totps.retain(|t| t.user_id != user_id);
+        state.recovery_codes.retain(|c| c.user_id != user_id);
+        state.refresh_tokens.retain(|t| t.user_id != user_id);
+        state.password_reset_tokens.retain(|t| t.user_id != user_id);
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl RoleRepository for InMemoryRepository {
+    async fn get_user_roles(&self, user_id: Uuid) -> Result<Vec<String>, anyhow::Error> {
+        let mut roles = self
+            .lock()
+            .user_roles
+            .iter()
+            .filter(|(id, _)| *id == user_id)
+            .map(|(_, role)| role.clone())
+            .collect::<Vec<_>>();
+        roles.sort();
+
+        Ok(roles)
+    }
+
+    async fn assign_role(&self, user_id: Uuid, role: &str) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        if state
+            .user_roles
+            .iter()
+            .any(|(id, r)| *id == user_id && r == role)
+        {
+            return Ok(false);
+        }
+
+        state.user_roles.push((user_id, role.to_string()));
+        Ok(true)
+    }
+
+    async fn unassign_role(&self, user_id: Uuid, role: &str) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        let count = state.user_roles.len();
+        state
+            .user_roles
+            .retain(|(id, r)| !(*id == user_id && r == role));
+
+        Ok(state.user_roles.len() < count)
+    }
+}
+
+#[async_trait]
+impl TotpRepository for InMemoryRepository {
+    async fn upsert_user_totp(&self, to_upsert: UserTotp) -> Result<(), anyhow::Error> {
+        let mut state = self.lock();
+        state.totps.retain(|t| t.user_id != to_upsert.user_id);
+        state.totps.push(to_upsert);
+
+        Ok(())
+    }
+
+    async fn get_user_totp(&self, user_id: Uuid) -> Result<Option<UserTotp>, anyhow::Error> {
+        Ok(self
+            .lock()
+            .totps
+            .iter()
+            .find(|t| t.user_id == user_id)
+            .cloned())
+    }
+
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        let Some(totp) = state
+            .totps
+            .iter_mut()
+            .find(|t| t.user_id == user_id && t.last_used_step.is_none_or(|last| last < step))
+        else {
+            return Ok(false);
+        };
+
+        totp.last_used_step = Some(step);
+        totp.confirmed_at.get_or_insert_with(Utc::now);
+        Ok(true)
+    }
+
+    async fn delete_user_totp(&self, user_id: Uuid) -> Result<usize, anyhow::Error> {
+        let mut state = self.lock();
+        state.recovery_codes.retain(|c| c.user_id != user_id);
+
+        let count = state.totps.len();
+        state.totps.retain(|t| t.user_id != user_id);
+        Ok(count - state.totps.len())
+    }
+}
+
+#[async_trait]
+impl RecoveryCodeRepository for InMemoryRepository {
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        to_insert: Vec<RecoveryCode>,
+    ) -> Result<(), anyhow::Error> {
+        let mut state = self.lock();
+        state.recovery_codes.retain(|c| c.user_id != user_id);
+        state.recovery_codes.extend(to_insert);
+
+        Ok(())
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user_id: Uuid,
+        code_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        let Some(code) = state
+            .recovery_codes
+            .iter_mut()
+            .find(|c| c.user_id == user_id && c.code_hash == code_hash && c.used_at.is_none())
+        else {
+            return Ok(false);
+        };
+
+        code.used_at = Some(Utc::now());
+        Ok(true)
+    }
+
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, anyhow::Error> {
+        let count = self
+            .lock()
+            .recovery_codes
+            .iter()
+            .filter(|c| c.user_id == user_id && c.used_at.is_none())
+            .count();
+
+        Ok(count as i64)
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for InMemoryRepository {
+    async fn insert_refresh_token(
+        &self,
+        to_insert: RefreshToken,
+    ) -> Result<RefreshToken, anyhow::Error> {
+        self.lock().refresh_tokens.push(to_insert.clone());
+
+        Ok(to_insert)
+    }
+
+    async fn get_refresh_token(
+        &self,
+        token_hash: Vec<u8>,
+    ) -> Result<Option<RefreshToken>, anyhow::Error> {
+        Ok(self
+            .lock()
+            .refresh_tokens
+            .iter()
+            .find(|t| t.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        rotated_id: Uuid,
+        replacement: RefreshToken,
+    ) -> Result<bool, anyhow::Error> {
+        let mut state = self.lock();
+        let Some(rotated) = state
+            .refresh_tokens
+            .iter_mut()
+            .find(|t| t.id == rotated_id && t.rotated_at.is_none() && t.revoked_at.is_none())
+        else {
+            return Ok(false);
+        };
+
+        rotated.rotated_at = Some(Utc::now());
+        state.refresh_tokens.push(replacement);
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl PasswordResetTokenRepository for InMemoryRepository {
+    async fn insert_password_reset_token(
+        &self,
+        to_insert: PasswordResetToken,
+    ) -> Result<(), anyhow::Error> {
+        self.lock().password_reset_tokens.push(to_insert);
+
+        Ok(())
+    }
+
+    async fn use_password_reset_token(
+        &self,
+        token_hash: Vec<u8>,
+    ) -> Result<Option<Uuid>, anyhow::Error> {
+        let mut state = self.lock();
+        let now = Utc::now();
+        let Some(user_id) = state
+            .password_reset_tokens
+            .iter()
+            .find(|t| t.token_hash == token_hash && t.used_at.is_none() && t.expires_at > now)
+            .map(|t| t.user_id)
+        else {
+            return Ok(None);
+        };
+
+        state
+            .password_reset_tokens
+            .iter_mut()
+            .filter(|t| t.user_id == user_id && t.used_at.is_none())
+            .for_each(|t| t.used_at = Some(now));
+
+        Ok(Some(user_id))
+    }
+}
+
+#[async_trait]
+impl SigningKeyRepository for InMemoryRepository {
+    async fn upsert_signing_key_promotion(
+        &self,
+        to_upsert: SigningKeyPromotion,
+    ) -> Result<(), anyhow::Error> {
+        let mut state = self.lock();
+        state
+            .signing_key_promotions
+            .retain(|p| p.kid != to_upsert.kid);
+        state.signing_key_promotions.push(to_upsert);
+
+        Ok(())
+    }
+
+    async fn get_signing_key_promotions(&self) -> Result<Vec<SigningKeyPromotion>, anyhow::Error> {
+        let mut promotions = self.lock().signing_key_promotions.clone();
+        promotions.sort_by(|a, b| (a.promoted_at, &a.kid).cmp(&(b.promoted_at, &b.kid)));
+
+        Ok(promotions)
+    }
+}
@@ -1,4 +1,239 @@
-pub mod users;
+use crate::models::password_reset_token::PasswordResetToken;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::signing_key_promotion::SigningKeyPromotion;
+use crate::models::totp::{RecoveryCode, UserTotp};
+use crate::models::user::{User, UserProfileChanges};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+use uuid::Uuid;
 
-#[derive(Debug, Clone)]
-pub struct Repository {}
+pub mod in_memory;
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// Everything `UserService` needs to persist. The operations are grouped by the table they work on,
+/// implementing all of the groups makes a type a `Repository`.
+pub trait Repository:
+    UserRepository
+    + RoleRepository
+    + TotpRepository
+    + RecoveryCodeRepository
+    + RefreshTokenRepository
+    + PasswordResetTokenRepository
+    + SigningKeyRepository
+    + Debug
+    + Send
+    + Sync
+{
+}
+
+impl<T> Repository for T where
+    T: UserRepository
+        + RoleRepository
+        + TotpRepository
+        + RecoveryCodeRepository
+        + RefreshTokenRepository
+        + PasswordResetTokenRepository
+        + SigningKeyRepository
+        + Debug
+        + Send
+        + Sync
+{
+}
+
+#[async_trait]
+pub trait UserRepository {
+    /// Fails with a `UniqueViolation` database error when the email is already taken.
+    async fn insert_user(&self, to_insert: User) -> Result<User, anyhow::Error>;
+
+    async fn get_user(&self, user_id: Uuid) -> Result<Option<User>, anyhow::Error>;
+
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, anyhow::Error>;
+
+    /// Marks the email as verified, unless the user has changed it since the verification link
+    /// was sent. Returns `false` when no user with that id and email exists.
+    async fn mark_email_verified(&self, user_id: Uuid, email: &str) -> Result<bool, anyhow::Error>;
+
+    /// Replaces the password hash, unless it was changed since `old_password_hash` was read.
+    /// Returns `false` when nothing was updated.
+    async fn update_password_hash(
+        &self,
+        user_id: Uuid,
+        old_password_hash: Vec<u8>,
+        new_password_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error>;
+
+    /// Replaces the password hash whatever it was before. Returns `false` when no user with that
+    /// id exists.
+    async fn set_password_hash(
+        &self,
+        user_id: Uuid,
+        password_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error>;
+
+    /// Returns the updated user, or `None` when no user with that id exists. `changes` must not be empty.
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        changes: UserProfileChanges,
+    ) -> Result<Option<User>, anyhow::Error>;
+
+    async fn get_users(&self) -> Result<Vec<User>, anyhow::Error>;
+
+    /// Deletes the user together with everything that belongs to them, like their tokens and roles.
+    /// Returns `false` when no user with that id exists.
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool, anyhow::Error>;
+}
+
+#[async_trait]
+pub trait RoleRepository {
+    async fn get_user_roles(&self, user_id: Uuid) -> Result<Vec<String>, anyhow::Error>;
+
+    /// Fails with a `ForeignKeyViolation` database error when the role does not exist.
+    /// Returns `false` when the user already has the role.
+    async fn assign_role(&self, user_id: Uuid, role: &str) -> Result<bool, anyhow::Error>;
+
+    /// Returns `false` when the user didn't have the role.
+    async fn unassign_role(&self, user_id: Uuid, role: &str) -> Result<bool, anyhow::Error>;
+}
+
+#[async_trait]
+pub trait TotpRepository {
+    /// Stores a new, not yet confirmed TOTP secret, replacing any previous enrollment of the user.
+    async fn upsert_user_totp(&self, to_upsert: UserTotp) -> Result<(), anyhow::Error>;
+
+    async fn get_user_totp(&self, user_id: Uuid) -> Result<Option<UserTotp>, anyhow::Error>;
+
+    /// Records `step` as used and confirms the enrollment if it wasn't yet. Returns `false`
+    /// when a code of the same or a later step was used in the meantime.
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, anyhow::Error>;
+
+    /// Disables TOTP for the user together with their recovery codes.
+    async fn delete_user_totp(&self, user_id: Uuid) -> Result<usize, anyhow::Error>;
+}
+
+#[async_trait]
+pub trait RecoveryCodeRepository {
+    /// Replaces every recovery code of the user with `to_insert`.
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        to_insert: Vec<RecoveryCode>,
+    ) -> Result<(), anyhow::Error>;
+
+    /// Marks the matching unused recovery code as used. Returns `false` when there is none.
+    async fn use_recovery_code(
+        &self,
+        user_id: Uuid,
+        code_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error>;
+
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, anyhow::Error>;
+}
+
+#[async_trait]
+pub trait RefreshTokenRepository {
+    async fn insert_refresh_token(
+        &self,
+        to_insert: RefreshToken,
+    ) -> Result<RefreshToken, anyhow::Error>;
+
+    async fn get_refresh_token(
+        &self,
+        token_hash: Vec<u8>,
+    ) -> Result<Option<RefreshToken>, anyhow::Error>;
+
+    /// Marks `rotated_id` as used and stores `replacement` atomically. Returns `false` without
+    /// storing anything when the token was already rotated or revoked in the meantime.
+    async fn rotate_refresh_token(
+        &self,
+        rotated_id: Uuid,
+        replacement: RefreshToken,
+    ) -> Result<bool, anyhow::Error>;
+}
+
+#[async_trait]
+pub trait PasswordResetTokenRepository {
+    async fn insert_password_reset_token(
+        &self,
+        to_insert: PasswordResetToken,
+    ) -> Result<(), anyhow::Error>;
+
+    /// Uses up the reset token atomically, making every other reset token of the user unusable as
+    /// well. Returns the id of the user the token was issued to, or `None` when the token is
+    /// unknown, expired or already used.
+    async fn use_password_reset_token(
+        &self,
+        token_hash: Vec<u8>,
+    ) -> Result<Option<Uuid>, anyhow::Error>;
+}
+
+#[async_trait]
+pub trait SigningKeyRepository {
+    /// Replaces the previous promotion of the same key, if there was one.
+    async fn upsert_signing_key_promotion(
+        &self,
+        to_upsert: SigningKeyPromotion,
+    ) -> Result<(), anyhow::Error>;
+
+    /// Returns the latest promotion of every key that was ever promoted, oldest first.
+    async fn get_signing_key_promotions(&self) -> Result<Vec<SigningKeyPromotion>, anyhow::Error>;
+}
+
+/// What the database backed `RevocationStore` persists. Kept apart from `Repository`, because
+/// `UserService` only revokes through the store.
+#[async_trait]
+pub trait RevocationRepository: Debug + Send + Sync {
+    /// Returns `false` when the token was revoked already.
+    async fn insert_revoked_token(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, anyhow::Error>;
+
+    /// Moves the user's revocation cutoff to `revoked_before`, unless it is already later.
+    async fn upsert_tokens_revoked_before(
+        &self,
+        user_id: Uuid,
+        revoked_before: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error>;
+
+    /// A token is revoked when its `jti` was revoked directly, when it was issued before the
+    /// user's revocation cutoff, when the session (refresh token family) it belongs to was revoked,
+    /// or when the user was deleted, which takes the other revocations with it.
+    async fn is_token_revoked(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        session_id: Uuid,
+        issued_at: DateTime<Utc>,
+    ) -> Result<bool, anyhow::Error>;
+
+    async fn revoke_refresh_token_family(&self, family_id: Uuid) -> Result<usize, anyhow::Error>;
+
+    /// Revokes every refresh token family of the user that was started before `issued_before`.
+    async fn revoke_refresh_tokens_issued_before(
+        &self,
+        user_id: Uuid,
+        issued_before: DateTime<Utc>,
+    ) -> Result<usize, anyhow::Error>;
+
+    /// Revokes every refresh token family of the user except `kept_family_id`.
+    async fn revoke_other_refresh_token_families(
+        &self,
+        user_id: Uuid,
+        kept_family_id: Uuid,
+    ) -> Result<usize, anyhow::Error>;
+
+    /// Counts an attempt at answering the MFA challenge `jti` and returns how many there were so
+    /// far, the current one included.
+    async fn increment_mfa_attempts(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<i32, anyhow::Error>;
+}
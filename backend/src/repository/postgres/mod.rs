@@ -0,0 +1,51 @@
+use anyhow::Context;
+use diesel::sql_types::{Nullable, Timestamptz};
+use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
+use diesel_async::AsyncPgConnection;
+use log::error;
+
+pub mod password_reset_tokens;
+pub mod recovery_codes;
+pub mod refresh_tokens;
+pub mod revocations;
+pub mod roles;
+pub mod signing_keys;
+pub mod tls;
+pub mod totp;
+pub mod users;
+
+/// The Diesel implementation of the repository traits, which is what the application runs on.
+#[derive(Debug, Clone)]
+pub struct PostgresRepository {
+    db_pool: Pool<AsyncPgConnection>,
+}
+
+impl PostgresRepository {
+    pub fn new(db_pool: Pool<AsyncPgConnection>) -> Self {
+        Self { db_pool }
+    }
+}
+
+diesel::define_sql_function!(fn coalesce(a: Nullable<Timestamptz>, b: Timestamptz) -> Nullable<Timestamptz>);
+
+async fn get_connection_from_pool(
+    db_pool: &Pool<AsyncPgConnection>,
+) -> Result<PooledConnection<'_, AsyncPgConnection>, anyhow::Error> {
+    let conn_result = db_pool
+        .get()
+        .await
+        .context("failed to get a connection from DB pool");
+
+    match conn_result {
+        Ok(c) => Ok(c),
+        Err(e) => {
+            error!("{}", e);
+            Err(e)
+        }
+    }
+}
+
+fn log_error_with_context(error: diesel::result::Error) -> diesel::result::Error {
+    error!("{}", error);
+    error
+}
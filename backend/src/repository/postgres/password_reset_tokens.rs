@@ -0,0 +1,89 @@
+use super::{get_connection_from_pool, log_error_with_context, PostgresRepository};
+use crate::models::password_reset_token::PasswordResetToken;
+use crate::repository::PasswordResetTokenRepository;
+use crate::schema::password_reset_tokens;
+use crate::schema::password_reset_tokens::{expires_at, token_hash, used_at, user_id};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::{ExpressionMethods, OptionalExtension};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use log::error;
+use uuid::Uuid;
+
+#[async_trait]
+impl PasswordResetTokenRepository for PostgresRepository {
+    async fn insert_password_reset_token(
+        &self,
+        to_insert: PasswordResetToken,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let insert_result = diesel::insert_into(password_reset_tokens::table)
+            .values(&to_insert)
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to insert password reset token to DB");
+
+        match insert_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Uses up the reset token in a single transaction, making every other reset token of the user
+    /// unusable as well. Returns the id of the user the token was issued to, or `None` when the
+    /// token is unknown, expired or already used.
+    async fn use_password_reset_token(
+        &self,
+        token_hash_: Vec<u8>,
+    ) -> Result<Option<Uuid>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let use_result = conn
+            .transaction::<Option<Uuid>, diesel::result::Error, _>(|conn| {
+                async move {
+                    let now = Utc::now();
+                    let maybe_user_id = diesel::update(password_reset_tokens::table)
+                        .filter(token_hash.eq(token_hash_))
+                        .filter(used_at.is_null())
+                        .filter(expires_at.gt(now))
+                        .set(used_at.eq(now))
+                        .returning(user_id)
+                        .get_result::<Uuid>(conn)
+                        .await
+                        .optional()?;
+
+                    let Some(user_id_) = maybe_user_id else {
+                        return Ok(None);
+                    };
+
+                    diesel::update(password_reset_tokens::table)
+                        .filter(user_id.eq(user_id_))
+                        .filter(used_at.is_null())
+                        .set(used_at.eq(now))
+                        .execute(conn)
+                        .await?;
+
+                    Ok(Some(user_id_))
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to use password reset token in DB");
+
+        match use_result {
+            Ok(maybe_user_id) => Ok(maybe_user_id),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
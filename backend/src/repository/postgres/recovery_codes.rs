@@ -0,0 +1,100 @@
+use super::{get_connection_from_pool, log_error_with_context, PostgresRepository};
+use crate::models::totp::RecoveryCode;
+use crate::repository::RecoveryCodeRepository;
+use crate::schema::recovery_codes;
+use crate::schema::recovery_codes::{code_hash, used_at, user_id};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use log::error;
+use uuid::Uuid;
+
+#[async_trait]
+impl RecoveryCodeRepository for PostgresRepository {
+    /// Replaces every recovery code of the user with `to_insert`.
+    async fn replace_recovery_codes(
+        &self,
+        user_id_: Uuid,
+        to_insert: Vec<RecoveryCode>,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let replace_result = conn
+            .transaction::<usize, diesel::result::Error, _>(|conn| {
+                async move {
+                    diesel::delete(recovery_codes::table)
+                        .filter(user_id.eq(user_id_))
+                        .execute(conn)
+                        .await?;
+
+                    diesel::insert_into(recovery_codes::table)
+                        .values(&to_insert)
+                        .execute(conn)
+                        .await
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to replace recovery codes in DB");
+
+        match replace_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Marks the matching unused recovery code as used. Returns `false` when there is none.
+    async fn use_recovery_code(
+        &self,
+        user_id_: Uuid,
+        code_hash_: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let update_result = diesel::update(recovery_codes::table)
+            .filter(user_id.eq(user_id_))
+            .filter(code_hash.eq(code_hash_))
+            .filter(used_at.is_null())
+            .set(used_at.eq(Utc::now()))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to use recovery code in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn count_unused_recovery_codes(&self, user_id_: Uuid) -> Result<i64, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let count_result = recovery_codes::table
+            .filter(user_id.eq(user_id_))
+            .filter(used_at.is_null())
+            .count()
+            .get_result(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to count recovery codes in DB");
+
+        match count_result {
+            Ok(count) => Ok(count),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use super::{get_connection_from_pool, log_error_with_context, PostgresRepository};
+use crate::models::refresh_token::RefreshToken;
+use crate::repository::RefreshTokenRepository;
+use crate::schema::refresh_tokens;
+use crate::schema::refresh_tokens::{id, revoked_at, rotated_at, token_hash};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use log::error;
+use uuid::Uuid;
+
+#[async_trait]
+impl RefreshTokenRepository for PostgresRepository {
+    async fn insert_refresh_token(
+        &self,
+        to_insert: RefreshToken,
+    ) -> Result<RefreshToken, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let insert_result = diesel::insert_into(refresh_tokens::table)
+            .values(&to_insert)
+            .returning(RefreshToken::as_returning())
+            .get_result(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to insert new refresh token to DB");
+
+        match insert_result {
+            Ok(t) => Ok(t),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_refresh_token(
+        &self,
+        token_hash_: Vec<u8>,
+    ) -> Result<Option<RefreshToken>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let get_result = refresh_tokens::table
+            .select(RefreshToken::as_select())
+            .filter(token_hash.eq(token_hash_))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to retrieve refresh token from DB");
+
+        match get_result {
+            Ok(maybe_token) => Ok(maybe_token),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Marks `rotated_id` as used and stores `replacement` in a single transaction.
+    /// Returns `false` without inserting anything when the token was already rotated
+    /// or revoked in the meantime.
+    async fn rotate_refresh_token(
+        &self,
+        rotated_id: Uuid,
+        replacement: RefreshToken,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let rotate_result = conn
+            .transaction::<bool, diesel::result::Error, _>(|conn| {
+                async move {
+                    let updated = diesel::update(refresh_tokens::table)
+                        .filter(id.eq(rotated_id))
+                        .filter(rotated_at.is_null())
+                        .filter(revoked_at.is_null())
+                        .set(rotated_at.eq(Utc::now()))
+                        .execute(conn)
+                        .await?;
+
+                    if updated == 0 {
+                        return Ok(false);
+                    }
+
+                    diesel::insert_into(refresh_tokens::table)
+                        .values(&replacement)
+                        .execute(conn)
+                        .await?;
+
+                    Ok(true)
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to rotate refresh token in DB");
+
+        match rotate_result {
+            Ok(rotated) => Ok(rotated),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use super::{get_connection_from_pool, log_error_with_context, PostgresRepository};
+use crate::repository::RevocationRepository;
+use crate::schema::{
+    mfa_challenge_attempts, refresh_tokens, revoked_tokens, user_token_revocations, users,
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::{exists, not};
+use diesel::sql_types::Timestamptz;
+use diesel::upsert::excluded;
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use log::error;
+use uuid::Uuid;
+
+diesel::define_sql_function!(fn greatest(a: Timestamptz, b: Timestamptz) -> Timestamptz);
+
+#[async_trait]
+impl RevocationRepository for PostgresRepository {
+    async fn insert_revoked_token(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let insert_result = async {
+            let inserted = diesel::insert_into(revoked_tokens::table)
+                .values((
+                    revoked_tokens::jti.eq(jti),
+                    revoked_tokens::user_id.eq(user_id),
+                    revoked_tokens::expires_at.eq(expires_at),
+                ))
+                .on_conflict_do_nothing()
+                .execute(&mut conn)
+                .await?;
+
+            // entries are useless once the token expires on its own, so prune them as we go
+            diesel::delete(revoked_tokens::table)
+                .filter(revoked_tokens::expires_at.lt(Utc::now()))
+                .execute(&mut conn)
+                .await?;
+
+            Ok::<_, diesel::result::Error>(inserted > 0)
+        }
+        .await
+        .map_err(log_error_with_context)
+        .context("failed to insert revoked token to DB");
+
+        match insert_result {
+            Ok(inserted) => Ok(inserted),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn upsert_tokens_revoked_before(
+        &self,
+        user_id: Uuid,
+        revoked_before: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let upsert_result = diesel::insert_into(user_token_revocations::table)
+            .values((
+                user_token_revocations::user_id.eq(user_id),
+                user_token_revocations::revoked_before.eq(revoked_before),
+            ))
+            .on_conflict(user_token_revocations::user_id)
+            .do_update()
+            .set(user_token_revocations::revoked_before.eq(greatest(
+                user_token_revocations::revoked_before,
+                excluded(user_token_revocations::revoked_before),
+            )))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to store token revocation time in DB");
+
+        match upsert_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// A token is revoked when its `jti` was revoked directly, when it was issued before the
+    /// user's revocation cutoff, or when the session (refresh token family) it belongs to was revoked.
+    async fn is_token_revoked(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        session_id: Uuid,
+        issued_at: DateTime<Utc>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let get_result = diesel::select(
+            exists(revoked_tokens::table.filter(revoked_tokens::jti.eq(jti)))
+                .or(exists(
+                    user_token_revocations::table
+                        .filter(user_token_revocations::user_id.eq(user_id))
+                        .filter(user_token_revocations::revoked_before.gt(issued_at)),
+                ))
+                .or(exists(
+                    refresh_tokens::table
+                        .filter(refresh_tokens::family_id.eq(session_id))
+                        .filter(refresh_tokens::revoked_at.is_not_null()),
+                ))
+                .or(not(exists(users::table.filter(users::id.eq(user_id))))),
+        )
+        .get_result::<bool>(&mut conn)
+        .await
+        .map_err(log_error_with_context)
+        .context("failed to check token revocation in DB");
+
+        match get_result {
+            Ok(is_revoked) => Ok(is_revoked),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id_: Uuid) -> Result<usize, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let revoke_result = diesel::update(refresh_tokens::table)
+            .filter(refresh_tokens::family_id.eq(family_id_))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .set(refresh_tokens::revoked_at.eq(Utc::now()))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to revoke refresh token family in DB");
+
+        match revoke_result {
+            Ok(revoked) => Ok(revoked),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Revokes every refresh token family of the user that was started before `issued_before`.
+    async fn revoke_refresh_tokens_issued_before(
+        &self,
+        user_id_: Uuid,
+        issued_before: DateTime<Utc>,
+    ) -> Result<usize, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        // a family was started before the cutoff if any of its tokens was created before it
+        let issued = diesel::alias!(refresh_tokens as issued);
+        let families_issued_before = issued
+            .select(issued.field(refresh_tokens::family_id))
+            .filter(issued.field(refresh_tokens::user_id).eq(user_id_))
+            .filter(issued.field(refresh_tokens::created_at).lt(issued_before));
+
+        let revoke_result = diesel::update(refresh_tokens::table)
+            .filter(refresh_tokens::family_id.eq_any(families_issued_before))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .set(refresh_tokens::revoked_at.eq(Utc::now()))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to revoke user's refresh tokens in DB");
+
+        match revoke_result {
+            Ok(revoked) => Ok(revoked),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Revokes every refresh token family of the user except `kept_family_id`.
+    async fn revoke_other_refresh_token_families(
+        &self,
+        user_id_: Uuid,
+        kept_family_id: Uuid,
+    ) -> Result<usize, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let revoke_result = diesel::update(refresh_tokens::table)
+            .filter(refresh_tokens::user_id.eq(user_id_))
+            .filter(refresh_tokens::family_id.ne(kept_family_id))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .set(refresh_tokens::revoked_at.eq(Utc::now()))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to revoke user's other refresh tokens in DB");
+
+        match revoke_result {
+            Ok(revoked) => Ok(revoked),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Counts an attempt at answering the MFA challenge `jti` and returns how many there were so
+    /// far, the current one included.
+    async fn increment_mfa_attempts(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<i32, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let increment_result = async {
+            let attempts = diesel::insert_into(mfa_challenge_attempts::table)
+                .values((
+                    mfa_challenge_attempts::jti.eq(jti),
+                    mfa_challenge_attempts::user_id.eq(user_id),
+                    mfa_challenge_attempts::attempts.eq(1),
+                    mfa_challenge_attempts::expires_at.eq(expires_at),
+                ))
+                .on_conflict(mfa_challenge_attempts::jti)
+                .do_update()
+                .set(mfa_challenge_attempts::attempts.eq(mfa_challenge_attempts::attempts + 1))
+                .returning(mfa_challenge_attempts::attempts)
+                .get_result::<i32>(&mut conn)
+                .await?;
+
+            // challenges are short-lived, so their attempts are pruned the same way as revoked tokens
+            diesel::delete(mfa_challenge_attempts::table)
+                .filter(mfa_challenge_attempts::expires_at.lt(Utc::now()))
+                .execute(&mut conn)
+                .await?;
+
+            Ok::<_, diesel::result::Error>(attempts)
+        }
+        .await
+        .map_err(log_error_with_context)
+        .context("failed to count MFA attempt in DB");
+
+        match increment_result {
+            Ok(attempts) => Ok(attempts),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use super::{get_connection_from_pool, log_error_with_context, PostgresRepository};
+use crate::repository::RoleRepository;
+use crate::schema::user_roles;
+use crate::schema::user_roles::{role, user_id};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use log::error;
+use uuid::Uuid;
+
+#[async_trait]
+impl RoleRepository for PostgresRepository {
+    async fn get_user_roles(&self, user_id_: Uuid) -> Result<Vec<String>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let get_result = user_roles::table
+            .select(role)
+            .filter(user_id.eq(user_id_))
+            .order(role)
+            .load(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to get user roles from DB");
+
+        match get_result {
+            Ok(roles) => Ok(roles),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn assign_role(&self, user_id_: Uuid, role_: &str) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let insert_result = diesel::insert_into(user_roles::table)
+            .values((
+                user_id.eq(user_id_),
+                role.eq(role_),
+                user_roles::created_at.eq(Utc::now()),
+            ))
+            .on_conflict_do_nothing()
+            .execute(&mut conn)
+            .await
+            .map_err(|diesel_error| {
+                let err = log_error_with_context(diesel_error);
+                anyhow::Error::from(err)
+            })
+            .context("failed to assign role in DB");
+
+        match insert_result {
+            Ok(inserted) => Ok(inserted > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn unassign_role(&self, user_id_: Uuid, role_: &str) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let delete_result = diesel::delete(user_roles::table)
+            .filter(user_id.eq(user_id_))
+            .filter(role.eq(role_))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to unassign role in DB");
+
+        match delete_result {
+            Ok(deleted) => Ok(deleted > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use super::{get_connection_from_pool, log_error_with_context, PostgresRepository};
+use crate::models::signing_key_promotion::SigningKeyPromotion;
+use crate::repository::SigningKeyRepository;
+use crate::schema::signing_key_promotions;
+use crate::schema::signing_key_promotions::{kid, promoted_at};
+use anyhow::Context;
+use async_trait::async_trait;
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use log::error;
+
+#[async_trait]
+impl SigningKeyRepository for PostgresRepository {
+    async fn upsert_signing_key_promotion(
+        &self,
+        to_upsert: SigningKeyPromotion,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let upsert_result = diesel::insert_into(signing_key_promotions::table)
+            .values(&to_upsert)
+            .on_conflict(kid)
+            .do_update()
+            .set(promoted_at.eq(to_upsert.promoted_at))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to upsert signing key promotion in DB");
+
+        match upsert_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_signing_key_promotions(&self) -> Result<Vec<SigningKeyPromotion>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let get_result = signing_key_promotions::table
+            .select(SigningKeyPromotion::as_select())
+            .order((promoted_at, kid))
+            .load(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to get signing key promotions from DB");
+
+        match get_result {
+            Ok(promotions) => Ok(promotions),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::tls::{get_crypto_provider, read_certificates, read_private_key};
+use anyhow::Context;
+use diesel::{ConnectionError, ConnectionResult};
+use diesel_async::{AsyncConnection, AsyncPgConnection};
+use rustls::{ClientConfig, RootCertStore};
+use std::path::Path;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+/// Builds the TLS configuration for connecting to Postgres. The server's certificate is verified
+/// against the certificates in `root_cert_path`, or the Mozilla root certificates when it isn't set,
+/// and it must be issued for the host that is connected to.
+pub fn get_client_config(
+    root_cert_path: Option<&Path>,
+    client_cert_paths: Option<(&Path, &Path)>,
+) -> Result<ClientConfig, anyhow::Error> {
+    let mut roots = RootCertStore::empty();
+    match root_cert_path {
+        Some(path) => {
+            for cert in read_certificates(path)? {
+                roots
+                    .add(cert)
+                    .with_context(|| format!("invalid CA certificate in {}", path.display()))?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+
+    let builder = ClientConfig::builder_with_provider(get_crypto_provider())
+        .with_safe_default_protocol_versions()
+        .context("failed to set up TLS")?
+        .with_root_certificates(roots);
+
+    match client_cert_paths {
+        Some((cert_path, key_path)) => builder
+            .with_client_auth_cert(read_certificates(cert_path)?, read_private_key(key_path)?)
+            .context("client certificate does not match its private key"),
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// Connects without TLS when `tls` is `None`, otherwise the connection string's `sslmode` decides
+/// whether an unencrypted connection is acceptable.
+pub async fn establish(
+    connection_string: &str,
+    tls: Option<ClientConfig>,
+) -> ConnectionResult<AsyncPgConnection> {
+    let Some(tls) = tls else {
+        return AsyncPgConnection::establish(connection_string).await;
+    };
+
+    let (client, connection) =
+        tokio_postgres::connect(connection_string, MakeRustlsConnect::new(tls))
+            .await
+            .map_err(|e| {
+                // the reason, e.g. the certificate error or the message of the server, is only in the source
+                let reason = match std::error::Error::source(&e) {
+                    Some(source) => format!("{}: {}", e, source),
+                    None => e.to_string(),
+                };
+                ConnectionError::BadConnection(reason)
+            })?;
+
+    AsyncPgConnection::try_from_client_and_connection(client, connection).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn get_client_config_should_use_mozilla_roots_when_no_ca_is_given() {
+        // Act
+
+        let actual = get_client_config(None, None);
+
+        // Assert
+
+        assert!(actual.is_ok());
+    }
+
+    #[test]
+    fn get_client_config_should_return_error_when_ca_file_has_no_certificates() {
+        // Arrange
+
+        let path = std::env::temp_dir().join(format!("ca-{}.crt", Uuid::new_v4()));
+        std::fs::write(&path, "not a certificate").unwrap();
+
+        // Act
+
+        let actual = get_client_config(Some(&path), None);
+
+        // Assert
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(actual
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("does not contain any certificates"));
+    }
+}
@@ -0,0 +1,127 @@
+use super::{coalesce, get_connection_from_pool, log_error_with_context, PostgresRepository};
+use crate::models::totp::UserTotp;
+use crate::repository::TotpRepository;
+use crate::schema::user_totp::{confirmed_at, last_used_step, user_id};
+use crate::schema::{recovery_codes, user_totp};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::upsert::excluded;
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper,
+};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use log::error;
+use uuid::Uuid;
+
+#[async_trait]
+impl TotpRepository for PostgresRepository {
+    /// Stores a new, not yet confirmed TOTP secret, replacing any previous enrollment of the user.
+    async fn upsert_user_totp(&self, to_upsert: UserTotp) -> Result<(), anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let upsert_result = diesel::insert_into(user_totp::table)
+            .values(&to_upsert)
+            .on_conflict(user_id)
+            .do_update()
+            .set((
+                user_totp::secret_ciphertext.eq(excluded(user_totp::secret_ciphertext)),
+                confirmed_at.eq(excluded(confirmed_at)),
+                last_used_step.eq(excluded(last_used_step)),
+                user_totp::created_at.eq(excluded(user_totp::created_at)),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to store TOTP secret in DB");
+
+        match upsert_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_user_totp(&self, user_id_: Uuid) -> Result<Option<UserTotp>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let get_result = user_totp::table
+            .select(UserTotp::as_select())
+            .filter(user_id.eq(user_id_))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to retrieve TOTP secret from DB");
+
+        match get_result {
+            Ok(maybe_totp) => Ok(maybe_totp),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Records `step` as used and confirms the enrollment if it wasn't yet. Returns `false`
+    /// when a code of the same or a later step was used in the meantime.
+    async fn use_totp_step(&self, user_id_: Uuid, step: i64) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let update_result = diesel::update(user_totp::table)
+            .filter(user_id.eq(user_id_))
+            .filter(last_used_step.is_null().or(last_used_step.lt(step)))
+            .set((
+                last_used_step.eq(step),
+                confirmed_at.eq(coalesce(confirmed_at, Utc::now())),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to record used TOTP code in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated == 1),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Disables TOTP for the user together with their recovery codes.
+    async fn delete_user_totp(&self, user_id_: Uuid) -> Result<usize, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let delete_result = conn
+            .transaction::<usize, diesel::result::Error, _>(|conn| {
+                async move {
+                    // recovery codes are useless without the second factor they stand in for
+                    diesel::delete(recovery_codes::table)
+                        .filter(recovery_codes::user_id.eq(user_id_))
+                        .execute(conn)
+                        .await?;
+
+                    diesel::delete(user_totp::table)
+                        .filter(user_id.eq(user_id_))
+                        .execute(conn)
+                        .await
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to delete TOTP secret from DB");
+
+        match delete_result {
+            Ok(deleted) => Ok(deleted),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use super::{coalesce, get_connection_from_pool, log_error_with_context, PostgresRepository};
+use crate::models::user::{User, UserProfileChanges};
+use crate::repository::UserRepository;
+use crate::schema::users;
+use crate::schema::users::{email, email_verified_at, id, password_hash};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use log::error;
+use uuid::Uuid;
+
+#[async_trait]
+impl UserRepository for PostgresRepository {
+    async fn insert_user(&self, to_insert: User) -> Result<User, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let insert_result = diesel::insert_into(users::table)
+            .values(&to_insert)
+            .returning(User::as_returning())
+            .get_result(&mut conn)
+            .await
+            .map_err(|diesel_error| {
+                let err = log_error_with_context(diesel_error);
+                anyhow::Error::from(err)
+            })
+            .context("failed to insert new user to DB");
+
+        match insert_result {
+            Ok(u) => Ok(u),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_user(&self, user_id: Uuid) -> Result<Option<User>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let get_result = users::table
+            .select(User::as_select())
+            .filter(id.eq(user_id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to get user from DB");
+
+        match get_result {
+            Ok(maybe_user) => Ok(maybe_user),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_user_by_email(&self, email_: &str) -> Result<Option<User>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let get_result = users::table
+            .select(User::as_select())
+            .filter(email.eq(email_))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to get user by email from DB");
+
+        match get_result {
+            Ok(maybe_user) => Ok(maybe_user),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Marks the email as verified, unless the user has changed it since the verification link
+    /// was sent. Returns `false` when no user with that id and email exists.
+    async fn mark_email_verified(
+        &self,
+        user_id: Uuid,
+        email_: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let update_result = diesel::update(users::table)
+            .filter(id.eq(user_id))
+            .filter(email.eq(email_))
+            .set(email_verified_at.eq(coalesce(email_verified_at, Utc::now())))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to mark email as verified in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Replaces the password hash, unless it was changed since `old_password_hash` was read.
+    /// Returns `false` when nothing was updated.
+    async fn update_password_hash(
+        &self,
+        user_id: Uuid,
+        old_password_hash: Vec<u8>,
+        new_password_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let update_result = diesel::update(users::table)
+            .filter(id.eq(user_id))
+            .filter(password_hash.eq(old_password_hash))
+            .set(password_hash.eq(new_password_hash))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to update password hash in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn set_password_hash(
+        &self,
+        user_id: Uuid,
+        new_password_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let update_result = diesel::update(users::table)
+            .filter(id.eq(user_id))
+            .set(password_hash.eq(new_password_hash))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to set password hash in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        changes: UserProfileChanges,
+    ) -> Result<Option<User>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let update_result = diesel::update(users::table)
+            .filter(id.eq(user_id))
+            .set(&changes)
+            .returning(User::as_returning())
+            .get_result(&mut conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to update user profile in DB");
+
+        match update_result {
+            Ok(maybe_user) => Ok(maybe_user),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let get_result = users::table
+            .select(User::as_select())
+            .load(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to get users from DB");
+
+        match get_result {
+            Ok(us) => Ok(us),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool, anyhow::Error> {
+        let mut conn = get_connection_from_pool(&self.db_pool).await?;
+
+        let delete_result = diesel::delete(users::table)
+            .filter(id.eq(user_id))
+            .execute(&mut conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to delete user from DB");
+
+        match delete_result {
+            Ok(deleted) => Ok(deleted > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
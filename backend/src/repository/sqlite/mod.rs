@@ -0,0 +1,100 @@
+use crate::migrations::SQLITE_MIGRATIONS;
+use anyhow::{anyhow, Context};
+use diesel::connection::SimpleConnection;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::{Nullable, Text, TimestamptzSqlite};
+use diesel::sqlite::{Sqlite, SqliteValue};
+use diesel::{Connection, SqliteConnection};
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_migrations::MigrationHarness;
+use log::error;
+use std::fmt::{Debug, Formatter};
+use tokio::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+pub mod password_reset_tokens;
+pub mod recovery_codes;
+pub mod refresh_tokens;
+pub mod revocations;
+pub mod roles;
+mod schema;
+pub mod signing_keys;
+pub mod totp;
+pub mod users;
+
+/// The Diesel implementation of the repository traits on SQLite, so the service can run without
+/// a Postgres server. SQLite only allows one writer at a time anyway, so every query goes
+/// through a single connection, which also keeps an in-memory database alive.
+pub struct SqliteRepository {
+    connection: Mutex<SyncConnectionWrapper<SqliteConnection>>,
+}
+
+impl Debug for SqliteRepository {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteRepository").finish_non_exhaustive()
+    }
+}
+
+impl SqliteRepository {
+    /// Opens the database at `path`, or a fresh in-memory one for `:memory:`, and applies
+    /// the pending migrations.
+    pub fn connect(path: &str) -> Result<Self, anyhow::Error> {
+        let mut conn = SqliteConnection::establish(path)
+            .with_context(|| format!("failed to open SQLite database '{}'", path))?;
+
+        // foreign keys are off by default, which would skip the cascading deletes
+        conn.batch_execute("PRAGMA foreign_keys = ON")
+            .context("failed to enable SQLite foreign keys")?;
+        conn.run_pending_migrations(SQLITE_MIGRATIONS)
+            .map_err(|e| anyhow!(e))
+            .context("failed to run SQLite migrations")?;
+
+        Ok(Self {
+            connection: Mutex::new(SyncConnectionWrapper::new(conn)),
+        })
+    }
+
+    async fn lock(&self) -> MutexGuard<'_, SyncConnectionWrapper<SqliteConnection>> {
+        self.connection.lock().await
+    }
+}
+
+/// SQLite has no UUID type, so ids are stored in their hyphenated text form.
+#[derive(Debug, Clone, Copy, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+struct SqliteUuid(Uuid);
+
+impl From<Uuid> for SqliteUuid {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<SqliteUuid> for Uuid {
+    fn from(value: SqliteUuid) -> Self {
+        value.0
+    }
+}
+
+impl ToSql<Text, Sqlite> for SqliteUuid {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(self.0.to_string());
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Sqlite> for SqliteUuid {
+    fn from_sql(bytes: SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+        let text = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(Self(Uuid::parse_str(&text)?))
+    }
+}
+
+diesel::define_sql_function!(fn coalesce(a: Nullable<TimestamptzSqlite>, b: TimestamptzSqlite) -> Nullable<TimestamptzSqlite>);
+
+fn log_error_with_context(error: diesel::result::Error) -> diesel::result::Error {
+    error!("{}", error);
+    error
+}
@@ -0,0 +1,111 @@
+use super::schema::password_reset_tokens;
+use super::schema::password_reset_tokens::{expires_at, token_hash, used_at, user_id};
+use super::{log_error_with_context, SqliteRepository, SqliteUuid};
+use crate::models::password_reset_token::PasswordResetToken;
+use crate::repository::PasswordResetTokenRepository;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use log::error;
+use uuid::Uuid;
+
+#[derive(Insertable)]
+#[diesel(table_name = password_reset_tokens)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+struct PasswordResetTokenRow {
+    id: SqliteUuid,
+    user_id: SqliteUuid,
+    token_hash: Vec<u8>,
+    expires_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+}
+
+impl From<PasswordResetToken> for PasswordResetTokenRow {
+    fn from(value: PasswordResetToken) -> Self {
+        Self {
+            id: value.id.into(),
+            user_id: value.user_id.into(),
+            token_hash: value.token_hash,
+            expires_at: value.expires_at,
+            created_at: value.created_at,
+            used_at: value.used_at,
+        }
+    }
+}
+
+#[async_trait]
+impl PasswordResetTokenRepository for SqliteRepository {
+    async fn insert_password_reset_token(
+        &self,
+        to_insert: PasswordResetToken,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let insert_result = diesel::insert_into(password_reset_tokens::table)
+            .values(PasswordResetTokenRow::from(to_insert))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to insert password reset token to DB");
+
+        match insert_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn use_password_reset_token(
+        &self,
+        token_hash_: Vec<u8>,
+    ) -> Result<Option<Uuid>, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let use_result = conn
+            .transaction::<Option<Uuid>, diesel::result::Error, _>(|conn| {
+                async move {
+                    let now = Utc::now();
+                    let maybe_user_id = diesel::update(password_reset_tokens::table)
+                        .filter(token_hash.eq(token_hash_))
+                        .filter(used_at.is_null())
+                        .filter(expires_at.gt(now))
+                        .set(used_at.eq(now))
+                        .returning(user_id)
+                        .get_result::<SqliteUuid>(conn)
+                        .await
+                        .optional()?;
+
+                    let Some(user_id_) = maybe_user_id else {
+                        return Ok(None);
+                    };
+
+                    diesel::update(password_reset_tokens::table)
+                        .filter(user_id.eq(user_id_))
+                        .filter(used_at.is_null())
+                        .set(used_at.eq(now))
+                        .execute(conn)
+                        .await?;
+
+                    Ok(Some(user_id_.into()))
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to use password reset token in DB");
+
+        match use_result {
+            Ok(maybe_user_id) => Ok(maybe_user_id),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
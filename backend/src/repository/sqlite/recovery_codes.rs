@@ -0,0 +1,128 @@
+use super::schema::recovery_codes;
+use super::schema::recovery_codes::{code_hash, used_at, user_id};
+use super::{log_error_with_context, SqliteRepository, SqliteUuid};
+use crate::models::totp::RecoveryCode;
+use crate::repository::RecoveryCodeRepository;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use log::error;
+use uuid::Uuid;
+
+#[derive(Insertable)]
+#[diesel(table_name = recovery_codes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+struct RecoveryCodeRow {
+    id: SqliteUuid,
+    user_id: SqliteUuid,
+    code_hash: Vec<u8>,
+    used_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<RecoveryCode> for RecoveryCodeRow {
+    fn from(value: RecoveryCode) -> Self {
+        Self {
+            id: value.id.into(),
+            user_id: value.user_id.into(),
+            code_hash: value.code_hash,
+            used_at: value.used_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[async_trait]
+impl RecoveryCodeRepository for SqliteRepository {
+    async fn replace_recovery_codes(
+        &self,
+        user_id_: Uuid,
+        to_insert: Vec<RecoveryCode>,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = self.lock().await;
+        let to_insert: Vec<RecoveryCodeRow> =
+            to_insert.into_iter().map(RecoveryCodeRow::from).collect();
+
+        let replace_result = conn
+            .transaction::<(), diesel::result::Error, _>(|conn| {
+                async move {
+                    diesel::delete(recovery_codes::table)
+                        .filter(user_id.eq(SqliteUuid(user_id_)))
+                        .execute(conn)
+                        .await?;
+
+                    // diesel only batches inserts for SQLite on synchronous connections
+                    for row in to_insert {
+                        diesel::insert_into(recovery_codes::table)
+                            .values(row)
+                            .execute(conn)
+                            .await?;
+                    }
+
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to replace recovery codes in DB");
+
+        match replace_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user_id_: Uuid,
+        code_hash_: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let update_result = diesel::update(recovery_codes::table)
+            .filter(user_id.eq(SqliteUuid(user_id_)))
+            .filter(code_hash.eq(code_hash_))
+            .filter(used_at.is_null())
+            .set(used_at.eq(Utc::now()))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to use recovery code in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn count_unused_recovery_codes(&self, user_id_: Uuid) -> Result<i64, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let count_result = recovery_codes::table
+            .filter(user_id.eq(SqliteUuid(user_id_)))
+            .filter(used_at.is_null())
+            .count()
+            .get_result(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to count recovery codes in DB");
+
+        match count_result {
+            Ok(count) => Ok(count),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
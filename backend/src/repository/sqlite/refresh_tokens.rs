@@ -0,0 +1,152 @@
+use super::schema::refresh_tokens;
+use super::schema::refresh_tokens::{id, revoked_at, rotated_at, token_hash};
+use super::{log_error_with_context, SqliteRepository, SqliteUuid};
+use crate::models::refresh_token::RefreshToken;
+use crate::repository::RefreshTokenRepository;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use log::error;
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = refresh_tokens)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+struct RefreshTokenRow {
+    id: SqliteUuid,
+    user_id: SqliteUuid,
+    family_id: SqliteUuid,
+    token_hash: Vec<u8>,
+    expires_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    rotated_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<RefreshToken> for RefreshTokenRow {
+    fn from(value: RefreshToken) -> Self {
+        Self {
+            id: value.id.into(),
+            user_id: value.user_id.into(),
+            family_id: value.family_id.into(),
+            token_hash: value.token_hash,
+            expires_at: value.expires_at,
+            created_at: value.created_at,
+            rotated_at: value.rotated_at,
+            revoked_at: value.revoked_at,
+        }
+    }
+}
+
+impl From<RefreshTokenRow> for RefreshToken {
+    fn from(value: RefreshTokenRow) -> Self {
+        Self {
+            id: value.id.into(),
+            user_id: value.user_id.into(),
+            family_id: value.family_id.into(),
+            token_hash: value.token_hash,
+            expires_at: value.expires_at,
+            created_at: value.created_at,
+            rotated_at: value.rotated_at,
+            revoked_at: value.revoked_at,
+        }
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for SqliteRepository {
+    async fn insert_refresh_token(
+        &self,
+        to_insert: RefreshToken,
+    ) -> Result<RefreshToken, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let insert_result = diesel::insert_into(refresh_tokens::table)
+            .values(RefreshTokenRow::from(to_insert))
+            .returning(RefreshTokenRow::as_returning())
+            .get_result(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to insert new refresh token to DB");
+
+        match insert_result {
+            Ok(t) => Ok(RefreshToken::from(t)),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_refresh_token(
+        &self,
+        token_hash_: Vec<u8>,
+    ) -> Result<Option<RefreshToken>, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let get_result = refresh_tokens::table
+            .select(RefreshTokenRow::as_select())
+            .filter(token_hash.eq(token_hash_))
+            .first(&mut *conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to retrieve refresh token from DB");
+
+        match get_result {
+            Ok(maybe_token) => Ok(maybe_token.map(RefreshToken::from)),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        rotated_id: Uuid,
+        replacement: RefreshToken,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+        let replacement = RefreshTokenRow::from(replacement);
+
+        let rotate_result = conn
+            .transaction::<bool, diesel::result::Error, _>(|conn| {
+                async move {
+                    let updated = diesel::update(refresh_tokens::table)
+                        .filter(id.eq(SqliteUuid(rotated_id)))
+                        .filter(rotated_at.is_null())
+                        .filter(revoked_at.is_null())
+                        .set(rotated_at.eq(Utc::now()))
+                        .execute(conn)
+                        .await?;
+
+                    if updated == 0 {
+                        return Ok(false);
+                    }
+
+                    diesel::insert_into(refresh_tokens::table)
+                        .values(replacement)
+                        .execute(conn)
+                        .await?;
+
+                    Ok(true)
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to rotate refresh token in DB");
+
+        match rotate_result {
+            Ok(rotated) => Ok(rotated),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
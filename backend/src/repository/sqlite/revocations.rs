@@ -0,0 +1,474 @@
+use super::schema::{
+    mfa_challenge_attempts, refresh_tokens, revoked_tokens, user_token_revocations, users,
+};
+use super::{log_error_with_context, SqliteRepository, SqliteUuid};
+use crate::repository::RevocationRepository;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::{exists, not};
+use diesel::prelude::*;
+use diesel::sql_types::TimestamptzSqlite;
+use diesel::upsert::excluded;
+use diesel_async::RunQueryDsl;
+use log::error;
+use uuid::Uuid;
+
+// the scalar `max` of SQLite, which is what `greatest` is called there. timestamps are stored as
+// ISO 8601 text in UTC, so comparing them as text orders them correctly
+diesel::define_sql_function!(fn max(a: TimestamptzSqlite, b: TimestamptzSqlite) -> TimestamptzSqlite);
+
+#[async_trait]
+impl RevocationRepository for SqliteRepository {
+    async fn insert_revoked_token(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let insert_result = async {
+            let inserted = diesel::insert_into(revoked_tokens::table)
+                .values((
+                    revoked_tokens::jti.eq(SqliteUuid(jti)),
+                    revoked_tokens::user_id.eq(SqliteUuid(user_id)),
+                    revoked_tokens::expires_at.eq(expires_at),
+                ))
+                .on_conflict_do_nothing()
+                .execute(&mut *conn)
+                .await?;
+
+            diesel::delete(revoked_tokens::table)
+                .filter(revoked_tokens::expires_at.lt(Utc::now()))
+                .execute(&mut *conn)
+                .await?;
+
+            Ok::<_, diesel::result::Error>(inserted > 0)
+        }
+        .await
+        .map_err(log_error_with_context)
+        .context("failed to insert revoked token to DB");
+
+        match insert_result {
+            Ok(inserted) => Ok(inserted),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn upsert_tokens_revoked_before(
+        &self,
+        user_id: Uuid,
+        revoked_before: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let upsert_result = diesel::insert_into(user_token_revocations::table)
+            .values((
+                user_token_revocations::user_id.eq(SqliteUuid(user_id)),
+                user_token_revocations::revoked_before.eq(revoked_before),
+            ))
+            .on_conflict(user_token_revocations::user_id)
+            .do_update()
+            .set(user_token_revocations::revoked_before.eq(max(
+                user_token_revocations::revoked_before,
+                excluded(user_token_revocations::revoked_before),
+            )))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to store token revocation time in DB");
+
+        match upsert_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn is_token_revoked(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        session_id: Uuid,
+        issued_at: DateTime<Utc>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let get_result = diesel::select(
+            exists(revoked_tokens::table.filter(revoked_tokens::jti.eq(SqliteUuid(jti))))
+                .or(exists(
+                    user_token_revocations::table
+                        .filter(user_token_revocations::user_id.eq(SqliteUuid(user_id)))
+                        .filter(user_token_revocations::revoked_before.gt(issued_at)),
+                ))
+                .or(exists(
+                    refresh_tokens::table
+                        .filter(refresh_tokens::family_id.eq(SqliteUuid(session_id)))
+                        .filter(refresh_tokens::revoked_at.is_not_null()),
+                ))
+                .or(not(exists(
+                    users::table.filter(users::id.eq(SqliteUuid(user_id))),
+                ))),
+        )
+        .get_result::<bool>(&mut *conn)
+        .await
+        .map_err(log_error_with_context)
+        .context("failed to check token revocation in DB");
+
+        match get_result {
+            Ok(is_revoked) => Ok(is_revoked),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: Uuid) -> Result<usize, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let revoke_result = diesel::update(refresh_tokens::table)
+            .filter(refresh_tokens::family_id.eq(SqliteUuid(family_id)))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .set(refresh_tokens::revoked_at.eq(Utc::now()))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to revoke refresh token family in DB");
+
+        match revoke_result {
+            Ok(revoked) => Ok(revoked),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn revoke_refresh_tokens_issued_before(
+        &self,
+        user_id: Uuid,
+        issued_before: DateTime<Utc>,
+    ) -> Result<usize, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let issued = diesel::alias!(refresh_tokens as issued);
+        let families_issued_before = issued
+            .select(issued.field(refresh_tokens::family_id))
+            .filter(
+                issued
+                    .field(refresh_tokens::user_id)
+                    .eq(SqliteUuid(user_id)),
+            )
+            .filter(issued.field(refresh_tokens::created_at).lt(issued_before));
+
+        let revoke_result = diesel::update(refresh_tokens::table)
+            .filter(refresh_tokens::family_id.eq_any(families_issued_before))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .set(refresh_tokens::revoked_at.eq(Utc::now()))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to revoke user's refresh tokens in DB");
+
+        match revoke_result {
+            Ok(revoked) => Ok(revoked),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Revokes every refresh token family of the user except `kept_family_id`.
+    async fn revoke_other_refresh_token_families(
+        &self,
+        user_id: Uuid,
+        kept_family_id: Uuid,
+    ) -> Result<usize, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let revoke_result = diesel::update(refresh_tokens::table)
+            .filter(refresh_tokens::user_id.eq(SqliteUuid(user_id)))
+            .filter(refresh_tokens::family_id.ne(SqliteUuid(kept_family_id)))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .set(refresh_tokens::revoked_at.eq(Utc::now()))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to revoke user's other refresh tokens in DB");
+
+        match revoke_result {
+            Ok(revoked) => Ok(revoked),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Counts an attempt at answering the MFA challenge `jti` and returns how many there were so
+    /// far, the current one included.
+    async fn increment_mfa_attempts(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<i32, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let increment_result = async {
+            let attempts = diesel::insert_into(mfa_challenge_attempts::table)
+                .values((
+                    mfa_challenge_attempts::jti.eq(SqliteUuid(jti)),
+                    mfa_challenge_attempts::user_id.eq(SqliteUuid(user_id)),
+                    mfa_challenge_attempts::attempts.eq(1),
+                    mfa_challenge_attempts::expires_at.eq(expires_at),
+                ))
+                .on_conflict(mfa_challenge_attempts::jti)
+                .do_update()
+                .set(mfa_challenge_attempts::attempts.eq(mfa_challenge_attempts::attempts + 1))
+                .returning(mfa_challenge_attempts::attempts)
+                .get_result::<i32>(&mut *conn)
+                .await?;
+
+            // challenges are short-lived, so their attempts are pruned the same way as revoked tokens
+            diesel::delete(mfa_challenge_attempts::table)
+                .filter(mfa_challenge_attempts::expires_at.lt(Utc::now()))
+                .execute(&mut *conn)
+                .await?;
+
+            Ok::<_, diesel::result::Error>(attempts)
+        }
+        .await
+        .map_err(log_error_with_context)
+        .context("failed to count MFA attempt in DB");
+
+        match increment_result {
+            Ok(attempts) => Ok(attempts),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::refresh_token::RefreshToken;
+    use crate::models::user::User;
+    use crate::repository::{RefreshTokenRepository, UserRepository};
+    use chrono::Duration;
+
+    #[actix_web::test]
+    async fn is_token_revoked_should_only_revoke_tokens_issued_before_cutoff() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        let user_id = insert_test_user(&repo).await;
+        let now = Utc::now();
+        repo.upsert_tokens_revoked_before(user_id, now)
+            .await
+            .unwrap();
+
+        // Act
+
+        let old_token = repo
+            .is_token_revoked(
+                Uuid::new_v4(),
+                user_id,
+                Uuid::new_v4(),
+                now - Duration::milliseconds(1),
+            )
+            .await
+            .unwrap();
+        let new_token = repo
+            .is_token_revoked(Uuid::new_v4(), user_id, Uuid::new_v4(), now)
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert!(old_token);
+        assert!(!new_token);
+    }
+
+    #[actix_web::test]
+    async fn upsert_tokens_revoked_before_should_keep_later_cutoff() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        let user_id = insert_test_user(&repo).await;
+        let now = Utc::now();
+        repo.upsert_tokens_revoked_before(user_id, now)
+            .await
+            .unwrap();
+
+        // Act
+
+        repo.upsert_tokens_revoked_before(user_id, now - Duration::minutes(5))
+            .await
+            .unwrap();
+
+        // Assert
+
+        let actual = repo
+            .is_token_revoked(
+                Uuid::new_v4(),
+                user_id,
+                Uuid::new_v4(),
+                now - Duration::minutes(1),
+            )
+            .await
+            .unwrap();
+        assert!(actual);
+    }
+
+    #[actix_web::test]
+    async fn is_token_revoked_should_revoke_tokens_of_deleted_user() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        let user_id = insert_test_user(&repo).await;
+        let family_id = insert_test_refresh_token(&repo, user_id).await;
+        repo.delete_user(user_id).await.unwrap();
+
+        // Act
+
+        let actual = repo
+            .is_token_revoked(Uuid::new_v4(), user_id, family_id, Utc::now())
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert!(actual);
+    }
+
+    #[actix_web::test]
+    async fn revoke_other_refresh_token_families_should_keep_given_family() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        let user_id = insert_test_user(&repo).await;
+        let kept_family_id = insert_test_refresh_token(&repo, user_id).await;
+        let other_family_id = insert_test_refresh_token(&repo, user_id).await;
+
+        // Act
+
+        let actual = repo
+            .revoke_other_refresh_token_families(user_id, kept_family_id)
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert_eq!(actual, 1);
+        let now = Utc::now();
+        assert!(!repo
+            .is_token_revoked(Uuid::new_v4(), user_id, kept_family_id, now)
+            .await
+            .unwrap());
+        assert!(repo
+            .is_token_revoked(Uuid::new_v4(), user_id, other_family_id, now)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn insert_revoked_token_should_return_false_when_token_was_revoked_already() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        let user_id = insert_test_user(&repo).await;
+        let jti = Uuid::new_v4();
+        let expires_at = Utc::now() + Duration::minutes(5);
+        let first = repo
+            .insert_revoked_token(jti, user_id, expires_at)
+            .await
+            .unwrap();
+
+        // Act
+
+        let actual = repo
+            .insert_revoked_token(jti, user_id, expires_at)
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert!(first);
+        assert!(!actual);
+    }
+
+    #[actix_web::test]
+    async fn increment_mfa_attempts_should_count_attempts_per_challenge() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        let user_id = insert_test_user(&repo).await;
+        let jti = Uuid::new_v4();
+        let expires_at = Utc::now() + Duration::minutes(5);
+        repo.increment_mfa_attempts(jti, user_id, expires_at)
+            .await
+            .unwrap();
+
+        // Act
+
+        let actual = repo
+            .increment_mfa_attempts(jti, user_id, expires_at)
+            .await
+            .unwrap();
+        let other_challenge = repo
+            .increment_mfa_attempts(Uuid::new_v4(), user_id, expires_at)
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert_eq!(actual, 2);
+        assert_eq!(other_challenge, 1);
+    }
+
+    /// Starts a new refresh token family, which is returned.
+    async fn insert_test_refresh_token(repo: &SqliteRepository, user_id: Uuid) -> Uuid {
+        let family_id = Uuid::new_v4();
+        repo.insert_refresh_token(RefreshToken {
+            id: Uuid::new_v4(),
+            user_id,
+            family_id,
+            token_hash: family_id.as_bytes().to_vec(),
+            expires_at: Utc::now() + Duration::minutes(5),
+            created_at: Utc::now(),
+            rotated_at: None,
+            revoked_at: None,
+        })
+        .await
+        .unwrap();
+
+        family_id
+    }
+
+    async fn insert_test_user(repo: &SqliteRepository) -> Uuid {
+        let user = repo
+            .insert_user(User {
+                id: Uuid::new_v4(),
+                name: "John Doe".to_string(),
+                email: "john.doe@example.com".to_string(),
+                password_hash: b"hash".to_vec(),
+                email_verified_at: None,
+            })
+            .await
+            .unwrap();
+
+        user.id
+    }
+}
@@ -0,0 +1,126 @@
+// The tables of `crate::schema` as SQLite stores them, created by `migrations_sqlite`.
+// Ids are kept as text, hashes and secrets as blobs and timestamps as ISO 8601 text.
+
+diesel::table! {
+    mfa_challenge_attempts (jti) {
+        jti -> Text,
+        user_id -> Text,
+        attempts -> Integer,
+        expires_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    password_reset_tokens (id) {
+        id -> Text,
+        user_id -> Text,
+        token_hash -> Binary,
+        expires_at -> TimestamptzSqlite,
+        created_at -> TimestamptzSqlite,
+        used_at -> Nullable<TimestamptzSqlite>,
+    }
+}
+
+diesel::table! {
+    recovery_codes (id) {
+        id -> Text,
+        user_id -> Text,
+        code_hash -> Binary,
+        used_at -> Nullable<TimestamptzSqlite>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Text,
+        user_id -> Text,
+        family_id -> Text,
+        token_hash -> Binary,
+        expires_at -> TimestamptzSqlite,
+        created_at -> TimestamptzSqlite,
+        rotated_at -> Nullable<TimestamptzSqlite>,
+        revoked_at -> Nullable<TimestamptzSqlite>,
+    }
+}
+
+diesel::table! {
+    revoked_tokens (jti) {
+        jti -> Text,
+        user_id -> Text,
+        expires_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    roles (name) {
+        name -> Text,
+        description -> Text,
+    }
+}
+
+diesel::table! {
+    user_roles (user_id, role) {
+        user_id -> Text,
+        role -> Text,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    signing_key_promotions (kid) {
+        kid -> Text,
+        promoted_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    user_token_revocations (user_id) {
+        user_id -> Text,
+        revoked_before -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    user_totp (user_id) {
+        user_id -> Text,
+        secret_ciphertext -> Binary,
+        confirmed_at -> Nullable<TimestamptzSqlite>,
+        last_used_step -> Nullable<BigInt>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Text,
+        name -> Text,
+        email -> Text,
+        password_hash -> Binary,
+        email_verified_at -> Nullable<TimestamptzSqlite>,
+    }
+}
+
+diesel::joinable!(mfa_challenge_attempts -> users (user_id));
+diesel::joinable!(password_reset_tokens -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(revoked_tokens -> users (user_id));
+diesel::joinable!(user_roles -> roles (role));
+diesel::joinable!(user_roles -> users (user_id));
+diesel::joinable!(user_token_revocations -> users (user_id));
+diesel::joinable!(user_totp -> users (user_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    mfa_challenge_attempts,
+    password_reset_tokens,
+    recovery_codes,
+    refresh_tokens,
+    revoked_tokens,
+    roles,
+    signing_key_promotions,
+    user_roles,
+    user_token_revocations,
+    user_totp,
+    users,
+);
@@ -0,0 +1,101 @@
+use super::schema::signing_key_promotions;
+use super::schema::signing_key_promotions::{kid, promoted_at};
+use super::{log_error_with_context, SqliteRepository};
+use crate::models::signing_key_promotion::SigningKeyPromotion;
+use crate::repository::SigningKeyRepository;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use log::error;
+
+#[async_trait]
+impl SigningKeyRepository for SqliteRepository {
+    async fn upsert_signing_key_promotion(
+        &self,
+        to_upsert: SigningKeyPromotion,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let upsert_result = diesel::insert_into(signing_key_promotions::table)
+            .values((kid.eq(to_upsert.kid), promoted_at.eq(to_upsert.promoted_at)))
+            .on_conflict(kid)
+            .do_update()
+            .set(promoted_at.eq(to_upsert.promoted_at))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to upsert signing key promotion in DB");
+
+        match upsert_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_signing_key_promotions(&self) -> Result<Vec<SigningKeyPromotion>, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let get_result = signing_key_promotions::table
+            .select((kid, promoted_at))
+            .order((promoted_at, kid))
+            .load::<(String, DateTime<Utc>)>(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to get signing key promotions from DB");
+
+        match get_result {
+            Ok(promotions) => Ok(promotions
+                .into_iter()
+                .map(|(kid_, promoted_at_)| SigningKeyPromotion {
+                    kid: kid_,
+                    promoted_at: promoted_at_,
+                })
+                .collect()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[actix_web::test]
+    async fn get_signing_key_promotions_should_return_latest_promotion_of_every_key_oldest_first() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        let now = Utc::now();
+        for (kid_, promoted_at_) in [
+            ("first", now - Duration::hours(2)),
+            ("second", now - Duration::hours(1)),
+            ("first", now),
+        ] {
+            repo.upsert_signing_key_promotion(SigningKeyPromotion {
+                kid: kid_.to_string(),
+                promoted_at: promoted_at_,
+            })
+            .await
+            .unwrap();
+        }
+
+        // Act
+
+        let actual = repo.get_signing_key_promotions().await.unwrap();
+
+        // Assert
+
+        let kids: Vec<_> = actual.iter().map(|p| p.kid.as_str()).collect();
+        assert_eq!(kids, ["second", "first"]);
+        assert_eq!(actual[1].promoted_at, now);
+    }
+}
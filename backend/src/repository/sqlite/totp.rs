@@ -0,0 +1,155 @@
+use super::schema::user_totp::{confirmed_at, last_used_step, user_id};
+use super::schema::{recovery_codes, user_totp};
+use super::{coalesce, log_error_with_context, SqliteRepository, SqliteUuid};
+use crate::models::totp::UserTotp;
+use crate::repository::TotpRepository;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use log::error;
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = user_totp)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+struct UserTotpRow {
+    user_id: SqliteUuid,
+    secret_ciphertext: Vec<u8>,
+    confirmed_at: Option<DateTime<Utc>>,
+    last_used_step: Option<i64>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<UserTotp> for UserTotpRow {
+    fn from(value: UserTotp) -> Self {
+        Self {
+            user_id: value.user_id.into(),
+            secret_ciphertext: value.secret_ciphertext,
+            confirmed_at: value.confirmed_at,
+            last_used_step: value.last_used_step,
+            created_at: value.created_at,
+        }
+    }
+}
+
+impl From<UserTotpRow> for UserTotp {
+    fn from(value: UserTotpRow) -> Self {
+        Self {
+            user_id: value.user_id.into(),
+            secret_ciphertext: value.secret_ciphertext,
+            confirmed_at: value.confirmed_at,
+            last_used_step: value.last_used_step,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[async_trait]
+impl TotpRepository for SqliteRepository {
+    async fn upsert_user_totp(&self, to_upsert: UserTotp) -> Result<(), anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let upsert_result = diesel::insert_into(user_totp::table)
+            .values(UserTotpRow::from(to_upsert))
+            .on_conflict(user_id)
+            .do_update()
+            .set((
+                user_totp::secret_ciphertext.eq(excluded(user_totp::secret_ciphertext)),
+                confirmed_at.eq(excluded(confirmed_at)),
+                last_used_step.eq(excluded(last_used_step)),
+                user_totp::created_at.eq(excluded(user_totp::created_at)),
+            ))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to store TOTP secret in DB");
+
+        match upsert_result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_user_totp(&self, user_id_: Uuid) -> Result<Option<UserTotp>, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let get_result = user_totp::table
+            .select(UserTotpRow::as_select())
+            .filter(user_id.eq(SqliteUuid(user_id_)))
+            .first(&mut *conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to retrieve TOTP secret from DB");
+
+        match get_result {
+            Ok(maybe_totp) => Ok(maybe_totp.map(UserTotp::from)),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn use_totp_step(&self, user_id_: Uuid, step: i64) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let update_result = diesel::update(user_totp::table)
+            .filter(user_id.eq(SqliteUuid(user_id_)))
+            .filter(last_used_step.is_null().or(last_used_step.lt(step)))
+            .set((
+                last_used_step.eq(step),
+                confirmed_at.eq(coalesce(confirmed_at, Utc::now())),
+            ))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to record used TOTP code in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated == 1),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn delete_user_totp(&self, user_id_: Uuid) -> Result<usize, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let delete_result = conn
+            .transaction::<usize, diesel::result::Error, _>(|conn| {
+                async move {
+                    diesel::delete(recovery_codes::table)
+                        .filter(recovery_codes::user_id.eq(SqliteUuid(user_id_)))
+                        .execute(conn)
+                        .await?;
+
+                    diesel::delete(user_totp::table)
+                        .filter(user_id.eq(SqliteUuid(user_id_)))
+                        .execute(conn)
+                        .await
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to delete TOTP secret from DB");
+
+        match delete_result {
+            Ok(deleted) => Ok(deleted),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use super::schema::users;
+use super::schema::users::{email, email_verified_at, id, password_hash};
+use super::{coalesce, log_error_with_context, SqliteRepository, SqliteUuid};
+use crate::models::user::{User, UserProfileChanges};
+use crate::repository::UserRepository;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use log::error;
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+struct UserRow {
+    id: SqliteUuid,
+    name: String,
+    email: String,
+    password_hash: Vec<u8>,
+    email_verified_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for UserRow {
+    fn from(value: User) -> Self {
+        Self {
+            id: value.id.into(),
+            name: value.name,
+            email: value.email,
+            password_hash: value.password_hash,
+            email_verified_at: value.email_verified_at,
+        }
+    }
+}
+
+impl From<UserRow> for User {
+    fn from(value: UserRow) -> Self {
+        Self {
+            id: value.id.into(),
+            name: value.name,
+            email: value.email,
+            password_hash: value.password_hash,
+            email_verified_at: value.email_verified_at,
+        }
+    }
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = users)]
+struct UserProfileChangesRow {
+    name: Option<String>,
+}
+
+impl From<UserProfileChanges> for UserProfileChangesRow {
+    fn from(value: UserProfileChanges) -> Self {
+        Self { name: value.name }
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteRepository {
+    async fn insert_user(&self, to_insert: User) -> Result<User, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let insert_result = diesel::insert_into(users::table)
+            .values(UserRow::from(to_insert))
+            .returning(UserRow::as_returning())
+            .get_result(&mut *conn)
+            .await
+            .map_err(|diesel_error| {
+                let err = log_error_with_context(diesel_error);
+                anyhow::Error::from(err)
+            })
+            .context("failed to insert new user to DB");
+
+        match insert_result {
+            Ok(u) => Ok(User::from(u)),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_user(&self, user_id: Uuid) -> Result<Option<User>, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let get_result = users::table
+            .select(UserRow::as_select())
+            .filter(id.eq(SqliteUuid(user_id)))
+            .first(&mut *conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to get user from DB");
+
+        match get_result {
+            Ok(maybe_user) => Ok(maybe_user.map(User::from)),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_user_by_email(&self, email_: &str) -> Result<Option<User>, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let get_result = users::table
+            .select(UserRow::as_select())
+            .filter(email.eq(email_))
+            .first(&mut *conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to get user by email from DB");
+
+        match get_result {
+            Ok(maybe_user) => Ok(maybe_user.map(User::from)),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn mark_email_verified(
+        &self,
+        user_id: Uuid,
+        email_: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let update_result = diesel::update(users::table)
+            .filter(id.eq(SqliteUuid(user_id)))
+            .filter(email.eq(email_))
+            .set(email_verified_at.eq(coalesce(email_verified_at, Utc::now())))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to mark email as verified in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn update_password_hash(
+        &self,
+        user_id: Uuid,
+        old_password_hash: Vec<u8>,
+        new_password_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let update_result = diesel::update(users::table)
+            .filter(id.eq(SqliteUuid(user_id)))
+            .filter(password_hash.eq(old_password_hash))
+            .set(password_hash.eq(new_password_hash))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to update password hash in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn set_password_hash(
+        &self,
+        user_id: Uuid,
+        new_password_hash: Vec<u8>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let update_result = diesel::update(users::table)
+            .filter(id.eq(SqliteUuid(user_id)))
+            .set(password_hash.eq(new_password_hash))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to set password hash in DB");
+
+        match update_result {
+            Ok(updated) => Ok(updated > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        changes: UserProfileChanges,
+    ) -> Result<Option<User>, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let update_result = diesel::update(users::table)
+            .filter(id.eq(SqliteUuid(user_id)))
+            .set(UserProfileChangesRow::from(changes))
+            .returning(UserRow::as_returning())
+            .get_result(&mut *conn)
+            .await
+            .optional()
+            .map_err(log_error_with_context)
+            .context("failed to update user profile in DB");
+
+        match update_result {
+            Ok(maybe_user) => Ok(maybe_user.map(User::from)),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let get_result = users::table
+            .select(UserRow::as_select())
+            .load(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to get users from DB");
+
+        match get_result {
+            Ok(us) => Ok(us.into_iter().map(User::from).collect()),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool, anyhow::Error> {
+        let mut conn = self.lock().await;
+
+        let delete_result = diesel::delete(users::table)
+            .filter(id.eq(SqliteUuid(user_id)))
+            .execute(&mut *conn)
+            .await
+            .map_err(log_error_with_context)
+            .context("failed to delete user from DB");
+
+        match delete_result {
+            Ok(deleted) => Ok(deleted > 0),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn get_user_should_return_inserted_user() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        let user = get_test_user("john.doe@example.com");
+        repo.insert_user(user.clone()).await.unwrap();
+
+        // Act
+
+        let actual = repo.get_user(user.id).await.unwrap().unwrap();
+
+        // Assert
+
+        assert_eq!(actual.id, user.id);
+        assert_eq!(actual.email, user.email);
+        assert_eq!(actual.password_hash, user.password_hash);
+        assert_eq!(actual.email_verified_at, user.email_verified_at);
+    }
+
+    #[actix_web::test]
+    async fn insert_user_should_return_unique_violation_when_email_already_exists() {
+        // Arrange
+
+        let repo = SqliteRepository::connect(":memory:").unwrap();
+        repo.insert_user(get_test_user("john.doe@example.com"))
+            .await
+            .unwrap();
+
+        // Act
+
+        let actual = match repo
+            .insert_user(get_test_user("john.doe@example.com"))
+            .await
+        {
+            Ok(_) => panic!(),
+            Err(e) => e,
+        };
+
+        // Assert
+
+        assert!(matches!(
+            actual.downcast_ref::<diesel::result::Error>(),
+            Some(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _
+            ))
+        ));
+    }
+
+    fn get_test_user(email_: &str) -> User {
+        User {
+            id: Uuid::new_v4(),
+            name: "John Doe".to_string(),
+            email: email_.to_string(),
+            password_hash: b"hash".to_vec(),
+            email_verified_at: Some(Utc::now()),
+        }
+    }
+}
@@ -1,10 +1,125 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    mfa_challenge_attempts (jti) {
+        jti -> Uuid,
+        user_id -> Uuid,
+        attempts -> Integer,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    password_reset_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Bytea,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    recovery_codes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Bytea,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        family_id -> Uuid,
+        token_hash -> Bytea,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+        rotated_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    revoked_tokens (jti) {
+        jti -> Uuid,
+        user_id -> Uuid,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    roles (name) {
+        name -> Text,
+        description -> Text,
+    }
+}
+
+diesel::table! {
+    user_roles (user_id, role) {
+        user_id -> Uuid,
+        role -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    signing_key_promotions (kid) {
+        kid -> Text,
+        promoted_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_token_revocations (user_id) {
+        user_id -> Uuid,
+        revoked_before -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_totp (user_id) {
+        user_id -> Uuid,
+        secret_ciphertext -> Bytea,
+        confirmed_at -> Nullable<Timestamptz>,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Uuid,
         name -> Text,
         email -> Text,
         password_hash -> Bytea,
+        email_verified_at -> Nullable<Timestamptz>,
     }
 }
+
+diesel::joinable!(mfa_challenge_attempts -> users (user_id));
+diesel::joinable!(password_reset_tokens -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(revoked_tokens -> users (user_id));
+diesel::joinable!(user_roles -> roles (role));
+diesel::joinable!(user_roles -> users (user_id));
+diesel::joinable!(user_token_revocations -> users (user_id));
+diesel::joinable!(user_totp -> users (user_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    mfa_challenge_attempts,
+    password_reset_tokens,
+    recovery_codes,
+    refresh_tokens,
+    revoked_tokens,
+    roles,
+    signing_key_promotions,
+    user_roles,
+    user_token_revocations,
+    user_totp,
+    users,
+);
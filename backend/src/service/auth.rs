@@ -1,16 +1,32 @@
-use crate::models::claims::Claims;
+use crate::models::claims::{Claims, EmailVerificationClaims, MfaChallengeClaims};
+use crate::models::signing_key_promotion::SigningKeyPromotion;
+use crate::service::passwords::verify_password;
 use crate::service::AuthService;
 use anyhow::Context;
-use chrono::Utc;
-use jsonwebtoken::{
-    decode, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
-};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, encode, Header, TokenData, Validation};
 use log::error;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::time::Duration;
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
+const OPAQUE_TOKEN_LENGTH_IN_BYTES: usize = 32;
+
 impl AuthService {
-    pub fn generate_token(&self, user_id: Uuid) -> Result<String, anyhow::Error> {
+    pub fn generate_token(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+        roles: Vec<String>,
+    ) -> Result<String, anyhow::Error> {
         let now = Utc::now();
         let expires_in = now + Duration::from_secs(self.options.token_expiration_in_seconds);
 
@@ -19,34 +35,142 @@ impl AuthService {
             sub: user_id,
             exp: expires_in.timestamp(),
             iat: now.timestamp(),
+            iat_us: Some(now.timestamp_micros()),
+            jti: Uuid::new_v4(),
+            sid: session_id,
+            roles,
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.options.encoding_key.as_bytes()),
-        )
-        .map_err(|e| {
-            error!("{}", e.to_string());
-            e
-        })
-        .context("failed to encode JWT")
+        self.encode_token(&claims)
     }
 
     pub fn verify_token(&self, token: String) -> jsonwebtoken::errors::Result<TokenData<Claims>> {
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.set_audience(&[self.options.audience.clone()]);
+        self.decode_token(&token, &self.options.audience)
+    }
+
+    pub fn generate_mfa_challenge_token(&self, user_id: Uuid) -> Result<String, anyhow::Error> {
+        let now = Utc::now();
+        let expires_in =
+            now + Duration::from_secs(self.options.mfa_challenge_expiration_in_seconds);
+
+        let claims = MfaChallengeClaims {
+            aud: self.get_mfa_challenge_audience(),
+            sub: user_id,
+            exp: expires_in.timestamp(),
+            iat: now.timestamp(),
+            iat_us: Some(now.timestamp_micros()),
+            jti: Uuid::new_v4(),
+        };
+
+        self.encode_token(&claims)
+    }
+
+    pub fn verify_mfa_challenge_token(
+        &self,
+        token: &str,
+    ) -> jsonwebtoken::errors::Result<TokenData<MfaChallengeClaims>> {
+        self.decode_token(token, &self.get_mfa_challenge_audience())
+    }
+
+    pub fn generate_email_verification_token(
+        &self,
+        user_id: Uuid,
+        email: String,
+    ) -> Result<String, anyhow::Error> {
+        let now = Utc::now();
+        let expires_in =
+            now + Duration::from_secs(self.options.email_verification_expiration_in_seconds);
+
+        let claims = EmailVerificationClaims {
+            aud: self.get_email_verification_audience(),
+            sub: user_id,
+            exp: expires_in.timestamp(),
+            iat: now.timestamp(),
+            email,
+        };
+
+        self.encode_token(&claims)
+    }
+
+    pub fn verify_email_verification_token(
+        &self,
+        token: &str,
+    ) -> jsonwebtoken::errors::Result<TokenData<EmailVerificationClaims>> {
+        self.decode_token(token, &self.get_email_verification_audience())
+    }
+
+    pub fn get_jwks(&self) -> JwkSet {
+        self.options.keyring.get_jwks()
+    }
+
+    pub fn has_signing_key(&self, kid: &str) -> bool {
+        self.options.keyring.contains_key(kid)
+    }
+
+    pub fn apply_signing_key_promotions(&self, promotions: &[SigningKeyPromotion]) {
+        self.options.keyring.apply_promotions(promotions)
+    }
+
+    pub fn is_admin_key(&self, presented_key: &str) -> bool {
+        match &self.options.admin_api_key {
+            None => false,
+            // compare digests so that neither the contents nor the length of the key leak through timing
+            Some(admin_api_key) => Sha256::digest(presented_key.as_bytes())
+                .ct_eq(&Sha256::digest(admin_api_key.as_bytes()))
+                .into(),
+        }
+    }
 
-        decode::<Claims>(
-            &token,
-            &DecodingKey::from_secret(self.options.encoding_key.as_bytes()),
-            &validation,
-        )
+    fn encode_token<T: Serialize>(&self, claims: &T) -> Result<String, anyhow::Error> {
+        let (kid, signing_key) = self.options.keyring.get_active_key();
+        let mut header = Header::new(signing_key.algorithm());
+        header.kid = Some(kid);
+
+        encode(&header, claims, signing_key.encoding_key())
+            .inspect_err(|e| error!("{}", e))
+            .context("failed to encode JWT")
+    }
+
+    fn decode_token<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        audience: &str,
+    ) -> jsonwebtoken::errors::Result<TokenData<T>> {
+        let signing_key = decode_header(token)?
+            .kid
+            .and_then(|kid| self.options.keyring.get_verification_key(&kid))
+            .ok_or(ErrorKind::InvalidToken)?;
+
+        let mut validation = Validation::new(signing_key.algorithm());
+        validation.set_audience(&[audience]);
+
+        decode::<T>(token, signing_key.decoding_key(), &validation)
+    }
+
+    fn get_mfa_challenge_audience(&self) -> String {
+        format!("{}/mfa", self.options.audience)
+    }
+
+    fn get_email_verification_audience(&self) -> String {
+        format!("{}/verify-email", self.options.audience)
+    }
+
+    /// Returns a new opaque refresh token together with the hash that should be persisted.
+    pub fn generate_refresh_token(&self) -> (String, Vec<u8>) {
+        generate_opaque_token()
+    }
+
+    pub fn hash_refresh_token(&self, token: &str) -> Vec<u8> {
+        hash_opaque_token(token)
+    }
+
+    pub fn get_refresh_token_expiration(&self) -> DateTime<Utc> {
+        Utc::now() + Duration::from_secs(self.options.refresh_token_expiration_in_seconds)
     }
 
     pub fn hash_password(&self, password: String) -> Result<Vec<u8>, anyhow::Error> {
-        let hashed = bcrypt::hash(password, 12).context("failed to hash user's password")?;
-        Ok(hashed.as_bytes().to_vec())
+        let hashed = self.options.password_hash_scheme.hash(&password)?;
+        Ok(hashed.into_bytes())
     }
 
     pub fn compare_hash_and_password(
@@ -56,8 +180,263 @@ impl AuthService {
     ) -> Result<bool, anyhow::Error> {
         let hash_as_str =
             std::str::from_utf8(&hash_bytes).context("failed to convert hash bytes to string")?;
-        let verification_result =
-            bcrypt::verify(password, hash_as_str).context("failed to verify hash with password")?;
-        Ok(verification_result)
+        verify_password(&password, hash_as_str)
+    }
+
+    /// Whether a stored hash was created with an outdated algorithm or cost.
+    pub fn password_needs_rehash(&self, hash_bytes: &[u8]) -> bool {
+        std::str::from_utf8(hash_bytes).map_or(true, |hash| {
+            self.options.password_hash_scheme.needs_rehash(hash)
+        })
+    }
+}
+
+pub(super) fn generate_opaque_token() -> (String, Vec<u8>) {
+    let mut bytes = [0u8; OPAQUE_TOKEN_LENGTH_IN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+    let hash = hash_opaque_token(&token);
+
+    (token, hash)
+}
+
+/// Opaque tokens are high-entropy random values, so a fast hash is enough here.
+pub(super) fn hash_opaque_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::keyring::Keyring;
+    use crate::service::keys::SigningKey;
+    use crate::service::{get_test_auth_options, AuthOptions};
+    use jsonwebtoken::Algorithm;
+
+    #[test]
+    fn verify_token_should_accept_token_signed_by_previous_key_after_promotion() {
+        // Arrange
+
+        let auth_service = get_test_auth_service_with_two_keys();
+        let token = auth_service
+            .generate_token(Uuid::new_v4(), Uuid::new_v4(), Vec::new())
+            .unwrap();
+        promote(&auth_service, "second");
+
+        // Act
+
+        let actual = auth_service.verify_token(token);
+
+        // Assert
+
+        assert_eq!(actual.unwrap().header.kid.as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn generate_token_should_use_promoted_key() {
+        // Arrange
+
+        let auth_service = get_test_auth_service_with_two_keys();
+        promote(&auth_service, "second");
+
+        // Act
+
+        let token = auth_service
+            .generate_token(Uuid::new_v4(), Uuid::new_v4(), Vec::new())
+            .unwrap();
+
+        // Assert
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn verify_token_should_return_error_when_kid_is_unknown() {
+        // Arrange
+
+        let token = get_test_auth_service_with_two_keys()
+            .generate_token(Uuid::new_v4(), Uuid::new_v4(), Vec::new())
+            .unwrap();
+
+        // Act
+
+        let actual = get_test_auth_service().verify_token(token);
+
+        // Assert
+
+        assert_eq!(actual.unwrap_err().kind(), &ErrorKind::InvalidToken);
+    }
+
+    #[test]
+    fn verify_mfa_challenge_token_should_return_error_for_access_token() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+        let token = auth_service
+            .generate_token(Uuid::new_v4(), Uuid::new_v4(), Vec::new())
+            .unwrap();
+
+        // Act
+
+        let actual = auth_service.verify_mfa_challenge_token(&token);
+
+        // Assert
+
+        assert_eq!(actual.unwrap_err().kind(), &ErrorKind::InvalidAudience);
+    }
+
+    #[test]
+    fn verify_token_should_return_error_for_mfa_challenge_token() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+        let token = auth_service
+            .generate_mfa_challenge_token(Uuid::new_v4())
+            .unwrap();
+
+        // Act
+
+        let actual = auth_service.verify_token(token);
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn verify_email_verification_token_should_return_claims_of_generated_token() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+        let user_id = Uuid::new_v4();
+        let token = auth_service
+            .generate_email_verification_token(user_id, "test@test.com".to_string())
+            .unwrap();
+
+        // Act
+
+        let actual = auth_service
+            .verify_email_verification_token(&token)
+            .unwrap();
+
+        // Assert
+
+        assert_eq!(actual.claims.sub, user_id);
+        assert_eq!(actual.claims.email, "test@test.com");
+    }
+
+    #[test]
+    fn verify_email_verification_token_should_return_error_for_mfa_challenge_token() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+        let token = auth_service
+            .generate_mfa_challenge_token(Uuid::new_v4())
+            .unwrap();
+
+        // Act
+
+        let actual = auth_service.verify_email_verification_token(&token);
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn is_admin_key_should_only_accept_configured_key() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+
+        // Act & Assert
+
+        assert!(auth_service.is_admin_key("admin"));
+        assert!(!auth_service.is_admin_key("admin2"));
+        assert!(!auth_service.is_admin_key(""));
+    }
+
+    #[test]
+    fn generate_refresh_token_should_return_hash_of_returned_token() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+
+        // Act
+
+        let (token, hash) = auth_service.generate_refresh_token();
+
+        // Assert
+
+        assert_eq!(auth_service.hash_refresh_token(&token), hash);
+    }
+
+    #[test]
+    fn generate_refresh_token_should_return_unique_tokens() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+
+        // Act
+
+        let (first, _) = auth_service.generate_refresh_token();
+        let (second, _) = auth_service.generate_refresh_token();
+
+        // Assert
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn hash_refresh_token_should_differ_for_different_tokens() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+
+        // Act
+
+        let first = auth_service.hash_refresh_token("first-token");
+        let second = auth_service.hash_refresh_token("second-token");
+
+        // Assert
+
+        assert_ne!(first, second);
+    }
+
+    fn get_test_auth_service() -> AuthService {
+        AuthService::new(AuthOptions {
+            admin_api_key: Some("admin".to_string()),
+            ..get_test_auth_options()
+        })
+    }
+
+    fn get_test_auth_service_with_two_keys() -> AuthService {
+        AuthService::new(AuthOptions {
+            keyring: Keyring::new(
+                "first".to_string(),
+                vec![
+                    (
+                        "first".to_string(),
+                        SigningKey::from_secret(Algorithm::HS256, b"first").unwrap(),
+                    ),
+                    (
+                        "second".to_string(),
+                        SigningKey::from_secret(Algorithm::HS256, b"second").unwrap(),
+                    ),
+                ],
+                Duration::from_secs(100),
+            )
+            .unwrap(),
+            ..get_test_auth_options()
+        })
+    }
+
+    fn promote(auth_service: &AuthService, kid: &str) {
+        auth_service.apply_signing_key_promotions(&[SigningKeyPromotion {
+            kid: kid.to_string(),
+            promoted_at: Utc::now(),
+        }]);
     }
 }
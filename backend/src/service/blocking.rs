@@ -0,0 +1,92 @@
+use crate::errors::Error;
+use actix_web::web;
+use anyhow::anyhow;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Runs password hashing and other blocking work, like sending mail, off the actix worker threads,
+/// which would otherwise stall every other request handled by the same worker until it is done.
+///
+/// At most `max_concurrent_tasks` closures run at once, the rest wait for a free slot
+/// without blocking anything.
+#[derive(Debug, Clone)]
+pub struct BlockingPool {
+    semaphore: Arc<Semaphore>,
+}
+
+impl BlockingPool {
+    pub fn new(max_concurrent_tasks: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent_tasks)),
+        }
+    }
+
+    pub async fn run<F, T>(&self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<T, Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let _permit = self
+            .semaphore
+            .acquire()
+            .await
+            .map_err(|e| anyhow!("blocking pool has been closed. reason: {}", e))?;
+
+        web::block(f)
+            .await
+            .map_err(|e| anyhow!("blocking task failed. reason: {}", e))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn run_should_return_result_of_closure() {
+        // Arrange
+
+        let pool = BlockingPool::new(1);
+
+        // Act
+
+        let actual = pool.run(|| Err::<(), _>(Error::InvalidCredentials)).await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::InvalidCredentials)));
+    }
+
+    #[actix_web::test]
+    async fn run_should_not_exceed_max_concurrent_tasks() {
+        // Arrange
+
+        let pool = BlockingPool::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+
+        let tasks = (0..6).map(|_| {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            pool.run(move || {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            })
+        });
+
+        // Act
+
+        let actual = futures::future::join_all(tasks).await;
+
+        // Assert
+
+        assert!(actual.iter().all(Result::is_ok));
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+    }
+}
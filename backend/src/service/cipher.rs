@@ -0,0 +1,169 @@
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use anyhow::{anyhow, bail, Context};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::fmt::{Debug, Formatter};
+
+const NONCE_LENGTH_IN_BYTES: usize = 12;
+const HASH_KEY_CONTEXT: &[u8] = b"secret cipher hash key";
+
+/// Encrypts secrets that have to be stored in a recoverable form, e.g. TOTP secrets, and hashes
+/// the ones that only have to be compared, e.g. recovery codes.
+/// Every ciphertext is bound to its `associated_data`, so it can't be copied over to another row.
+#[derive(Clone)]
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+    /// Keyed with a key derived from the encryption key, so that no key is used by two algorithms.
+    mac: Hmac<Sha256>,
+}
+
+impl SecretCipher {
+    /// Expects a base64 encoded 256 bit key, e.g. the output of `openssl rand -base64 32`.
+    pub fn from_base64(key: &str) -> Result<Self, anyhow::Error> {
+        let key = STANDARD
+            .decode(key.trim())
+            .context("encryption key is not valid base64")?;
+
+        Self::new(&key)
+    }
+
+    pub fn new(key: &[u8]) -> Result<Self, anyhow::Error> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| anyhow!("encryption key must be 32 bytes long"))?;
+
+        let mut key_derivation =
+            <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        key_derivation.update(HASH_KEY_CONTEXT);
+        let mac = <Hmac<Sha256> as Mac>::new_from_slice(&key_derivation.finalize().into_bytes())
+            .expect("HMAC accepts keys of any length");
+
+        Ok(Self { cipher, mac })
+    }
+
+    /// HMAC-SHA256 of `data`. Unlike a plain hash, it can't be brute forced offline without the key.
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        let mut mac = self.mac.clone();
+        mac.update(data);
+
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Returns the random nonce followed by the ciphertext.
+    pub fn encrypt(
+        &self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let mut nonce = [0u8; NONCE_LENGTH_IN_BYTES];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: associated_data,
+                },
+            )
+            .map_err(|_| anyhow!("failed to encrypt secret"))?;
+
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    pub fn decrypt(
+        &self,
+        encrypted: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        if encrypted.len() < NONCE_LENGTH_IN_BYTES {
+            bail!("encrypted secret is too short");
+        }
+
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH_IN_BYTES);
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: associated_data,
+                },
+            )
+            .map_err(|_| anyhow!("failed to decrypt secret"))
+    }
+}
+
+impl Debug for SecretCipher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretCipher").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypt_should_return_encrypted_secret() {
+        // Arrange
+
+        let cipher = SecretCipher::new(&[7u8; 32]).unwrap();
+        let encrypted = cipher.encrypt(b"secret", b"user").unwrap();
+
+        // Act
+
+        let actual = cipher.decrypt(&encrypted, b"user").unwrap();
+
+        // Assert
+
+        assert_eq!(actual, b"secret");
+    }
+
+    #[test]
+    fn decrypt_should_return_error_when_associated_data_differs() {
+        // Arrange
+
+        let cipher = SecretCipher::new(&[7u8; 32]).unwrap();
+        let encrypted = cipher.encrypt(b"secret", b"user").unwrap();
+
+        // Act
+
+        let actual = cipher.decrypt(&encrypted, b"another user");
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn hash_should_depend_on_key() {
+        // Arrange
+
+        let cipher = SecretCipher::new(&[7u8; 32]).unwrap();
+        let other_cipher = SecretCipher::new(&[8u8; 32]).unwrap();
+
+        // Act
+
+        let actual = cipher.hash(b"secret");
+
+        // Assert
+
+        assert_eq!(actual, cipher.hash(b"secret"));
+        assert_ne!(actual, other_cipher.hash(b"secret"));
+    }
+
+    #[test]
+    fn from_base64_should_return_error_when_key_has_wrong_length() {
+        // Act
+
+        let actual = SecretCipher::from_base64(&STANDARD.encode([7u8; 16]));
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+}
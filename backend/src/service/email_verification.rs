@@ -0,0 +1,271 @@
+use super::{AuthService, UserService};
+use crate::api::contracts;
+use crate::errors::Error;
+use crate::mail::templates::MailTemplate;
+use crate::models::user::User;
+use log::{info, warn};
+use std::sync::Arc;
+
+impl AuthService {
+    pub fn get_email_verification_link(&self, token: &str) -> String {
+        format!("{}?token={}", self.options.email_verification_url, token)
+    }
+
+    pub fn is_verified_email_required(&self) -> bool {
+        self.options.require_verified_email
+    }
+
+    fn get_email_verification_expiration_in_hours(&self) -> u64 {
+        self.options
+            .email_verification_expiration_in_seconds
+            .div_ceil(3600)
+    }
+}
+
+impl UserService {
+    pub async fn verify_email(
+        &self,
+        auth_service: Arc<AuthService>,
+        request: contracts::VerifyEmailRequest,
+    ) -> Result<(), Error> {
+        let claims = match auth_service.verify_email_verification_token(&request.token) {
+            Ok(token_data) => token_data.claims,
+            Err(e) => {
+                warn!("failed to verify email verification token. reason: {}", e);
+                return Err(Error::InvalidEmailVerificationToken);
+            }
+        };
+
+        if !self
+            .repo
+            .mark_email_verified(claims.sub, &claims.email)
+            .await?
+        {
+            return Err(Error::InvalidEmailVerificationToken);
+        }
+
+        Ok(())
+    }
+
+    /// Sends another verification link to an unverified user. Like password resets,
+    /// the outcome must not be shown to the caller.
+    pub async fn resend_verification_email(
+        &self,
+        auth_service: Arc<AuthService>,
+        request: contracts::ResendVerificationEmailRequest,
+    ) -> Result<(), Error> {
+        match self.repo.get_user_by_email(&request.email).await? {
+            Some(user) if user.email_verified_at.is_none() => {
+                self.send_verification_email(&auth_service, &user).await
+            }
+            Some(_) => {
+                info!("verification email was requested for an already verified email");
+                Ok(())
+            }
+            None => {
+                info!("verification email was requested for an unknown email");
+                Ok(())
+            }
+        }
+    }
+
+    pub(super) async fn send_verification_email(
+        &self,
+        auth_service: &AuthService,
+        user: &User,
+    ) -> Result<(), Error> {
+        let token = auth_service.generate_email_verification_token(user.id, user.email.clone())?;
+
+        self.send_mail(
+            user.email.clone(),
+            MailTemplate::VerifyEmail {
+                name: user.name.clone(),
+                link: auth_service.get_email_verification_link(&token),
+                expires_in_hours: auth_service.get_email_verification_expiration_in_hours(),
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{
+        get_test_auth_options, get_test_user_service, register_test_user, AuthOptions,
+    };
+
+    #[test]
+    fn get_email_verification_expiration_in_hours_should_round_up() {
+        // Arrange
+
+        let get_expiration_in_hours = |seconds| {
+            AuthService::new(AuthOptions {
+                email_verification_expiration_in_seconds: seconds,
+                ..get_test_auth_options()
+            })
+            .get_email_verification_expiration_in_hours()
+        };
+
+        // Act
+
+        let actual = [1800, 3600, 5400].map(get_expiration_in_hours);
+
+        // Assert
+
+        assert_eq!(actual, [1, 1, 2]);
+    }
+
+    #[actix_web::test]
+    async fn verify_email_should_mark_email_verified() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let user = register_test_user(&user_service, &auth_service).await;
+        let token = auth_service
+            .generate_email_verification_token(user.id, user.email.clone())
+            .unwrap();
+
+        // Act
+
+        user_service
+            .verify_email(
+                auth_service.clone(),
+                contracts::VerifyEmailRequest { token },
+            )
+            .await
+            .unwrap();
+
+        // Assert
+
+        let stored = user_service.repo.get_user(user.id).await.unwrap().unwrap();
+        assert!(stored.email_verified_at.is_some());
+    }
+
+    #[actix_web::test]
+    async fn verify_email_should_return_error_for_mfa_challenge_token() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let user = register_test_user(&user_service, &auth_service).await;
+        let token = auth_service.generate_mfa_challenge_token(user.id).unwrap();
+
+        // Act
+
+        let actual = user_service
+            .verify_email(
+                auth_service.clone(),
+                contracts::VerifyEmailRequest { token },
+            )
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::InvalidEmailVerificationToken)));
+        let stored = user_service.repo.get_user(user.id).await.unwrap().unwrap();
+        assert!(stored.email_verified_at.is_none());
+    }
+
+    #[actix_web::test]
+    async fn verify_email_should_return_error_when_email_has_changed() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let user = register_test_user(&user_service, &auth_service).await;
+        let token = auth_service
+            .generate_email_verification_token(user.id, "jane.doe@example.com".to_string())
+            .unwrap();
+
+        // Act
+
+        let actual = user_service
+            .verify_email(
+                auth_service.clone(),
+                contracts::VerifyEmailRequest { token },
+            )
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::InvalidEmailVerificationToken)));
+    }
+
+    #[actix_web::test]
+    async fn resend_verification_email_should_send_working_link_to_unverified_user() {
+        // Arrange
+
+        let (user_service, mailer) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let user = register_test_user(&user_service, &auth_service).await;
+
+        // Act
+
+        user_service
+            .resend_verification_email(
+                auth_service.clone(),
+                contracts::ResendVerificationEmailRequest {
+                    email: user.email.clone(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Assert
+
+        let sent = mailer.get_sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].to, user.email);
+        user_service
+            .verify_email(
+                auth_service.clone(),
+                contracts::VerifyEmailRequest {
+                    token: get_token_from_link(&sent[1].text_body),
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn resend_verification_email_should_not_send_mail_to_verified_user() {
+        // Arrange
+
+        let (user_service, mailer) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let user = register_test_user(&user_service, &auth_service).await;
+        user_service
+            .repo
+            .mark_email_verified(user.id, &user.email)
+            .await
+            .unwrap();
+
+        // Act
+
+        user_service
+            .resend_verification_email(
+                auth_service.clone(),
+                contracts::ResendVerificationEmailRequest {
+                    email: user.email.clone(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert_eq!(mailer.get_sent().len(), 1);
+    }
+
+    fn get_token_from_link(body: &str) -> String {
+        body.split("?token=")
+            .nth(1)
+            .and_then(|rest| rest.split_whitespace().next())
+            .unwrap()
+            .to_string()
+    }
+}
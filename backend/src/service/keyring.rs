@@ -0,0 +1,299 @@
+use crate::models::signing_key_promotion::SigningKeyPromotion;
+use crate::service::keys::SigningKey;
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+/// Every key tokens may be signed with, looked up by `kid`. Exactly one of them signs new tokens,
+/// the rest are only used for verification. Clones share the same keys, so applied promotions are
+/// seen by every worker.
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    state: Arc<RwLock<KeyringState>>,
+    retirement_period: Duration,
+}
+
+#[derive(Debug)]
+struct KeyringState {
+    /// Signs new tokens until a promotion is applied.
+    configured_kid: String,
+    active_kid: String,
+    keys: HashMap<String, SigningKey>,
+    retired_at: HashMap<String, DateTime<Utc>>,
+}
+
+impl Keyring {
+    /// `retirement_period` is how long a key stays valid after it stops signing tokens,
+    /// which should be the lifetime of the longest lived tokens it signed.
+    pub fn new(
+        active_kid: String,
+        keys: Vec<(String, SigningKey)>,
+        retirement_period: Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let mut keys_by_kid = HashMap::with_capacity(keys.len());
+        for (kid, key) in keys {
+            if keys_by_kid.insert(kid.clone(), key).is_some() {
+                bail!("signing key '{}' is configured more than once", kid);
+            }
+        }
+
+        if !keys_by_kid.contains_key(&active_kid) {
+            bail!("active signing key '{}' is not configured", active_kid);
+        }
+
+        Ok(Self {
+            state: Arc::new(RwLock::new(KeyringState {
+                configured_kid: active_kid.clone(),
+                active_kid,
+                keys: keys_by_kid,
+                retired_at: HashMap::new(),
+            })),
+            retirement_period,
+        })
+    }
+
+    pub fn get_active_key(&self) -> (String, SigningKey) {
+        let state = self.read();
+        let key = state.keys[&state.active_kid].clone();
+
+        (state.active_kid.clone(), key)
+    }
+
+    pub fn get_verification_key(&self, kid: &str) -> Option<SigningKey> {
+        let state = self.read();
+        if !self.is_valid(&state, kid) {
+            return None;
+        }
+
+        state.keys.get(kid).cloned()
+    }
+
+    pub fn contains_key(&self, kid: &str) -> bool {
+        self.read().keys.contains_key(kid)
+    }
+
+    /// Makes the key promoted last the one that signs new tokens. Every other key that signed
+    /// tokens before is retired when the key after it was promoted, and keeps verifying the tokens
+    /// it signed until the retirement period is over.
+    ///
+    /// `promotions` must be ordered oldest first. Promotions of keys that are no longer configured
+    /// are skipped, and without any promotion the configured active key is used.
+    pub fn apply_promotions(&self, promotions: &[SigningKeyPromotion]) {
+        let mut state = self.write();
+        let mut active_kid = state.configured_kid.clone();
+        let mut retired_at = HashMap::new();
+        for promotion in promotions
+            .iter()
+            .filter(|promotion| state.keys.contains_key(&promotion.kid))
+        {
+            if promotion.kid != active_kid {
+                retired_at.insert(active_kid, promotion.promoted_at);
+                active_kid = promotion.kid.clone();
+            }
+            retired_at.remove(&active_kid);
+        }
+
+        state.active_kid = active_kid;
+        state.retired_at = retired_at;
+    }
+
+    pub fn get_jwks(&self) -> JwkSet {
+        let state = self.read();
+        let mut keys: Vec<_> = state
+            .keys
+            .iter()
+            .filter(|(kid, _)| self.is_valid(&state, kid))
+            .filter_map(|(kid, key)| {
+                key.jwk().cloned().map(|mut jwk| {
+                    jwk.common.key_id = Some(kid.clone());
+                    jwk
+                })
+            })
+            .collect();
+        keys.sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+
+        JwkSet { keys }
+    }
+
+    fn is_valid(&self, state: &KeyringState, kid: &str) -> bool {
+        match state.retired_at.get(kid) {
+            None => true,
+            Some(retired_at) => *retired_at + self.retirement_period > Utc::now(),
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, KeyringState> {
+        self.state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, KeyringState> {
+        self.state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::Algorithm;
+
+    const EC_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/ec_private.pem");
+    const EC_PUBLIC_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/ec_public.pem");
+
+    #[test]
+    fn new_should_return_error_when_active_key_is_not_configured() {
+        // Act
+
+        let actual = Keyring::new(
+            "missing".to_string(),
+            vec![("first".to_string(), get_test_key())],
+            Duration::from_secs(60),
+        );
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn apply_promotions_should_change_active_key_and_keep_previous_key_for_verification() {
+        // Arrange
+
+        let keyring = get_test_keyring(Duration::from_secs(60));
+
+        // Act
+
+        keyring.apply_promotions(&[get_test_promotion("second", Utc::now())]);
+
+        // Assert
+
+        assert_eq!(keyring.get_active_key().0, "second");
+        assert!(keyring.get_verification_key("first").is_some());
+    }
+
+    #[test]
+    fn apply_promotions_should_skip_keys_that_are_not_configured() {
+        // Arrange
+
+        let keyring = get_test_keyring(Duration::from_secs(60));
+
+        // Act
+
+        keyring.apply_promotions(&[
+            get_test_promotion("second", Utc::now() - chrono::Duration::minutes(1)),
+            get_test_promotion("missing", Utc::now()),
+        ]);
+
+        // Assert
+
+        assert_eq!(keyring.get_active_key().0, "second");
+    }
+
+    #[test]
+    fn apply_promotions_should_retire_key_when_next_key_was_promoted() {
+        // Arrange
+
+        let keyring = get_test_keyring(Duration::from_secs(30 * 60));
+        let now = Utc::now();
+
+        // Act
+
+        keyring.apply_promotions(&[
+            get_test_promotion("second", now - chrono::Duration::hours(2)),
+            get_test_promotion("first", now - chrono::Duration::hours(1)),
+        ]);
+
+        // Assert
+
+        assert_eq!(keyring.get_active_key().0, "first");
+        assert!(keyring.get_verification_key("second").is_none());
+    }
+
+    #[test]
+    fn apply_promotions_should_return_to_configured_key_without_promotions() {
+        // Arrange
+
+        let keyring = get_test_keyring(Duration::from_secs(60));
+        keyring.apply_promotions(&[get_test_promotion("second", Utc::now())]);
+
+        // Act
+
+        keyring.apply_promotions(&[]);
+
+        // Assert
+
+        assert_eq!(keyring.get_active_key().0, "first");
+        assert!(keyring.get_verification_key("second").is_some());
+    }
+
+    #[test]
+    fn get_verification_key_should_return_none_when_retirement_period_is_over() {
+        // Arrange
+
+        let keyring = get_test_keyring(Duration::ZERO);
+        keyring.apply_promotions(&[get_test_promotion("second", Utc::now())]);
+
+        // Act
+
+        let actual = keyring.get_verification_key("first");
+
+        // Assert
+
+        assert!(actual.is_none());
+    }
+
+    #[test]
+    fn get_jwks_should_publish_asymmetric_keys_with_their_kid() {
+        // Arrange
+
+        let asymmetric_key =
+            SigningKey::from_pem(Algorithm::ES256, EC_PRIVATE_KEY, EC_PUBLIC_KEY).unwrap();
+        let keyring = Keyring::new(
+            "symmetric".to_string(),
+            vec![
+                ("symmetric".to_string(), get_test_key()),
+                ("asymmetric".to_string(), asymmetric_key),
+            ],
+            Duration::from_secs(60),
+        )
+        .unwrap();
+
+        // Act
+
+        let actual = keyring.get_jwks();
+
+        // Assert
+
+        assert_eq!(actual.keys.len(), 1);
+        assert_eq!(actual.keys[0].common.key_id.as_deref(), Some("asymmetric"));
+    }
+
+    fn get_test_keyring(retirement_period: Duration) -> Keyring {
+        Keyring::new(
+            "first".to_string(),
+            vec![
+                ("first".to_string(), get_test_key()),
+                ("second".to_string(), get_test_key()),
+            ],
+            retirement_period,
+        )
+        .unwrap()
+    }
+
+    fn get_test_promotion(kid: &str, promoted_at: DateTime<Utc>) -> SigningKeyPromotion {
+        SigningKeyPromotion {
+            kid: kid.to_string(),
+            promoted_at,
+        }
+    }
+
+    fn get_test_key() -> SigningKey {
+        SigningKey::from_secret(Algorithm::HS256, b"test").unwrap()
+    }
+}
@@ -0,0 +1,378 @@
+use anyhow::{anyhow, bail, Context};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+    EllipticCurveKeyType, Jwk, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+    PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use pkcs1::der::Decode;
+use spki::{ObjectIdentifier, SubjectPublicKeyInfoRef};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+
+const RSA_ENCRYPTION_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const EC_PUBLIC_KEY_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const P256_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const P384_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.34");
+const ED25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// Key material used to sign access tokens and to verify them again.
+#[derive(Clone)]
+pub struct SigningKey {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+enum KeyFamily {
+    Hmac,
+    Rsa,
+    EllipticCurve,
+    Edwards,
+}
+
+impl SigningKey {
+    pub fn from_secret(algorithm: Algorithm, secret: &[u8]) -> Result<Self, anyhow::Error> {
+        if !matches!(get_key_family(algorithm), KeyFamily::Hmac) {
+            bail!("{:?} requires a private and public key pair", algorithm);
+        }
+
+        Ok(Self {
+            algorithm,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        })
+    }
+
+    pub fn from_pem_files(
+        algorithm: Algorithm,
+        private_key_path: &Path,
+        public_key_path: &Path,
+    ) -> Result<Self, anyhow::Error> {
+        let private_pem = std::fs::read(private_key_path).with_context(|| {
+            format!(
+                "failed to read private key from {}",
+                private_key_path.display()
+            )
+        })?;
+        let public_pem = std::fs::read(public_key_path).with_context(|| {
+            format!(
+                "failed to read public key from {}",
+                public_key_path.display()
+            )
+        })?;
+
+        Self::from_pem(algorithm, &private_pem, &public_pem)
+    }
+
+    pub fn from_pem(
+        algorithm: Algorithm,
+        private_pem: &[u8],
+        public_pem: &[u8],
+    ) -> Result<Self, anyhow::Error> {
+        let encoding_key = match get_key_family(algorithm) {
+            KeyFamily::Hmac => bail!("{:?} requires a secret key, not a key pair", algorithm),
+            KeyFamily::Rsa => EncodingKey::from_rsa_pem(private_pem),
+            KeyFamily::EllipticCurve => EncodingKey::from_ec_pem(private_pem),
+            KeyFamily::Edwards => EncodingKey::from_ed_pem(private_pem),
+        }
+        .context("failed to parse private key")?;
+
+        let jwk = build_jwk(algorithm, public_pem)?;
+        let decoding_key = DecodingKey::from_jwk(&jwk).context("failed to parse public key")?;
+
+        let signing_key = Self {
+            algorithm,
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+        };
+        signing_key.ensure_keys_match()?;
+
+        Ok(signing_key)
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    /// Public part of the key, if it is safe to publish. Symmetric keys never are.
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+
+    fn ensure_keys_match(&self) -> Result<(), anyhow::Error> {
+        let claims = HashMap::from([("sub", "key-check")]);
+        let token = encode(&Header::new(self.algorithm), &claims, &self.encoding_key)
+            .context("failed to sign with private key")?;
+
+        let mut validation = Validation::new(self.algorithm);
+        validation.required_spec_claims = HashSet::new();
+        validation.validate_exp = false;
+
+        decode::<HashMap<String, String>>(&token, &self.decoding_key, &validation)
+            .map(|_| ())
+            .map_err(|_| anyhow!("public key does not match private key"))
+    }
+}
+
+impl Debug for SigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+fn get_key_family(algorithm: Algorithm) -> KeyFamily {
+    match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => KeyFamily::Hmac,
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => KeyFamily::Rsa,
+        Algorithm::ES256 | Algorithm::ES384 => KeyFamily::EllipticCurve,
+        Algorithm::EdDSA => KeyFamily::Edwards,
+    }
+}
+
+fn get_key_algorithm(algorithm: Algorithm) -> KeyAlgorithm {
+    match algorithm {
+        Algorithm::HS256 => KeyAlgorithm::HS256,
+        Algorithm::HS384 => KeyAlgorithm::HS384,
+        Algorithm::HS512 => KeyAlgorithm::HS512,
+        Algorithm::ES256 => KeyAlgorithm::ES256,
+        Algorithm::ES384 => KeyAlgorithm::ES384,
+        Algorithm::RS256 => KeyAlgorithm::RS256,
+        Algorithm::RS384 => KeyAlgorithm::RS384,
+        Algorithm::RS512 => KeyAlgorithm::RS512,
+        Algorithm::PS256 => KeyAlgorithm::PS256,
+        Algorithm::PS384 => KeyAlgorithm::PS384,
+        Algorithm::PS512 => KeyAlgorithm::PS512,
+        Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+    }
+}
+
+fn build_jwk(algorithm: Algorithm, public_pem: &[u8]) -> Result<Jwk, anyhow::Error> {
+    let pem = pem::parse(public_pem).context("failed to parse public key PEM")?;
+
+    let algorithm_parameters = match (get_key_family(algorithm), pem.tag()) {
+        (KeyFamily::Rsa, "RSA PUBLIC KEY") => get_rsa_parameters(pem.contents())?,
+        (family, "PUBLIC KEY") => {
+            let spki = SubjectPublicKeyInfoRef::from_der(pem.contents())
+                .map_err(|e| anyhow!("failed to parse public key: {}", e))?;
+            let key_bytes = spki
+                .subject_public_key
+                .as_bytes()
+                .ok_or_else(|| anyhow!("public key is not byte aligned"))?;
+
+            match family {
+                KeyFamily::Hmac => bail!("{:?} does not use a public key", algorithm),
+                KeyFamily::Rsa => {
+                    ensure_oid(spki.algorithm.oid, RSA_ENCRYPTION_OID, algorithm)?;
+                    get_rsa_parameters(key_bytes)?
+                }
+                KeyFamily::EllipticCurve => {
+                    ensure_oid(spki.algorithm.oid, EC_PUBLIC_KEY_OID, algorithm)?;
+                    let (curve, curve_oid, coordinate_length) = match algorithm {
+                        Algorithm::ES384 => (EllipticCurve::P384, P384_OID, 48),
+                        _ => (EllipticCurve::P256, P256_OID, 32),
+                    };
+                    let parameters_oid = spki
+                        .algorithm
+                        .parameters_oid()
+                        .map_err(|_| anyhow!("public key does not name its curve"))?;
+                    ensure_oid(parameters_oid, curve_oid, algorithm)?;
+                    get_ec_parameters(curve, coordinate_length, key_bytes)?
+                }
+                KeyFamily::Edwards => {
+                    ensure_oid(spki.algorithm.oid, ED25519_OID, algorithm)?;
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x: URL_SAFE_NO_PAD.encode(key_bytes),
+                    })
+                }
+            }
+        }
+        (_, tag) => bail!("unsupported public key PEM block '{}'", tag),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(get_key_algorithm(algorithm)),
+            ..Default::default()
+        },
+        algorithm: algorithm_parameters,
+    })
+}
+
+fn ensure_oid(
+    actual: ObjectIdentifier,
+    expected: ObjectIdentifier,
+    algorithm: Algorithm,
+) -> Result<(), anyhow::Error> {
+    if actual != expected {
+        bail!("public key type does not match {:?}", algorithm);
+    }
+
+    Ok(())
+}
+
+fn get_rsa_parameters(der: &[u8]) -> Result<AlgorithmParameters, anyhow::Error> {
+    let public_key = pkcs1::RsaPublicKey::from_der(der)
+        .map_err(|e| anyhow!("failed to parse RSA public key: {}", e))?;
+
+    Ok(AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(public_key.modulus.as_bytes()),
+        e: URL_SAFE_NO_PAD.encode(public_key.public_exponent.as_bytes()),
+    }))
+}
+
+fn get_ec_parameters(
+    curve: EllipticCurve,
+    coordinate_length: usize,
+    point: &[u8],
+) -> Result<AlgorithmParameters, anyhow::Error> {
+    // only uncompressed points (0x04 || x || y) can be published as a JWK
+    if point.len() != 1 + 2 * coordinate_length || point[0] != 0x04 {
+        bail!("elliptic curve public key is not an uncompressed point");
+    }
+
+    let (x, y) = point[1..].split_at(coordinate_length);
+
+    Ok(AlgorithmParameters::EllipticCurve(
+        EllipticCurveKeyParameters {
+            key_type: EllipticCurveKeyType::EC,
+            curve,
+            x: URL_SAFE_NO_PAD.encode(x),
+            y: URL_SAFE_NO_PAD.encode(y),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSA_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/rsa_private.pem");
+    const RSA_PUBLIC_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/rsa_public.pem");
+    const EC_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/ec_private.pem");
+    const EC_PUBLIC_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/ec_public.pem");
+    const EC_OTHER_PUBLIC_KEY: &[u8] =
+        include_bytes!("../../tests/fixtures/keys/ec_other_public.pem");
+    const ED25519_PRIVATE_KEY: &[u8] =
+        include_bytes!("../../tests/fixtures/keys/ed25519_private.pem");
+    const ED25519_PUBLIC_KEY: &[u8] =
+        include_bytes!("../../tests/fixtures/keys/ed25519_public.pem");
+
+    #[test]
+    fn from_pem_should_publish_rsa_parameters_when_rs256_is_used() {
+        // Act
+
+        let actual = SigningKey::from_pem(Algorithm::RS256, RSA_PRIVATE_KEY, RSA_PUBLIC_KEY);
+
+        // Assert
+
+        let jwk = actual.unwrap().jwk().cloned().unwrap();
+        assert_eq!(jwk.common.key_algorithm, Some(KeyAlgorithm::RS256));
+        assert!(matches!(jwk.algorithm, AlgorithmParameters::RSA(_)));
+    }
+
+    #[test]
+    fn from_pem_should_publish_p256_parameters_when_es256_is_used() {
+        // Act
+
+        let actual = SigningKey::from_pem(Algorithm::ES256, EC_PRIVATE_KEY, EC_PUBLIC_KEY);
+
+        // Assert
+
+        let jwk = actual.unwrap().jwk().cloned().unwrap();
+        match jwk.algorithm {
+            AlgorithmParameters::EllipticCurve(parameters) => {
+                assert_eq!(parameters.curve, EllipticCurve::P256)
+            }
+            _ => panic!("expected elliptic curve parameters"),
+        }
+    }
+
+    #[test]
+    fn from_pem_should_publish_ed25519_parameters_when_eddsa_is_used() {
+        // Act
+
+        let actual =
+            SigningKey::from_pem(Algorithm::EdDSA, ED25519_PRIVATE_KEY, ED25519_PUBLIC_KEY);
+
+        // Assert
+
+        let jwk = actual.unwrap().jwk().cloned().unwrap();
+        assert!(matches!(
+            jwk.algorithm,
+            AlgorithmParameters::OctetKeyPair(_)
+        ));
+    }
+
+    #[test]
+    fn from_pem_should_return_error_when_keys_do_not_match() {
+        // Act
+
+        let actual = SigningKey::from_pem(Algorithm::ES256, EC_PRIVATE_KEY, EC_OTHER_PUBLIC_KEY);
+
+        // Assert
+
+        assert_eq!(
+            actual.unwrap_err().to_string(),
+            "public key does not match private key"
+        );
+    }
+
+    #[test]
+    fn from_pem_should_return_error_when_key_type_does_not_match_algorithm() {
+        // Act
+
+        let actual = SigningKey::from_pem(Algorithm::RS256, EC_PRIVATE_KEY, EC_PUBLIC_KEY);
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn from_secret_should_not_publish_key() {
+        // Act
+
+        let actual = SigningKey::from_secret(Algorithm::HS256, b"secret").unwrap();
+
+        // Assert
+
+        assert!(actual.jwk().is_none());
+    }
+
+    #[test]
+    fn from_secret_should_return_error_when_asymmetric_algorithm_is_used() {
+        // Act
+
+        let actual = SigningKey::from_secret(Algorithm::RS256, b"secret");
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+}
@@ -0,0 +1,421 @@
+use super::{AuthService, UserService};
+use crate::api::contracts;
+use crate::errors::Error;
+use crate::models::refresh_token::TokenPair;
+use crate::models::totp::{TotpEnrollment, UserTotp};
+use anyhow::anyhow;
+use chrono::Utc;
+use log::warn;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// How many codes, right or wrong, a single MFA challenge accepts before it is revoked.
+const MAX_MFA_ATTEMPTS: u32 = 5;
+
+impl UserService {
+    /// Starts a new TOTP enrollment. It only takes effect once a code is confirmed,
+    /// so enrolling again before that simply replaces the secret.
+    pub async fn enroll_totp(
+        &self,
+        auth_service: Arc<AuthService>,
+        user_id: Uuid,
+    ) -> Result<TotpEnrollment, Error> {
+        if self.get_enabled_totp(user_id).await?.is_some() {
+            return Err(Error::TotpAlreadyEnabled);
+        }
+
+        let user = self
+            .repo
+            .get_user(user_id)
+            .await?
+            .ok_or_else(|| anyhow!("authenticated user {} no longer exists", user_id))?;
+
+        let secret = auth_service.generate_totp_secret();
+        let to_upsert = UserTotp {
+            user_id,
+            secret_ciphertext: auth_service.encrypt_totp_secret(user_id, &secret)?,
+            confirmed_at: None,
+            last_used_step: None,
+            created_at: Utc::now(),
+        };
+        self.repo.upsert_user_totp(to_upsert).await?;
+        let recovery_codes = self.replace_recovery_codes(&auth_service, user_id).await?;
+
+        let (secret, otpauth_uri) = auth_service.get_totp_enrollment(&user.email, &secret);
+
+        Ok(TotpEnrollment {
+            secret,
+            otpauth_uri,
+            recovery_codes,
+        })
+    }
+
+    pub async fn confirm_totp(
+        &self,
+        auth_service: Arc<AuthService>,
+        user_id: Uuid,
+        request: contracts::ConfirmTotpRequest,
+    ) -> Result<(), Error> {
+        let totp = match self.repo.get_user_totp(user_id).await? {
+            Some(t) if t.is_enabled() => return Err(Error::TotpAlreadyEnabled),
+            Some(t) => t,
+            None => return Err(Error::TotpNotEnrolled),
+        };
+
+        self.use_totp_code(&auth_service, &totp, &request.code)
+            .await
+    }
+
+    pub async fn disable_totp(
+        &self,
+        auth_service: Arc<AuthService>,
+        user_id: Uuid,
+        request: contracts::DisableTotpRequest,
+    ) -> Result<(), Error> {
+        let totp = self
+            .get_enabled_totp(user_id)
+            .await?
+            .ok_or(Error::TotpNotEnrolled)?;
+
+        self.use_second_factor(
+            &auth_service,
+            &totp,
+            request.code.as_deref(),
+            request.recovery_code.as_deref(),
+        )
+        .await?;
+        self.repo.delete_user_totp(user_id).await?;
+
+        Ok(())
+    }
+
+    /// Second step of a login for users with TOTP enabled.
+    pub async fn login_mfa(
+        &self,
+        auth_service: Arc<AuthService>,
+        request: contracts::MfaLoginRequest,
+    ) -> Result<TokenPair, Error> {
+        let claims = match auth_service.verify_mfa_challenge_token(&request.mfa_token) {
+            Ok(token_data) => token_data.claims,
+            Err(e) => {
+                warn!("failed to verify MFA challenge token. reason: {}", e);
+                return Err(Error::InvalidCredentials);
+            }
+        };
+
+        if self
+            .revocation_store
+            .is_mfa_challenge_revoked(&claims)
+            .await?
+        {
+            return Err(Error::InvalidCredentials);
+        }
+
+        // counted before the code is checked, so that parallel guesses can't get past the limit.
+        // the user has to log in with their password again to get a new challenge
+        let attempts = self.revocation_store.record_mfa_attempt(&claims).await?;
+        if attempts > MAX_MFA_ATTEMPTS {
+            warn!(
+                "too many MFA attempts by user {}, revoking challenge",
+                claims.sub
+            );
+            self.revocation_store.revoke_mfa_challenge(&claims).await?;
+            return Err(Error::InvalidCredentials);
+        }
+
+        // TOTP may have been disabled since the challenge was issued
+        let totp = self
+            .get_enabled_totp(claims.sub)
+            .await?
+            .ok_or(Error::InvalidCredentials)?;
+
+        self.use_second_factor(
+            &auth_service,
+            &totp,
+            request.code.as_deref(),
+            request.recovery_code.as_deref(),
+        )
+        .await?;
+
+        // only one request can use up the challenge, every other one is treated as a replay
+        if !self.revocation_store.revoke_mfa_challenge(&claims).await? {
+            return Err(Error::InvalidCredentials);
+        }
+
+        self.start_session(&auth_service, claims.sub).await
+    }
+
+    pub(super) async fn get_enabled_totp(&self, user_id: Uuid) -> Result<Option<UserTotp>, Error> {
+        let totp = self.repo.get_user_totp(user_id).await?;
+
+        Ok(totp.filter(UserTotp::is_enabled))
+    }
+
+    /// Accepts either a TOTP code or, for users who lost their device, one of their recovery codes.
+    async fn use_second_factor(
+        &self,
+        auth_service: &AuthService,
+        totp: &UserTotp,
+        code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<(), Error> {
+        match (code, recovery_code) {
+            (Some(code), None) => self.use_totp_code(auth_service, totp, code).await,
+            (None, Some(recovery_code)) => {
+                self.use_recovery_code(auth_service, totp.user_id, recovery_code)
+                    .await
+            }
+            _ => Err(Error::InvalidTotpCode),
+        }
+    }
+
+    pub(super) async fn use_totp_code(
+        &self,
+        auth_service: &AuthService,
+        totp: &UserTotp,
+        code: &str,
+    ) -> Result<(), Error> {
+        let secret = auth_service.decrypt_totp_secret(totp.user_id, &totp.secret_ciphertext)?;
+        let step = auth_service
+            .verify_totp_code(&secret, code, totp.last_used_step)
+            .ok_or(Error::InvalidTotpCode)?;
+
+        // a concurrent request may have used a code of this step already
+        if !self.repo.use_totp_step(totp.user_id, step).await? {
+            return Err(Error::InvalidTotpCode);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::refresh_token::LoginOutcome;
+    use crate::service::{get_test_auth_options, get_test_user_service, register_test_user};
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[actix_web::test]
+    async fn login_mfa_should_return_error_when_challenge_is_replayed() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let recovery_codes = register_with_totp(&user_service, &auth_service).await;
+        let mfa_token = login(&user_service, &auth_service).await;
+        user_service
+            .login_mfa(
+                auth_service.clone(),
+                get_recovery_code_request(&mfa_token, &recovery_codes[0]),
+            )
+            .await
+            .unwrap();
+
+        // Act
+
+        let actual = user_service
+            .login_mfa(
+                auth_service.clone(),
+                get_recovery_code_request(&mfa_token, &recovery_codes[1]),
+            )
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::InvalidCredentials)));
+    }
+
+    #[actix_web::test]
+    async fn login_mfa_should_revoke_challenge_after_too_many_failed_attempts() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let recovery_codes = register_with_totp(&user_service, &auth_service).await;
+        let mfa_token = login(&user_service, &auth_service).await;
+        for _ in 0..MAX_MFA_ATTEMPTS {
+            let failed_attempt = user_service
+                .login_mfa(
+                    auth_service.clone(),
+                    contracts::MfaLoginRequest {
+                        mfa_token: mfa_token.clone(),
+                        code: Some("000000".to_string()),
+                        recovery_code: None,
+                    },
+                )
+                .await;
+            assert!(matches!(failed_attempt, Err(Error::InvalidTotpCode)));
+        }
+
+        // Act
+
+        let actual = user_service
+            .login_mfa(
+                auth_service.clone(),
+                get_recovery_code_request(&mfa_token, &recovery_codes[0]),
+            )
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::InvalidCredentials)));
+        let new_mfa_token = login(&user_service, &auth_service).await;
+        user_service
+            .login_mfa(
+                auth_service.clone(),
+                get_recovery_code_request(&new_mfa_token, &recovery_codes[0]),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn login_mfa_should_let_only_one_concurrent_exchange_succeed() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let (recovery_codes, mfa_token) = actix_web::rt::System::new().block_on(async {
+            let recovery_codes = register_with_totp(&user_service, &auth_service).await;
+            let mfa_token = login(&user_service, &auth_service).await;
+            (recovery_codes, mfa_token)
+        });
+        let requests = recovery_codes
+            .iter()
+            .take(MAX_MFA_ATTEMPTS as usize)
+            .map(|recovery_code| get_recovery_code_request(&mfa_token, recovery_code))
+            .collect();
+
+        // Act
+
+        let actual = login_mfa_concurrently(&user_service, &auth_service, requests);
+
+        // Assert
+
+        assert_eq!(actual.iter().filter(|result| result.is_ok()).count(), 1);
+        assert!(actual
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .all(|e| matches!(e, Error::InvalidCredentials)));
+    }
+
+    #[test]
+    fn login_mfa_should_count_concurrent_attempts_against_limit() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let (recovery_codes, mfa_token) = actix_web::rt::System::new().block_on(async {
+            let recovery_codes = register_with_totp(&user_service, &auth_service).await;
+            let mfa_token = login(&user_service, &auth_service).await;
+            (recovery_codes, mfa_token)
+        });
+        let requests = (0..MAX_MFA_ATTEMPTS * 2)
+            .map(|_| contracts::MfaLoginRequest {
+                mfa_token: mfa_token.clone(),
+                code: Some("000000".to_string()),
+                recovery_code: None,
+            })
+            .collect();
+
+        // Act
+
+        let actual = login_mfa_concurrently(&user_service, &auth_service, requests);
+
+        // Assert
+
+        let checked_codes = actual
+            .iter()
+            .filter(|result| matches!(result, Err(Error::InvalidTotpCode)))
+            .count();
+        assert_eq!(checked_codes, MAX_MFA_ATTEMPTS as usize);
+        let after_limit = actix_web::rt::System::new().block_on(user_service.login_mfa(
+            auth_service.clone(),
+            get_recovery_code_request(&mfa_token, &recovery_codes[0]),
+        ));
+        assert!(matches!(after_limit, Err(Error::InvalidCredentials)));
+    }
+
+    /// Sends every request from its own thread, all released at once.
+    fn login_mfa_concurrently(
+        user_service: &UserService,
+        auth_service: &Arc<AuthService>,
+        requests: Vec<contracts::MfaLoginRequest>,
+    ) -> Vec<Result<TokenPair, Error>> {
+        let barrier = Barrier::new(requests.len());
+        thread::scope(|scope| {
+            let handles: Vec<_> = requests
+                .into_iter()
+                .map(|request| {
+                    let barrier = &barrier;
+                    scope.spawn(move || {
+                        barrier.wait();
+                        actix_web::rt::System::new()
+                            .block_on(user_service.login_mfa(auth_service.clone(), request))
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+
+    /// Registers a user with TOTP enabled and returns their recovery codes.
+    async fn register_with_totp(
+        user_service: &UserService,
+        auth_service: &Arc<AuthService>,
+    ) -> Vec<String> {
+        let user = register_test_user(user_service, auth_service).await;
+        let enrollment = user_service
+            .enroll_totp(auth_service.clone(), user.id)
+            .await
+            .unwrap();
+
+        // confirming would need a code of the current time step, so the enrollment is confirmed directly
+        let mut totp = user_service
+            .repo
+            .get_user_totp(user.id)
+            .await
+            .unwrap()
+            .unwrap();
+        totp.confirmed_at = Some(Utc::now());
+        user_service.repo.upsert_user_totp(totp).await.unwrap();
+
+        enrollment.recovery_codes
+    }
+
+    /// Logs in with the password and returns the MFA challenge token.
+    async fn login(user_service: &UserService, auth_service: &Arc<AuthService>) -> String {
+        let outcome = user_service
+            .login(
+                auth_service.clone(),
+                contracts::LoginUserRequest {
+                    email: "john.doe@example.com".to_string(),
+                    password: "password123".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        match outcome {
+            LoginOutcome::MfaRequired(mfa_token) => mfa_token,
+            LoginOutcome::Authenticated(_) => panic!("expected MFA challenge"),
+        }
+    }
+
+    fn get_recovery_code_request(
+        mfa_token: &str,
+        recovery_code: &str,
+    ) -> contracts::MfaLoginRequest {
+        contracts::MfaLoginRequest {
+            mfa_token: mfa_token.to_string(),
+            code: None,
+            recovery_code: Some(recovery_code.to_string()),
+        }
+    }
+}
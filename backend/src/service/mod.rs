@@ -1,14 +1,36 @@
+use crate::mail::Mailer;
 use crate::repository::Repository;
-use diesel::r2d2::{ConnectionManager, Pool};
-use diesel::PgConnection;
+use crate::service::blocking::BlockingPool;
+use crate::service::cipher::SecretCipher;
+use crate::service::keyring::Keyring;
+use crate::service::passwords::PasswordHashScheme;
+use crate::service::revocation::RevocationStore;
+use std::sync::Arc;
 
 pub mod auth;
+pub mod blocking;
+pub mod cipher;
+pub mod email_verification;
+pub mod keyring;
+pub mod keys;
+pub mod mfa;
+pub mod password_reset;
+pub mod passwords;
+pub mod recovery_codes;
+pub mod revocation;
+pub mod roles;
+pub mod sessions;
+pub mod signing_keys;
+pub mod totp;
 pub mod users;
 
 #[derive(Debug, Clone)]
 pub struct UserService {
-    db_pool: Pool<ConnectionManager<PgConnection>>,
-    repo: Repository,
+    repo: Arc<dyn Repository>,
+    revocation_store: Arc<dyn RevocationStore>,
+    mailer: Arc<dyn Mailer>,
+    hashing_pool: BlockingPool,
+    mail_pool: BlockingPool,
 }
 
 #[derive(Debug, Clone)]
@@ -18,9 +40,20 @@ pub struct AuthService {
 
 #[derive(Debug, Clone)]
 pub struct AuthOptions {
-    pub encoding_key: String,
+    pub keyring: Keyring,
+    pub admin_api_key: Option<String>,
+    pub totp_cipher: SecretCipher,
+    pub totp_issuer: String,
+    pub mfa_challenge_expiration_in_seconds: u64,
+    pub password_reset_url: String,
+    pub password_reset_expiration_in_seconds: u64,
+    pub email_verification_url: String,
+    pub email_verification_expiration_in_seconds: u64,
+    pub require_verified_email: bool,
     pub audience: String,
     pub token_expiration_in_seconds: u64,
+    pub refresh_token_expiration_in_seconds: u64,
+    pub password_hash_scheme: PasswordHashScheme,
 }
 
 impl AuthService {
@@ -30,10 +63,97 @@ impl AuthService {
 }
 
 impl UserService {
-    pub fn new(db_pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+    pub fn new(
+        repo: Arc<dyn Repository>,
+        revocation_store: Arc<dyn RevocationStore>,
+        mailer: Arc<dyn Mailer>,
+        hashing_pool: BlockingPool,
+        mail_pool: BlockingPool,
+    ) -> Self {
         Self {
-            db_pool,
-            repo: Repository {},
+            repo,
+            revocation_store,
+            mailer,
+            hashing_pool,
+            mail_pool,
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) fn get_test_auth_options() -> AuthOptions {
+    use crate::service::keys::SigningKey;
+    use jsonwebtoken::Algorithm;
+    use std::time::Duration;
+
+    AuthOptions {
+        keyring: Keyring::new(
+            "test".to_string(),
+            vec![(
+                "test".to_string(),
+                SigningKey::from_secret(Algorithm::HS256, b"test").unwrap(),
+            )],
+            Duration::from_secs(100),
+        )
+        .unwrap(),
+        admin_api_key: None,
+        totp_cipher: SecretCipher::new(&[0u8; 32]).unwrap(),
+        totp_issuer: "test".to_string(),
+        mfa_challenge_expiration_in_seconds: 100,
+        password_reset_url: "http://localhost/reset-password".to_string(),
+        password_reset_expiration_in_seconds: 100,
+        email_verification_url: "http://localhost/verify-email".to_string(),
+        email_verification_expiration_in_seconds: 100,
+        require_verified_email: false,
+        audience: "test".to_string(),
+        token_expiration_in_seconds: 100,
+        refresh_token_expiration_in_seconds: 1000,
+        password_hash_scheme: PasswordHashScheme::Argon2id {
+            memory_cost_in_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        },
+    }
+}
+
+/// A `UserService` that keeps everything in memory, together with the mailer that collects the
+/// mail it sends.
+#[cfg(test)]
+pub(crate) fn get_test_user_service() -> (UserService, Arc<crate::mail::in_memory::InMemoryMailer>)
+{
+    use crate::mail::in_memory::InMemoryMailer;
+    use crate::repository::in_memory::InMemoryRepository;
+    use crate::service::revocation::InMemoryRevocationStore;
+
+    let mailer = Arc::new(InMemoryMailer::default());
+    let user_service = UserService::new(
+        Arc::new(InMemoryRepository::default()),
+        Arc::new(InMemoryRevocationStore::default()),
+        mailer.clone(),
+        BlockingPool::new(1),
+        BlockingPool::new(1),
+    );
+
+    (user_service, mailer)
+}
+
+#[cfg(test)]
+pub(crate) fn get_test_register_request() -> crate::api::contracts::RegisterUserRequest {
+    crate::api::contracts::RegisterUserRequest {
+        name: "John Doe".to_string(),
+        email: "john.doe@example.com".to_string(),
+        password: "password123".to_string(),
+    }
+}
+
+/// Registers the user of `get_test_register_request`.
+#[cfg(test)]
+pub(crate) async fn register_test_user(
+    user_service: &UserService,
+    auth_service: &Arc<AuthService>,
+) -> crate::models::user::User {
+    user_service
+        .register(auth_service.clone(), get_test_register_request())
+        .await
+        .unwrap()
+}
@@ -0,0 +1,253 @@
+use super::auth::{generate_opaque_token, hash_opaque_token};
+use super::{AuthService, UserService};
+use crate::api::contracts;
+use crate::errors::Error;
+use crate::mail::templates::MailTemplate;
+use crate::models::password_reset_token::PasswordResetToken;
+use chrono::{DateTime, Utc};
+use log::info;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+impl AuthService {
+    /// Returns a new reset token together with the hash that should be persisted.
+    pub fn generate_password_reset_token(&self) -> (String, Vec<u8>) {
+        generate_opaque_token()
+    }
+
+    pub fn hash_password_reset_token(&self, token: &str) -> Vec<u8> {
+        hash_opaque_token(token)
+    }
+
+    pub fn get_password_reset_expiration(&self) -> DateTime<Utc> {
+        Utc::now() + Duration::from_secs(self.options.password_reset_expiration_in_seconds)
+    }
+
+    pub fn get_password_reset_link(&self, token: &str) -> String {
+        // tokens are URL safe base64, so they don't need to be encoded
+        format!("{}?token={}", self.options.password_reset_url, token)
+    }
+
+    /// Rounded up, so that the email never claims the link expires sooner than it does.
+    fn get_password_reset_expiration_in_minutes(&self) -> u64 {
+        self.options
+            .password_reset_expiration_in_seconds
+            .div_ceil(60)
+    }
+}
+
+impl UserService {
+    /// Mails a reset link when a user with the email exists. The outcome must not be shown to
+    /// the caller, otherwise it could be used to find out which emails are registered.
+    pub async fn request_password_reset(
+        &self,
+        auth_service: Arc<AuthService>,
+        request: contracts::ForgotPasswordRequest,
+    ) -> Result<(), Error> {
+        let Some(user) = self.repo.get_user_by_email(&request.email).await? else {
+            info!("password reset was requested for an unknown email");
+            return Ok(());
+        };
+
+        let (token, token_hash) = auth_service.generate_password_reset_token();
+        let to_insert = PasswordResetToken {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            token_hash,
+            expires_at: auth_service.get_password_reset_expiration(),
+            created_at: Utc::now(),
+            used_at: None,
+        };
+        self.repo.insert_password_reset_token(to_insert).await?;
+
+        self.send_mail(
+            user.email,
+            MailTemplate::PasswordReset {
+                name: user.name,
+                link: auth_service.get_password_reset_link(&token),
+                expires_in_minutes: auth_service.get_password_reset_expiration_in_minutes(),
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sets a new password and signs the user out everywhere. The token is used up before the
+    /// password is hashed, so that made up tokens can't keep the blocking pool busy.
+    pub async fn reset_password(
+        &self,
+        auth_service: Arc<AuthService>,
+        request: contracts::ResetPasswordRequest,
+    ) -> Result<(), Error> {
+        let token_hash = auth_service.hash_password_reset_token(&request.token);
+        let user_id = self
+            .repo
+            .use_password_reset_token(token_hash)
+            .await?
+            .ok_or(Error::InvalidPasswordResetToken)?;
+
+        let password_hash = self.hash_password(&auth_service, request.password).await?;
+        if !self.repo.set_password_hash(user_id, password_hash).await? {
+            return Err(Error::UserNotFound);
+        }
+
+        self.revocation_store
+            .revoke_tokens_issued_before(user_id, Utc::now())
+            .await?;
+
+        info!("password of user {} was reset", user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::refresh_token::LoginOutcome;
+    use crate::service::{
+        get_test_auth_options, get_test_user_service, register_test_user, AuthOptions,
+    };
+
+    #[test]
+    fn get_password_reset_link_should_append_token_to_configured_url() {
+        // Arrange
+
+        let auth_service = AuthService::new(get_test_auth_options());
+        let (token, _) = auth_service.generate_password_reset_token();
+
+        // Act
+
+        let actual = auth_service.get_password_reset_link(&token);
+
+        // Assert
+
+        assert_eq!(
+            actual,
+            format!("http://localhost/reset-password?token={}", token)
+        );
+    }
+
+    #[test]
+    fn get_password_reset_expiration_in_minutes_should_round_up() {
+        // Arrange
+
+        let get_expiration_in_minutes = |seconds| {
+            AuthService::new(AuthOptions {
+                password_reset_expiration_in_seconds: seconds,
+                ..get_test_auth_options()
+            })
+            .get_password_reset_expiration_in_minutes()
+        };
+
+        // Act
+
+        let actual = [30, 60, 90].map(get_expiration_in_minutes);
+
+        // Assert
+
+        assert_eq!(actual, [1, 1, 2]);
+    }
+
+    #[actix_web::test]
+    async fn reset_password_should_store_new_password_and_accept_login_right_after() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let user_id = register_test_user(&user_service, &auth_service).await.id;
+        let token = insert_test_password_reset_token(&user_service, &auth_service, user_id).await;
+
+        // Act
+
+        user_service
+            .reset_password(
+                auth_service.clone(),
+                contracts::ResetPasswordRequest {
+                    token,
+                    password: "newpassword123".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Assert
+
+        let outcome = user_service
+            .login(
+                auth_service.clone(),
+                contracts::LoginUserRequest {
+                    email: "john.doe@example.com".to_string(),
+                    password: "newpassword123".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        let LoginOutcome::Authenticated(tokens) = outcome else {
+            panic!("expected tokens");
+        };
+        let claims = auth_service.verify_token(tokens.token).unwrap().claims;
+        assert!(!user_service
+            .revocation_store
+            .is_revoked(&claims)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn reset_password_should_return_error_and_keep_password_when_token_is_unknown() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(get_test_auth_options()));
+        let user_id = register_test_user(&user_service, &auth_service).await.id;
+        let password_hash = user_service
+            .repo
+            .get_user(user_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .password_hash;
+
+        // Act
+
+        let actual = user_service
+            .reset_password(
+                auth_service.clone(),
+                contracts::ResetPasswordRequest {
+                    token: "unknown".to_string(),
+                    password: "newpassword123".to_string(),
+                },
+            )
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::InvalidPasswordResetToken)));
+        let stored = user_service.repo.get_user(user_id).await.unwrap().unwrap();
+        assert_eq!(stored.password_hash, password_hash);
+    }
+
+    async fn insert_test_password_reset_token(
+        user_service: &UserService,
+        auth_service: &AuthService,
+        user_id: Uuid,
+    ) -> String {
+        let (token, token_hash) = auth_service.generate_password_reset_token();
+        user_service
+            .repo
+            .insert_password_reset_token(PasswordResetToken {
+                id: Uuid::new_v4(),
+                user_id,
+                token_hash,
+                expires_at: auth_service.get_password_reset_expiration(),
+                created_at: Utc::now(),
+                used_at: None,
+            })
+            .await
+            .unwrap();
+
+        token
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{anyhow, bail, Context};
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use serde::Deserialize;
+
+const ARGON2ID_IDENTIFIER: &str = "argon2id";
+const BCRYPT_PREFIXES: [&str; 3] = ["$2a$", "$2b$", "$2y$"];
+const BCRYPT_COSTS: std::ops::RangeInclusive<u32> = 4..=31;
+
+/// How new password hashes are created. Existing hashes are verified with whatever algorithm
+/// their PHC string names, so changing the scheme only affects users once they log in again.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(tag = "algorithm", rename_all = "lowercase")]
+pub enum PasswordHashScheme {
+    Argon2id {
+        memory_cost_in_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+    Bcrypt {
+        cost: u32,
+    },
+}
+
+impl Default for PasswordHashScheme {
+    /// The minimum Argon2id configuration recommended by OWASP.
+    fn default() -> Self {
+        Self::Argon2id {
+            memory_cost_in_kib: 19456,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl PasswordHashScheme {
+    pub fn hash(&self, password: &str) -> Result<String, anyhow::Error> {
+        match self {
+            PasswordHashScheme::Argon2id {
+                memory_cost_in_kib,
+                iterations,
+                parallelism,
+            } => {
+                let params = Params::new(*memory_cost_in_kib, *iterations, *parallelism, None)
+                    .context("invalid Argon2id parameters")?;
+                let salt = SaltString::generate(&mut rand::thread_rng());
+
+                let hash = Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password(password.as_bytes(), &salt)
+                    .context("failed to hash password with Argon2id")?;
+                Ok(hash.to_string())
+            }
+            PasswordHashScheme::Bcrypt { cost } => {
+                bcrypt::hash(password, *cost).context("failed to hash password with bcrypt")
+            }
+        }
+    }
+
+    /// Whether `hash` was created with another algorithm or other parameters than this scheme.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        match self {
+            PasswordHashScheme::Argon2id {
+                memory_cost_in_kib,
+                iterations,
+                parallelism,
+            } => {
+                let Ok(parsed) = PasswordHash::new(hash) else {
+                    return true;
+                };
+                let Ok(params) = Params::try_from(&parsed) else {
+                    return true;
+                };
+
+                parsed.algorithm.as_str() != ARGON2ID_IDENTIFIER
+                    || parsed.version != Some(Version::V0x13.into())
+                    || params.m_cost() != *memory_cost_in_kib
+                    || params.t_cost() != *iterations
+                    || params.p_cost() != *parallelism
+            }
+            PasswordHashScheme::Bcrypt { cost } => get_bcrypt_cost(hash) != Some(*cost),
+        }
+    }
+
+    /// Checks the parameters up front, instead of failing the first time a password is hashed.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        match self {
+            PasswordHashScheme::Argon2id {
+                memory_cost_in_kib,
+                iterations,
+                parallelism,
+            } => Params::new(*memory_cost_in_kib, *iterations, *parallelism, None)
+                .map(|_| ())
+                .context("invalid Argon2id parameters"),
+            PasswordHashScheme::Bcrypt { cost } if !BCRYPT_COSTS.contains(cost) => bail!(
+                "bcrypt cost must be between {} and {}",
+                BCRYPT_COSTS.start(),
+                BCRYPT_COSTS.end()
+            ),
+            PasswordHashScheme::Bcrypt { .. } => Ok(()),
+        }
+    }
+}
+
+/// Verifies `password` against a hash of any supported algorithm, detected from its prefix.
+pub fn verify_password(password: &str, hash: &str) -> Result<bool, anyhow::Error> {
+    if BCRYPT_PREFIXES
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+    {
+        return bcrypt::verify(password, hash).context("failed to verify hash with password");
+    }
+
+    if hash.starts_with("$argon2") {
+        let parsed = PasswordHash::new(hash)
+            .map_err(|e| anyhow!("failed to parse Argon2 hash. reason: {}", e))?;
+
+        // the algorithm and its parameters are taken from the hash itself
+        return match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e).context("failed to verify hash with password"),
+        };
+    }
+
+    bail!("password hash uses an unsupported algorithm")
+}
+
+/// Reads the cost out of a `$2b$<cost>$<salt and hash>` string.
+fn get_bcrypt_cost(hash: &str) -> Option<u32> {
+    BCRYPT_PREFIXES
+        .iter()
+        .find_map(|prefix| hash.strip_prefix(prefix))
+        .and_then(|rest| rest.split('$').next())
+        .and_then(|cost| cost.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ARGON2ID: PasswordHashScheme = PasswordHashScheme::Argon2id {
+        memory_cost_in_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+    const TEST_BCRYPT: PasswordHashScheme = PasswordHashScheme::Bcrypt { cost: 4 };
+
+    #[test]
+    fn verify_password_should_accept_argon2id_and_bcrypt_hashes() {
+        // Arrange
+
+        let argon2id_hash = TEST_ARGON2ID.hash("password").unwrap();
+        let bcrypt_hash = TEST_BCRYPT.hash("password").unwrap();
+
+        // Act & Assert
+
+        assert!(argon2id_hash.starts_with("$argon2id$"));
+        assert!(verify_password("password", &argon2id_hash).unwrap());
+        assert!(!verify_password("wrong", &argon2id_hash).unwrap());
+        assert!(verify_password("password", &bcrypt_hash).unwrap());
+        assert!(!verify_password("wrong", &bcrypt_hash).unwrap());
+    }
+
+    #[test]
+    fn verify_password_should_return_error_for_unknown_algorithm() {
+        // Act
+
+        let actual = verify_password("password", "$1$salt$hash");
+
+        // Assert
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn needs_rehash_should_return_true_when_algorithm_differs() {
+        // Arrange
+
+        let bcrypt_hash = TEST_BCRYPT.hash("password").unwrap();
+
+        // Act & Assert
+
+        assert!(TEST_ARGON2ID.needs_rehash(&bcrypt_hash));
+        assert!(!TEST_BCRYPT.needs_rehash(&bcrypt_hash));
+    }
+
+    #[test]
+    fn needs_rehash_should_return_true_when_parameters_differ() {
+        // Arrange
+
+        let argon2id_hash = TEST_ARGON2ID.hash("password").unwrap();
+        let bcrypt_hash = TEST_BCRYPT.hash("password").unwrap();
+        let stronger_argon2id = PasswordHashScheme::Argon2id {
+            memory_cost_in_kib: 2048,
+            iterations: 1,
+            parallelism: 1,
+        };
+
+        // Act & Assert
+
+        assert!(!TEST_ARGON2ID.needs_rehash(&argon2id_hash));
+        assert!(stronger_argon2id.needs_rehash(&argon2id_hash));
+        assert!(PasswordHashScheme::Bcrypt { cost: 5 }.needs_rehash(&bcrypt_hash));
+    }
+}
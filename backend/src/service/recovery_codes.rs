@@ -0,0 +1,174 @@
+use super::{AuthService, UserService};
+use crate::api::contracts;
+use crate::errors::Error;
+use crate::models::totp::RecoveryCode;
+use chrono::Utc;
+use log::info;
+use rand::Rng;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_GROUP_LENGTH: usize = 5;
+/// Lowercase letters and digits without the ones that are easily confused, e.g. `l` and `1`.
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+impl AuthService {
+    /// Returns codes formatted as two groups of characters, e.g. `k7pqr-2mxvd`.
+    pub fn generate_recovery_codes(&self) -> Vec<String> {
+        let mut rng = rand::thread_rng();
+        let mut generate_group = || -> String {
+            (0..RECOVERY_CODE_GROUP_LENGTH)
+                .map(|_| {
+                    RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char
+                })
+                .collect()
+        };
+
+        (0..RECOVERY_CODE_COUNT)
+            .map(|_| format!("{}-{}", generate_group(), generate_group()))
+            .collect()
+    }
+
+    /// Recovery codes only have about 50 bits of entropy, so a plain hash of a leaked code could be
+    /// brute forced offline. They are hashed with a key derived from the TOTP encryption key instead,
+    /// bound to the user like their TOTP secret. The code is normalized first, so it can be typed
+    /// without the dash and in any case.
+    pub fn hash_recovery_code(&self, user_id: Uuid, code: &str) -> Vec<u8> {
+        let normalized: String = code
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        self.options
+            .totp_cipher
+            .hash(&[user_id.as_bytes(), normalized.as_bytes()].concat())
+    }
+}
+
+impl UserService {
+    pub async fn regenerate_recovery_codes(
+        &self,
+        auth_service: Arc<AuthService>,
+        user_id: Uuid,
+        request: contracts::RegenerateRecoveryCodesRequest,
+    ) -> Result<Vec<String>, Error> {
+        let totp = self
+            .get_enabled_totp(user_id)
+            .await?
+            .ok_or(Error::TotpNotEnrolled)?;
+
+        self.use_totp_code(&auth_service, &totp, &request.code)
+            .await?;
+
+        self.replace_recovery_codes(&auth_service, user_id).await
+    }
+
+    pub async fn count_recovery_codes(&self, user_id: Uuid) -> Result<i64, Error> {
+        Ok(self.repo.count_unused_recovery_codes(user_id).await?)
+    }
+
+    /// Invalidates the user's previous recovery codes and returns the new ones in plain text.
+    pub(super) async fn replace_recovery_codes(
+        &self,
+        auth_service: &AuthService,
+        user_id: Uuid,
+    ) -> Result<Vec<String>, Error> {
+        let codes = auth_service.generate_recovery_codes();
+        let to_insert = codes
+            .iter()
+            .map(|code| RecoveryCode {
+                id: Uuid::new_v4(),
+                user_id,
+                code_hash: auth_service.hash_recovery_code(user_id, code),
+                used_at: None,
+                created_at: Utc::now(),
+            })
+            .collect();
+        self.repo.replace_recovery_codes(user_id, to_insert).await?;
+
+        Ok(codes)
+    }
+
+    pub(super) async fn use_recovery_code(
+        &self,
+        auth_service: &AuthService,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<(), Error> {
+        let code_hash = auth_service.hash_recovery_code(user_id, code);
+        if !self.repo.use_recovery_code(user_id, code_hash).await? {
+            return Err(Error::InvalidRecoveryCode);
+        }
+
+        info!("user {} used a recovery code", user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::get_test_auth_options;
+    use std::collections::HashSet;
+
+    #[test]
+    fn generate_recovery_codes_should_return_distinct_codes() {
+        // Act
+
+        let actual = get_test_auth_service().generate_recovery_codes();
+
+        // Assert
+
+        assert_eq!(actual.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(
+            actual.iter().collect::<HashSet<_>>().len(),
+            RECOVERY_CODE_COUNT
+        );
+        assert!(actual
+            .iter()
+            .all(|code| code.len() == 11 && code.as_bytes()[5] == b'-'));
+    }
+
+    #[test]
+    fn hash_recovery_code_should_ignore_case_dashes_and_whitespace() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+        let user_id = Uuid::new_v4();
+
+        // Act
+
+        let actual = auth_service.hash_recovery_code(user_id, " K7PQR2mxvd ");
+
+        // Assert
+
+        assert_eq!(
+            actual,
+            auth_service.hash_recovery_code(user_id, "k7pqr-2mxvd")
+        );
+    }
+
+    #[test]
+    fn hash_recovery_code_should_differ_between_users() {
+        // Arrange
+
+        let auth_service = get_test_auth_service();
+
+        // Act
+
+        let actual = auth_service.hash_recovery_code(Uuid::new_v4(), "k7pqr-2mxvd");
+
+        // Assert
+
+        assert_ne!(
+            actual,
+            auth_service.hash_recovery_code(Uuid::new_v4(), "k7pqr-2mxvd")
+        );
+    }
+
+    fn get_test_auth_service() -> AuthService {
+        AuthService::new(get_test_auth_options())
+    }
+}
@@ -0,0 +1,427 @@
+use crate::models::claims::{Claims, MfaChallengeClaims};
+use crate::repository::RevocationRepository;
+use async_trait::async_trait;
+use chrono::{DateTime, SubsecRound, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Keeps track of access tokens that must no longer be accepted even though they have not expired yet.
+#[async_trait]
+pub trait RevocationStore: Debug + Send + Sync {
+    async fn revoke_token(&self, claims: &Claims) -> Result<(), anyhow::Error>;
+
+    async fn revoke_session(&self, session_id: Uuid) -> Result<(), anyhow::Error>;
+
+    /// Revokes the tokens of the user issued before `before`. Tokens carry their issue time in
+    /// microseconds, so the cutoff is truncated to microseconds as well, otherwise a token issued
+    /// right after it would count as issued before.
+    async fn revoke_tokens_issued_before(
+        &self,
+        user_id: Uuid,
+        before: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error>;
+
+    /// Revokes every session of the user except `session_id`, together with their access tokens.
+    async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<(), anyhow::Error>;
+
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool, anyhow::Error>;
+
+    /// MFA challenges are revoked once they were answered or tried too often, so that they can't
+    /// be replayed or used to guess codes. Returns `false` when the challenge was revoked already,
+    /// which only one of several concurrent calls gets to see.
+    async fn revoke_mfa_challenge(
+        &self,
+        claims: &MfaChallengeClaims,
+    ) -> Result<bool, anyhow::Error>;
+
+    /// Counts an attempt at answering the challenge and returns how many there were so far, the
+    /// current one included.
+    async fn record_mfa_attempt(&self, claims: &MfaChallengeClaims) -> Result<u32, anyhow::Error>;
+
+    /// A challenge is revoked like an access token, by its `jti` or by the user's revocation cutoff.
+    async fn is_mfa_challenge_revoked(
+        &self,
+        claims: &MfaChallengeClaims,
+    ) -> Result<bool, anyhow::Error>;
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseRevocationStore {
+    repo: Arc<dyn RevocationRepository>,
+}
+
+impl DatabaseRevocationStore {
+    pub fn new(repo: Arc<dyn RevocationRepository>) -> Self {
+        Self { repo }
+    }
+}
+
+#[async_trait]
+impl RevocationStore for DatabaseRevocationStore {
+    async fn revoke_token(&self, claims: &Claims) -> Result<(), anyhow::Error> {
+        self.repo
+            .insert_revoked_token(claims.jti, claims.sub, claims.expires_at())
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_session(&self, session_id: Uuid) -> Result<(), anyhow::Error> {
+        self.repo.revoke_refresh_token_family(session_id).await?;
+        Ok(())
+    }
+
+    async fn revoke_tokens_issued_before(
+        &self,
+        user_id: Uuid,
+        before: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        // tokens issued earlier in the cutoff's microsecond are still caught by their session,
+        // whose refresh tokens are revoked with the full precision below
+        self.repo
+            .upsert_tokens_revoked_before(user_id, before.trunc_subsecs(6))
+            .await?;
+        self.repo
+            .revoke_refresh_tokens_issued_before(user_id, before)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        // access tokens belong to the session they were issued for, so they are revoked along with it
+        self.repo
+            .revoke_other_refresh_token_families(user_id, session_id)
+            .await?;
+        Ok(())
+    }
+
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool, anyhow::Error> {
+        self.repo
+            .is_token_revoked(claims.jti, claims.sub, claims.sid, claims.issued_at())
+            .await
+    }
+
+    async fn revoke_mfa_challenge(
+        &self,
+        claims: &MfaChallengeClaims,
+    ) -> Result<bool, anyhow::Error> {
+        self.repo
+            .insert_revoked_token(claims.jti, claims.sub, claims.expires_at())
+            .await
+    }
+
+    async fn record_mfa_attempt(&self, claims: &MfaChallengeClaims) -> Result<u32, anyhow::Error> {
+        let attempts = self
+            .repo
+            .increment_mfa_attempts(claims.jti, claims.sub, claims.expires_at())
+            .await?;
+
+        Ok(attempts.try_into().unwrap_or_default())
+    }
+
+    async fn is_mfa_challenge_revoked(
+        &self,
+        claims: &MfaChallengeClaims,
+    ) -> Result<bool, anyhow::Error> {
+        // a challenge doesn't belong to a session yet, so no session can match the nil id
+        self.repo
+            .is_token_revoked(claims.jti, claims.sub, Uuid::nil(), claims.issued_at())
+            .await
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryRevocationStore {
+    revocations: Mutex<InMemoryRevocations>,
+}
+
+#[derive(Debug, Default)]
+struct InMemoryRevocations {
+    tokens: HashSet<Uuid>,
+    sessions: HashSet<Uuid>,
+    revoked_before: HashMap<Uuid, DateTime<Utc>>,
+    /// The session each user kept when their other sessions were revoked, and when that happened.
+    kept_sessions: HashMap<Uuid, (Uuid, DateTime<Utc>)>,
+    mfa_attempts: HashMap<Uuid, u32>,
+}
+
+#[async_trait]
+impl RevocationStore for InMemoryRevocationStore {
+    async fn revoke_token(&self, claims: &Claims) -> Result<(), anyhow::Error> {
+        self.lock().tokens.insert(claims.jti);
+        Ok(())
+    }
+
+    async fn revoke_session(&self, session_id: Uuid) -> Result<(), anyhow::Error> {
+        self.lock().sessions.insert(session_id);
+        Ok(())
+    }
+
+    async fn revoke_tokens_issued_before(
+        &self,
+        user_id: Uuid,
+        before: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        let before = before.trunc_subsecs(6);
+        let mut revocations = self.lock();
+        let revoked_before = revocations.revoked_before.entry(user_id).or_insert(before);
+        if *revoked_before < before {
+            *revoked_before = before;
+        }
+        Ok(())
+    }
+
+    async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        // the sessions of a user aren't known here, so the tokens of other sessions issued until now are revoked instead
+        self.lock()
+            .kept_sessions
+            .insert(user_id, (session_id, Utc::now().trunc_subsecs(6)));
+        Ok(())
+    }
+
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool, anyhow::Error> {
+        let revocations = self.lock();
+        let is_revoked = revocations.tokens.contains(&claims.jti)
+            || revocations.sessions.contains(&claims.sid)
+            || revocations
+                .revoked_before
+                .get(&claims.sub)
+                .is_some_and(|before| *before > claims.issued_at())
+            || revocations
+                .kept_sessions
+                .get(&claims.sub)
+                .is_some_and(|(session_id, before)| {
+                    *session_id != claims.sid && *before > claims.issued_at()
+                });
+
+        Ok(is_revoked)
+    }
+
+    async fn revoke_mfa_challenge(
+        &self,
+        claims: &MfaChallengeClaims,
+    ) -> Result<bool, anyhow::Error> {
+        Ok(self.lock().tokens.insert(claims.jti))
+    }
+
+    async fn record_mfa_attempt(&self, claims: &MfaChallengeClaims) -> Result<u32, anyhow::Error> {
+        let mut revocations = self.lock();
+        let attempts = revocations.mfa_attempts.entry(claims.jti).or_default();
+        *attempts += 1;
+
+        Ok(*attempts)
+    }
+
+    async fn is_mfa_challenge_revoked(
+        &self,
+        claims: &MfaChallengeClaims,
+    ) -> Result<bool, anyhow::Error> {
+        let revocations = self.lock();
+        let is_revoked = revocations.tokens.contains(&claims.jti)
+            || revocations
+                .revoked_before
+                .get(&claims.sub)
+                .is_some_and(|before| *before > claims.issued_at());
+
+        Ok(is_revoked)
+    }
+}
+
+impl InMemoryRevocationStore {
+    fn lock(&self) -> std::sync::MutexGuard<'_, InMemoryRevocations> {
+        self.revocations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Timelike};
+
+    #[actix_web::test]
+    async fn is_revoked_should_return_false_when_nothing_was_revoked() {
+        // Arrange
+
+        let store = InMemoryRevocationStore::default();
+        let claims = get_test_claims(Utc::now());
+
+        // Act
+
+        let actual = store.is_revoked(&claims).await.unwrap();
+
+        // Assert
+
+        assert!(!actual);
+    }
+
+    #[actix_web::test]
+    async fn is_revoked_should_return_true_when_token_was_revoked() {
+        // Arrange
+
+        let store = InMemoryRevocationStore::default();
+        let claims = get_test_claims(Utc::now());
+        store.revoke_token(&claims).await.unwrap();
+
+        // Act
+
+        let actual = store.is_revoked(&claims).await.unwrap();
+
+        // Assert
+
+        assert!(actual);
+    }
+
+    #[actix_web::test]
+    async fn is_revoked_should_return_true_when_session_was_revoked() {
+        // Arrange
+
+        let store = InMemoryRevocationStore::default();
+        let claims = get_test_claims(Utc::now());
+        store.revoke_session(claims.sid).await.unwrap();
+
+        // Act
+
+        let actual = store.is_revoked(&claims).await.unwrap();
+
+        // Assert
+
+        assert!(actual);
+    }
+
+    #[actix_web::test]
+    async fn is_revoked_should_only_revoke_tokens_issued_before_cutoff() {
+        // Arrange
+
+        let store = InMemoryRevocationStore::default();
+        let now = Utc::now();
+        let old_claims = get_test_claims(now - Duration::minutes(5));
+        let new_claims = Claims {
+            sub: old_claims.sub,
+            ..get_test_claims(now + Duration::minutes(5))
+        };
+        store
+            .revoke_tokens_issued_before(old_claims.sub, now)
+            .await
+            .unwrap();
+
+        // Act
+
+        let old_is_revoked = store.is_revoked(&old_claims).await.unwrap();
+        let new_is_revoked = store.is_revoked(&new_claims).await.unwrap();
+
+        // Assert
+
+        assert!(old_is_revoked);
+        assert!(!new_is_revoked);
+    }
+
+    #[actix_web::test]
+    async fn is_revoked_should_tell_tokens_issued_within_same_second_apart() {
+        // Arrange
+
+        let store = InMemoryRevocationStore::default();
+        let cutoff = Utc::now().with_nanosecond(500_000_789).unwrap();
+        let old_claims = get_test_claims(cutoff - Duration::milliseconds(100));
+        let new_claims = Claims {
+            sub: old_claims.sub,
+            ..get_test_claims(cutoff + Duration::microseconds(1))
+        };
+        store
+            .revoke_tokens_issued_before(old_claims.sub, cutoff)
+            .await
+            .unwrap();
+
+        // Act
+
+        let old_is_revoked = store.is_revoked(&old_claims).await.unwrap();
+        let new_is_revoked = store.is_revoked(&new_claims).await.unwrap();
+
+        // Assert
+
+        assert!(old_is_revoked);
+        assert!(!new_is_revoked);
+    }
+
+    #[actix_web::test]
+    async fn is_mfa_challenge_revoked_should_revoke_challenge_issued_earlier_in_same_second() {
+        // Arrange
+
+        let store = InMemoryRevocationStore::default();
+        let cutoff = Utc::now().with_nanosecond(500_000_000).unwrap();
+        let issued_at = cutoff - Duration::milliseconds(100);
+        let claims = MfaChallengeClaims {
+            aud: "test/mfa".to_string(),
+            exp: (issued_at + Duration::minutes(1)).timestamp(),
+            iat: issued_at.timestamp(),
+            iat_us: Some(issued_at.timestamp_micros()),
+            sub: Uuid::new_v4(),
+            jti: Uuid::new_v4(),
+        };
+        store
+            .revoke_tokens_issued_before(claims.sub, cutoff)
+            .await
+            .unwrap();
+
+        // Act
+
+        let actual = store.is_mfa_challenge_revoked(&claims).await.unwrap();
+
+        // Assert
+
+        assert!(actual);
+    }
+
+    #[actix_web::test]
+    async fn is_revoked_should_only_keep_given_session_when_other_sessions_were_revoked() {
+        // Arrange
+
+        let store = InMemoryRevocationStore::default();
+        let kept_claims = get_test_claims(Utc::now() - Duration::minutes(1));
+        let other_claims = Claims {
+            sub: kept_claims.sub,
+            ..get_test_claims(Utc::now() - Duration::minutes(1))
+        };
+        store
+            .revoke_other_sessions(kept_claims.sub, kept_claims.sid)
+            .await
+            .unwrap();
+
+        // Act
+
+        let kept_is_revoked = store.is_revoked(&kept_claims).await.unwrap();
+        let other_is_revoked = store.is_revoked(&other_claims).await.unwrap();
+
+        // Assert
+
+        assert!(!kept_is_revoked);
+        assert!(other_is_revoked);
+    }
+
+    fn get_test_claims(issued_at: DateTime<Utc>) -> Claims {
+        Claims {
+            aud: "test".to_string(),
+            exp: (issued_at + Duration::minutes(1)).timestamp(),
+            iat: issued_at.timestamp(),
+            iat_us: Some(issued_at.timestamp_micros()),
+            sub: Uuid::new_v4(),
+            jti: Uuid::new_v4(),
+            sid: Uuid::new_v4(),
+            roles: Vec::new(),
+        }
+    }
+}
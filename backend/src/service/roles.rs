@@ -0,0 +1,225 @@
+use super::UserService;
+use crate::errors::Error;
+use chrono::Utc;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use log::{error, info};
+
+impl UserService {
+    /// Assigns `role` to the user, which takes effect with the next access token they get.
+    /// Returns `false` when the user already had it.
+    pub async fn grant_role(&self, email: &str, role: &str) -> Result<bool, Error> {
+        let user = self
+            .repo
+            .get_user_by_email(email)
+            .await?
+            .ok_or(Error::UserNotFound)?;
+
+        let is_granted = match self.repo.assign_role(user.id, role).await {
+            Ok(g) => g,
+            Err(e) if is_foreign_key_violation(&e) => return Err(Error::RoleNotFound),
+            Err(e) => {
+                error!("{}", e);
+                return Err(Error::Internal(e));
+            }
+        };
+
+        if is_granted {
+            info!("granted role '{}' to user {}", role, user.id);
+        }
+        Ok(is_granted)
+    }
+
+    /// Takes `role` away from the user and signs them out everywhere, so that none of their
+    /// access tokens carries it anymore. Returns `false` when the user didn't have it.
+    pub async fn revoke_role(&self, email: &str, role: &str) -> Result<bool, Error> {
+        let user = self
+            .repo
+            .get_user_by_email(email)
+            .await?
+            .ok_or(Error::UserNotFound)?;
+
+        if !self.repo.unassign_role(user.id, role).await? {
+            return Ok(false);
+        }
+
+        self.revocation_store
+            .revoke_tokens_issued_before(user.id, Utc::now())
+            .await?;
+
+        info!("revoked role '{}' from user {}", role, user.id);
+        Ok(true)
+    }
+}
+
+/// Assigning a role that is not in the `roles` table violates the foreign key of `user_roles`.
+fn is_foreign_key_violation(error: &anyhow::Error) -> bool {
+    error.source().is_some_and(|source| {
+        matches!(
+            source.downcast_ref::<DieselError>(),
+            Some(DieselError::DatabaseError(
+                DatabaseErrorKind::ForeignKeyViolation,
+                _
+            ))
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::contracts;
+    use crate::models::refresh_token::LoginOutcome;
+    use crate::service::{
+        get_test_auth_options, get_test_user_service, register_test_user, AuthService,
+    };
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn grant_role_should_add_role_to_next_access_token() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        register_test_user(&user_service, &auth_service).await;
+
+        // Act
+
+        let actual = user_service
+            .grant_role("john.doe@example.com", "admin")
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert!(actual);
+        let token = login(&user_service, &auth_service).await;
+        let claims = auth_service.verify_token(token).unwrap().claims;
+        assert_eq!(claims.roles, ["admin"]);
+    }
+
+    #[actix_web::test]
+    async fn grant_role_should_return_error_when_user_does_not_exist() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+
+        // Act
+
+        let actual = user_service
+            .grant_role("john.doe@example.com", "admin")
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::UserNotFound)));
+    }
+
+    #[actix_web::test]
+    async fn revoke_role_should_remove_role_and_revoke_issued_tokens() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        register_test_user(&user_service, &auth_service).await;
+        user_service
+            .grant_role("john.doe@example.com", "admin")
+            .await
+            .unwrap();
+        let token = login(&user_service, &auth_service).await;
+        let claims = auth_service.verify_token(token).unwrap().claims;
+
+        // Act
+
+        let actual = user_service
+            .revoke_role("john.doe@example.com", "admin")
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert!(actual);
+        assert!(user_service
+            .repo
+            .get_user_roles(claims.sub)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(user_service
+            .revocation_store
+            .is_revoked(&claims)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn revoke_role_should_accept_token_issued_right_after() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        register_test_user(&user_service, &auth_service).await;
+        user_service
+            .grant_role("john.doe@example.com", "admin")
+            .await
+            .unwrap();
+        user_service
+            .revoke_role("john.doe@example.com", "admin")
+            .await
+            .unwrap();
+
+        // Act
+
+        let token = login(&user_service, &auth_service).await;
+
+        // Assert
+
+        let claims = auth_service.verify_token(token).unwrap().claims;
+        assert!(claims.roles.is_empty());
+        assert!(!user_service
+            .revocation_store
+            .is_revoked(&claims)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn revoke_role_should_return_false_when_user_does_not_have_role() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        register_test_user(&user_service, &get_test_auth_service()).await;
+
+        // Act
+
+        let actual = user_service
+            .revoke_role("john.doe@example.com", "admin")
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert!(!actual);
+    }
+
+    fn get_test_auth_service() -> Arc<AuthService> {
+        Arc::new(AuthService::new(get_test_auth_options()))
+    }
+
+    async fn login(user_service: &UserService, auth_service: &Arc<AuthService>) -> String {
+        let outcome = user_service
+            .login(
+                auth_service.clone(),
+                contracts::LoginUserRequest {
+                    email: "john.doe@example.com".to_string(),
+                    password: "password123".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        match outcome {
+            LoginOutcome::Authenticated(tokens) => tokens.token,
+            LoginOutcome::MfaRequired(_) => panic!("expected tokens"),
+        }
+    }
+}
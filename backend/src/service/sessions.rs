@@ -0,0 +1,129 @@
+use super::{AuthService, UserService};
+use crate::api::contracts;
+use crate::errors::Error;
+use crate::models::claims::Claims;
+use crate::models::refresh_token::{RefreshToken, TokenPair};
+use chrono::{DateTime, Utc};
+use log::warn;
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl UserService {
+    pub async fn refresh(
+        &self,
+        auth_service: Arc<AuthService>,
+        request: contracts::RefreshTokenRequest,
+    ) -> Result<TokenPair, Error> {
+        let token_hash = auth_service.hash_refresh_token(&request.refresh_token);
+        let stored = match self.repo.get_refresh_token(token_hash).await? {
+            Some(t) => t,
+            None => return Err(Error::InvalidRefreshToken),
+        };
+
+        if stored.revoked_at.is_some() {
+            return Err(Error::InvalidRefreshToken);
+        }
+
+        if stored.rotated_at.is_some() {
+            return Err(self.reject_reused_token(stored.family_id).await);
+        }
+
+        if stored.expires_at <= Utc::now() {
+            return Err(Error::InvalidRefreshToken);
+        }
+
+        let (refresh_token, replacement) =
+            build_refresh_token(&auth_service, stored.user_id, stored.family_id);
+        let is_rotated = self
+            .repo
+            .rotate_refresh_token(stored.id, replacement)
+            .await?;
+        if !is_rotated {
+            // someone else presented the same token between our read and the update
+            return Err(self.reject_reused_token(stored.family_id).await);
+        }
+
+        // read again on every refresh, so that role changes reach the user's next access token
+        let roles = self.repo.get_user_roles(stored.user_id).await?;
+        let token = auth_service.generate_token(stored.user_id, stored.family_id, roles)?;
+
+        Ok(TokenPair {
+            token,
+            refresh_token,
+        })
+    }
+
+    /// Revokes the presented access token and the session it was issued for.
+    pub async fn logout(&self, claims: Claims) -> Result<(), Error> {
+        self.revocation_store.revoke_token(&claims).await?;
+        self.revocation_store.revoke_session(claims.sid).await?;
+
+        Ok(())
+    }
+
+    /// Revokes every token issued to the user before `before`, which defaults to now.
+    pub async fn logout_everywhere(
+        &self,
+        user_id: Uuid,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        let now = Utc::now();
+        let before = before.map_or(now, |b| b.min(now));
+
+        self.revocation_store
+            .revoke_tokens_issued_before(user_id, before)
+            .await?;
+
+        Ok(())
+    }
+
+    pub(super) async fn start_session(
+        &self,
+        auth_service: &AuthService,
+        user_id: Uuid,
+    ) -> Result<TokenPair, Error> {
+        let session_id = Uuid::new_v4();
+        let (refresh_token, to_insert) = build_refresh_token(auth_service, user_id, session_id);
+        self.repo.insert_refresh_token(to_insert).await?;
+
+        let roles = self.repo.get_user_roles(user_id).await?;
+        let token = auth_service.generate_token(user_id, session_id, roles)?;
+
+        Ok(TokenPair {
+            token,
+            refresh_token,
+        })
+    }
+
+    async fn reject_reused_token(&self, family_id: Uuid) -> Error {
+        warn!(
+            "refresh token reuse detected, revoking token family {}",
+            family_id
+        );
+
+        match self.revocation_store.revoke_session(family_id).await {
+            Ok(_) => Error::InvalidRefreshToken,
+            Err(e) => Error::Internal(e),
+        }
+    }
+}
+
+fn build_refresh_token(
+    auth_service: &AuthService,
+    user_id: Uuid,
+    family_id: Uuid,
+) -> (String, RefreshToken) {
+    let (token, token_hash) = auth_service.generate_refresh_token();
+    let refresh_token = RefreshToken {
+        id: Uuid::new_v4(),
+        user_id,
+        family_id,
+        token_hash,
+        expires_at: auth_service.get_refresh_token_expiration(),
+        created_at: Utc::now(),
+        rotated_at: None,
+        revoked_at: None,
+    };
+
+    (token, refresh_token)
+}
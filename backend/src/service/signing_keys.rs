@@ -0,0 +1,145 @@
+use super::{AuthService, UserService};
+use crate::errors::Error;
+use crate::models::signing_key_promotion::SigningKeyPromotion;
+use chrono::Utc;
+use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+
+impl UserService {
+    /// Makes `kid` the key that signs new tokens. The promotion is stored, so it survives restarts
+    /// and every other instance applies it the next time it reloads the signing keys.
+    pub async fn promote_signing_key(
+        &self,
+        auth_service: Arc<AuthService>,
+        kid: &str,
+    ) -> Result<(), Error> {
+        if !auth_service.has_signing_key(kid) {
+            return Err(Error::SigningKeyNotFound);
+        }
+
+        self.repo
+            .upsert_signing_key_promotion(SigningKeyPromotion {
+                kid: kid.to_string(),
+                promoted_at: Utc::now(),
+            })
+            .await?;
+        self.reload_signing_keys(&auth_service).await?;
+
+        info!("promoted signing key '{}'", kid);
+        Ok(())
+    }
+
+    /// Applies the promotions stored by any instance to the keyring.
+    pub async fn reload_signing_keys(&self, auth_service: &AuthService) -> Result<(), Error> {
+        let promotions = self.repo.get_signing_key_promotions().await?;
+        auth_service.apply_signing_key_promotions(&promotions);
+
+        Ok(())
+    }
+}
+
+/// Reloads the signing keys every `interval`, so that keys promoted by another instance sign tokens
+/// here as well, until the runtime shuts down.
+pub async fn reload_signing_keys_periodically(
+    user_service: UserService,
+    auth_service: AuthService,
+    interval: Duration,
+) {
+    let mut interval = actix_web::rt::time::interval(interval);
+    // the first tick completes immediately, right after the signing keys were loaded
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = user_service.reload_signing_keys(&auth_service).await {
+            error!("failed to reload signing keys. reason: {:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::keyring::Keyring;
+    use crate::service::keys::SigningKey;
+    use crate::service::{get_test_auth_options, get_test_user_service, AuthOptions};
+    use jsonwebtoken::{decode_header, Algorithm};
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn promote_signing_key_should_sign_tokens_of_other_instances_after_reload() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(get_test_auth_service());
+        let other_instance = get_test_auth_service();
+
+        // Act
+
+        user_service
+            .promote_signing_key(auth_service.clone(), "second")
+            .await
+            .unwrap();
+        user_service
+            .reload_signing_keys(&other_instance)
+            .await
+            .unwrap();
+
+        // Assert
+
+        let token = other_instance
+            .generate_token(Uuid::new_v4(), Uuid::new_v4(), Vec::new())
+            .unwrap();
+        assert_eq!(
+            decode_header(&token).unwrap().kid.as_deref(),
+            Some("second")
+        );
+    }
+
+    #[actix_web::test]
+    async fn promote_signing_key_should_return_error_when_key_is_unknown() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(get_test_auth_service());
+
+        // Act
+
+        let actual = user_service
+            .promote_signing_key(auth_service.clone(), "missing")
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::SigningKeyNotFound)));
+        assert!(user_service
+            .repo
+            .get_signing_key_promotions()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    fn get_test_auth_service() -> AuthService {
+        AuthService::new(AuthOptions {
+            keyring: Keyring::new(
+                "first".to_string(),
+                vec![
+                    (
+                        "first".to_string(),
+                        SigningKey::from_secret(Algorithm::HS256, b"first").unwrap(),
+                    ),
+                    (
+                        "second".to_string(),
+                        SigningKey::from_secret(Algorithm::HS256, b"second").unwrap(),
+                    ),
+                ],
+                Duration::from_secs(100),
+            )
+            .unwrap(),
+            ..get_test_auth_options()
+        })
+    }
+}
@@ -0,0 +1,172 @@
+use crate::service::AuthService;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::RngCore;
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+const TOTP_SECRET_LENGTH_IN_BYTES: usize = 20;
+const TOTP_STEP_IN_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+/// Codes of the neighbouring steps are accepted as well, so a slightly skewed clock still works.
+const TOTP_ALLOWED_DRIFT_IN_STEPS: i64 = 1;
+
+impl AuthService {
+    pub fn generate_totp_secret(&self) -> Vec<u8> {
+        let mut secret = vec![0u8; TOTP_SECRET_LENGTH_IN_BYTES];
+        rand::thread_rng().fill_bytes(&mut secret);
+
+        secret
+    }
+
+    /// Returns the base32 secret and the `otpauth://` URI that authenticator apps expect.
+    pub fn get_totp_enrollment(&self, account: &str, secret: &[u8]) -> (String, String) {
+        let encoded_secret = BASE32_NOPAD.encode(secret);
+        let issuer = utf8_percent_encode(&self.options.totp_issuer, NON_ALPHANUMERIC).to_string();
+        let account = utf8_percent_encode(account, NON_ALPHANUMERIC);
+
+        let uri = format!(
+            "otpauth://totp/{issuer}:{account}?secret={encoded_secret}&issuer={issuer}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_STEP_IN_SECONDS}"
+        );
+
+        (encoded_secret, uri)
+    }
+
+    pub fn encrypt_totp_secret(
+        &self,
+        user_id: Uuid,
+        secret: &[u8],
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        self.options
+            .totp_cipher
+            .encrypt(secret, user_id.as_bytes())
+            .context("failed to encrypt TOTP secret")
+    }
+
+    pub fn decrypt_totp_secret(
+        &self,
+        user_id: Uuid,
+        secret_ciphertext: &[u8],
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        self.options
+            .totp_cipher
+            .decrypt(secret_ciphertext, user_id.as_bytes())
+            .context("failed to decrypt TOTP secret")
+    }
+
+    /// Returns the time step `code` belongs to. Steps up to and including `last_used_step`
+    /// are skipped, so every code can only be used once.
+    pub fn verify_totp_code(
+        &self,
+        secret: &[u8],
+        code: &str,
+        last_used_step: Option<i64>,
+    ) -> Option<i64> {
+        verify_totp_code_at(secret, code, last_used_step, Utc::now())
+    }
+}
+
+fn verify_totp_code_at(
+    secret: &[u8],
+    code: &str,
+    last_used_step: Option<i64>,
+    now: DateTime<Utc>,
+) -> Option<i64> {
+    let current_step = now.timestamp() / TOTP_STEP_IN_SECONDS;
+
+    (current_step - TOTP_ALLOWED_DRIFT_IN_STEPS..=current_step + TOTP_ALLOWED_DRIFT_IN_STEPS)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| {
+            generate_totp_code(secret, *step)
+                .as_bytes()
+                .ct_eq(code.as_bytes())
+                .into()
+        })
+}
+
+/// RFC 6238 code with HMAC-SHA1, which is what authenticator apps support universally.
+fn generate_totp_code(secret: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the SHA1 secret used by the test vectors in RFC 6238, appendix B
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn generate_totp_code_should_match_rfc_test_vectors() {
+        // Act & Assert
+
+        assert_eq!(generate_totp_code(RFC_SECRET, 59 / 30), "287082");
+        assert_eq!(generate_totp_code(RFC_SECRET, 1111111109 / 30), "081804");
+        assert_eq!(generate_totp_code(RFC_SECRET, 1234567890 / 30), "005924");
+    }
+
+    #[test]
+    fn verify_totp_code_at_should_accept_code_from_previous_step() {
+        // Arrange
+
+        let now = DateTime::from_timestamp(1111111109 + 30, 0).unwrap();
+
+        // Act
+
+        let actual = verify_totp_code_at(RFC_SECRET, "081804", None, now);
+
+        // Assert
+
+        assert_eq!(actual, Some(1111111109 / 30));
+    }
+
+    #[test]
+    fn verify_totp_code_at_should_reject_code_that_was_already_used() {
+        // Arrange
+
+        let now = DateTime::from_timestamp(1111111109, 0).unwrap();
+
+        // Act
+
+        let actual = verify_totp_code_at(RFC_SECRET, "081804", Some(1111111109 / 30), now);
+
+        // Assert
+
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn verify_totp_code_at_should_reject_wrong_code() {
+        // Arrange
+
+        let now = DateTime::from_timestamp(1111111109, 0).unwrap();
+
+        // Act
+
+        let actual = verify_totp_code_at(RFC_SECRET, "123456", None, now);
+
+        // Assert
+
+        assert_eq!(actual, None);
+    }
+}
@@ -1,22 +1,26 @@
 use super::{AuthService, UserService};
 use crate::api::contracts;
 use crate::errors::Error;
-use crate::models::user::User;
-use log::error;
+use crate::mail::templates::MailTemplate;
+use crate::models::claims::Claims;
+use crate::models::refresh_token::LoginOutcome;
+use crate::models::user::{User, UserProfileChanges};
+use chrono::Utc;
+use log::{error, info};
 use std::sync::Arc;
 use uuid::Uuid;
 
 impl UserService {
-    pub fn register(
+    pub async fn register(
         &self,
         auth_service: Arc<AuthService>,
         request: contracts::RegisterUserRequest,
     ) -> Result<User, Error> {
-        let password_hash = match auth_service.hash_password(request.password) {
+        let password_hash = match self.hash_password(&auth_service, request.password).await {
             Ok(p) => p,
             Err(e) => {
                 error!("{}", e);
-                return Err(Error::Internal(e));
+                return Err(e);
             }
         };
 
@@ -25,10 +29,13 @@ impl UserService {
             name: request.name,
             email: request.email,
             password_hash,
+            email_verified_at: None,
         };
 
-        self.repo
-            .insert_user(&self.db_pool, to_insert)
+        let user = self
+            .repo
+            .insert_user(to_insert)
+            .await
             .map_err(|e| match e.source() {
                 Some(source)
                     if source
@@ -49,33 +56,744 @@ impl UserService {
                     error!("{}", e);
                     Error::Internal(e)
                 }
-            })
+            })?;
+
+        // the account is usable without a verified email, so a failed mail must not fail the
+        // registration. the user can ask for another one
+        if let Err(e) = self.send_verification_email(&auth_service, &user).await {
+            error!("failed to send verification email. reason: {}", e);
+        }
+
+        Ok(user)
     }
 
-    pub fn login(
+    pub async fn login(
         &self,
         auth_service: Arc<AuthService>,
         request: contracts::LoginUserRequest,
-    ) -> Result<String, Error> {
-        if let Some((user_id, hash_bytes)) = self
-            .repo
-            .get_stored_credentials(request.email, &self.db_pool)?
-        {
-            let is_matching =
-                auth_service.compare_hash_and_password(request.password, hash_bytes)?;
+    ) -> Result<LoginOutcome, Error> {
+        if let Some(user) = self.repo.get_user_by_email(&request.email).await? {
+            let is_matching = self
+                .compare_hash_and_password(
+                    &auth_service,
+                    request.password.clone(),
+                    user.password_hash.clone(),
+                )
+                .await?;
             if !is_matching {
                 return Err(Error::InvalidCredentials);
             }
 
-            let token = auth_service.generate_token(user_id)?;
+            if auth_service.password_needs_rehash(&user.password_hash) {
+                self.rehash_password(&auth_service, user.id, request.password, user.password_hash)
+                    .await;
+            }
+
+            // only checked after the password, so that it doesn't reveal which emails are registered
+            if auth_service.is_verified_email_required() && user.email_verified_at.is_none() {
+                return Err(Error::EmailNotVerified);
+            }
+
+            let user_id = user.id;
 
-            return Ok(token);
+            if self.get_enabled_totp(user_id).await?.is_some() {
+                let mfa_token = auth_service.generate_mfa_challenge_token(user_id)?;
+                return Ok(LoginOutcome::MfaRequired(mfa_token));
+            }
+
+            let tokens = self.start_session(&auth_service, user_id).await?;
+            return Ok(LoginOutcome::Authenticated(tokens));
         }
 
         Err(Error::InvalidCredentials)
     }
 
-    pub fn get_users(&self) -> Result<Vec<User>, Error> {
-        Ok(self.repo.get_users(&self.db_pool)?)
+    /// Upgrades a hash made with an outdated algorithm or cost. The login succeeds either way,
+    /// so failures are only logged and the upgrade is retried on the next login.
+    async fn rehash_password(
+        &self,
+        auth_service: &Arc<AuthService>,
+        user_id: Uuid,
+        password: String,
+        old_password_hash: Vec<u8>,
+    ) {
+        let result = match self.hash_password(auth_service, password).await {
+            Ok(new_password_hash) => self
+                .repo
+                .update_password_hash(user_id, old_password_hash, new_password_hash)
+                .await
+                .map_err(Error::Internal),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(true) => info!("rehashed password of user {}", user_id),
+            Ok(false) => {}
+            Err(e) => error!(
+                "failed to rehash password of user {}. reason: {}",
+                user_id, e
+            ),
+        }
+    }
+
+    pub async fn get_users(&self) -> Result<Vec<User>, Error> {
+        Ok(self.repo.get_users().await?)
+    }
+
+    pub async fn get_user(&self, user_id: Uuid) -> Result<User, Error> {
+        self.repo
+            .get_user(user_id)
+            .await?
+            .ok_or(Error::UserNotFound)
+    }
+
+    pub async fn update_profile(
+        &self,
+        user_id: Uuid,
+        request: contracts::UpdateUserRequest,
+    ) -> Result<User, Error> {
+        let changes = UserProfileChanges::from(request);
+        if changes.is_empty() {
+            return self.get_user(user_id).await;
+        }
+
+        self.repo
+            .update_user_profile(user_id, changes)
+            .await?
+            .ok_or(Error::UserNotFound)
+    }
+
+    /// Deleting a user also deletes their sessions, so their access tokens stop working as well.
+    pub async fn delete_user(&self, user_id: Uuid) -> Result<(), Error> {
+        if !self.repo.delete_user(user_id).await? {
+            return Err(Error::UserNotFound);
+        }
+
+        info!("deleted user {}", user_id);
+        Ok(())
+    }
+
+    /// Replaces the password of the caller after checking their current one. Every other session
+    /// is signed out, and so is the caller's own unless `keep_current_session` is set.
+    pub async fn change_password(
+        &self,
+        auth_service: Arc<AuthService>,
+        claims: &Claims,
+        request: contracts::ChangePasswordRequest,
+    ) -> Result<(), Error> {
+        let user = self.get_user(claims.sub).await?;
+
+        let is_matching = self
+            .compare_hash_and_password(
+                &auth_service,
+                request.current_password,
+                user.password_hash.clone(),
+            )
+            .await?;
+        if !is_matching {
+            return Err(Error::IncorrectCurrentPassword);
+        }
+
+        let new_password_hash = self
+            .hash_password(&auth_service, request.new_password)
+            .await?;
+        // the update only matches the hash that was checked, so a concurrent change makes it fail
+        // instead of being overwritten
+        let is_updated = self
+            .repo
+            .update_password_hash(user.id, user.password_hash, new_password_hash)
+            .await?;
+        if !is_updated {
+            return Err(Error::PasswordChangedConcurrently);
+        }
+
+        if request.keep_current_session {
+            self.revocation_store
+                .revoke_other_sessions(user.id, claims.sid)
+                .await?;
+        } else {
+            self.revocation_store
+                .revoke_tokens_issued_before(user.id, Utc::now())
+                .await?;
+        }
+
+        info!("password of user {} was changed", user.id);
+        Ok(())
+    }
+
+    /// Hashing is deliberately slow, so it runs on the blocking pool instead of the async runtime.
+    pub(super) async fn hash_password(
+        &self,
+        auth_service: &Arc<AuthService>,
+        password: String,
+    ) -> Result<Vec<u8>, Error> {
+        let auth_service = auth_service.clone();
+
+        self.hashing_pool
+            .run(move || Ok(auth_service.hash_password(password)?))
+            .await
+    }
+
+    async fn compare_hash_and_password(
+        &self,
+        auth_service: &Arc<AuthService>,
+        password: String,
+        password_hash: Vec<u8>,
+    ) -> Result<bool, Error> {
+        let auth_service = auth_service.clone();
+
+        self.hashing_pool
+            .run(move || Ok(auth_service.compare_hash_and_password(password, password_hash)?))
+            .await
+    }
+
+    /// Mailers talk to the mail server synchronously, so sending runs on a blocking pool as well,
+    /// one of its own, so that a slow mail server can't use up the slots logins hash passwords in.
+    pub(super) async fn send_mail(&self, to: String, template: MailTemplate) -> Result<(), Error> {
+        let mailer = self.mailer.clone();
+
+        self.mail_pool
+            .run(move || Ok(mailer.send_template(&to, template)?))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::passwords::PasswordHashScheme;
+    use crate::service::{
+        get_test_auth_options, get_test_register_request, get_test_user_service,
+        register_test_user, AuthOptions,
+    };
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn register_should_store_user_and_send_verification_email() {
+        // Arrange
+
+        let (user_service, mailer) = get_test_user_service();
+
+        // Act
+
+        let actual = user_service
+            .register(get_test_auth_service(), get_test_register_request())
+            .await
+            .unwrap();
+
+        // Assert
+
+        let stored = user_service
+            .repo
+            .get_user(actual.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.email, "john.doe@example.com");
+        assert_ne!(stored.password_hash, b"password123".to_vec());
+        assert_eq!(mailer.get_sent().len(), 1);
+        assert_eq!(mailer.get_sent()[0].to, "john.doe@example.com");
+    }
+
+    #[actix_web::test]
+    async fn register_should_return_error_when_email_already_exists() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        register_test_user(&user_service, &get_test_auth_service()).await;
+
+        // Act
+
+        let actual = user_service
+            .register(get_test_auth_service(), get_test_register_request())
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::EmailAlreadyExists)));
+    }
+
+    #[actix_web::test]
+    async fn login_should_return_tokens_when_credentials_are_valid() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        register_test_user(&user_service, &get_test_auth_service()).await;
+
+        // Act
+
+        let actual = user_service
+            .login(get_test_auth_service(), get_login_request("password123"))
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Ok(LoginOutcome::Authenticated(_))));
+    }
+
+    #[actix_web::test]
+    async fn login_should_return_error_when_password_is_wrong() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        register_test_user(&user_service, &get_test_auth_service()).await;
+
+        // Act
+
+        let actual = user_service
+            .login(get_test_auth_service(), get_login_request("wrong-password"))
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::InvalidCredentials)));
+    }
+
+    #[actix_web::test]
+    async fn login_should_return_error_when_user_does_not_exist() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+
+        // Act
+
+        let actual = user_service
+            .login(get_test_auth_service(), get_login_request("password123"))
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::InvalidCredentials)));
+    }
+
+    #[actix_web::test]
+    async fn login_should_return_error_when_email_is_not_verified_and_verification_is_required() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = Arc::new(AuthService::new(AuthOptions {
+            require_verified_email: true,
+            ..get_test_auth_options()
+        }));
+        register_test_user(&user_service, &auth_service).await;
+
+        // Act
+
+        let actual = user_service
+            .login(auth_service, get_login_request("password123"))
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::EmailNotVerified)));
+    }
+
+    #[actix_web::test]
+    async fn login_should_rehash_password_when_hashed_with_outdated_scheme() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let bcrypt_auth_service = Arc::new(AuthService::new(AuthOptions {
+            password_hash_scheme: PasswordHashScheme::Bcrypt { cost: 4 },
+            ..get_test_auth_options()
+        }));
+        let user = register_test_user(&user_service, &bcrypt_auth_service).await;
+
+        // Act
+
+        let actual = user_service
+            .login(get_test_auth_service(), get_login_request("password123"))
+            .await;
+
+        // Assert
+
+        assert!(actual.is_ok());
+        let stored = user_service.repo.get_user(user.id).await.unwrap().unwrap();
+        assert!(stored.password_hash.starts_with(b"$argon2id$"));
+    }
+
+    #[actix_web::test]
+    async fn update_profile_should_change_given_fields() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let user = register_test_user(&user_service, &get_test_auth_service()).await;
+
+        // Act
+
+        let actual = user_service
+            .update_profile(
+                user.id,
+                contracts::UpdateUserRequest {
+                    name: Some("Jane Doe".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert_eq!(actual.name, "Jane Doe");
+        assert_eq!(actual.email, "john.doe@example.com");
+        let stored = user_service.repo.get_user(user.id).await.unwrap().unwrap();
+        assert_eq!(stored.name, "Jane Doe");
+    }
+
+    #[actix_web::test]
+    async fn update_profile_should_return_user_unchanged_when_no_fields_given() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let user = register_test_user(&user_service, &get_test_auth_service()).await;
+
+        // Act
+
+        let actual = user_service
+            .update_profile(user.id, contracts::UpdateUserRequest::default())
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert_eq!(actual.name, "John Doe");
+    }
+
+    #[actix_web::test]
+    async fn update_profile_should_return_error_when_user_does_not_exist() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+
+        // Act
+
+        let actual = user_service
+            .update_profile(
+                Uuid::new_v4(),
+                contracts::UpdateUserRequest {
+                    name: Some("Jane Doe".to_string()),
+                },
+            )
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::UserNotFound)));
+    }
+
+    #[actix_web::test]
+    async fn delete_user_should_remove_user() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let user = register_test_user(&user_service, &get_test_auth_service()).await;
+
+        // Act
+
+        user_service.delete_user(user.id).await.unwrap();
+
+        // Assert
+
+        assert!(user_service.repo.get_user(user.id).await.unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn delete_user_should_return_error_when_user_does_not_exist() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+
+        // Act
+
+        let actual = user_service.delete_user(Uuid::new_v4()).await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::UserNotFound)));
+    }
+
+    #[actix_web::test]
+    async fn logout_everywhere_should_accept_token_issued_right_after() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        let user = register_test_user(&user_service, &auth_service).await;
+        user_service.logout_everywhere(user.id, None).await.unwrap();
+
+        // Act
+
+        let claims = login(&user_service, &auth_service, "password123").await;
+
+        // Assert
+
+        assert!(!user_service
+            .revocation_store
+            .is_revoked(&claims)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn change_password_should_store_new_password_and_revoke_other_sessions() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        register_test_user(&user_service, &auth_service).await;
+        let current_claims = login(&user_service, &auth_service, "password123").await;
+        let other_claims = login(&user_service, &auth_service, "password123").await;
+
+        // Act
+
+        user_service
+            .change_password(
+                auth_service.clone(),
+                &current_claims,
+                get_change_password_request("password123", true),
+            )
+            .await
+            .unwrap();
+
+        // Assert
+
+        let revocation_store = &user_service.revocation_store;
+        assert!(!revocation_store.is_revoked(&current_claims).await.unwrap());
+        assert!(revocation_store.is_revoked(&other_claims).await.unwrap());
+        let old_password_login = user_service
+            .login(auth_service.clone(), get_login_request("password123"))
+            .await;
+        assert!(matches!(old_password_login, Err(Error::InvalidCredentials)));
+        login(&user_service, &auth_service, "newpassword123").await;
+    }
+
+    #[actix_web::test]
+    async fn change_password_should_revoke_current_session_when_not_kept() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        register_test_user(&user_service, &auth_service).await;
+        let current_claims = login(&user_service, &auth_service, "password123").await;
+
+        // Act
+
+        user_service
+            .change_password(
+                auth_service.clone(),
+                &current_claims,
+                get_change_password_request("password123", false),
+            )
+            .await
+            .unwrap();
+
+        // Assert
+
+        assert!(user_service
+            .revocation_store
+            .is_revoked(&current_claims)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn change_password_should_accept_token_issued_right_after() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        register_test_user(&user_service, &auth_service).await;
+        let current_claims = login(&user_service, &auth_service, "password123").await;
+        user_service
+            .change_password(
+                auth_service.clone(),
+                &current_claims,
+                get_change_password_request("password123", false),
+            )
+            .await
+            .unwrap();
+
+        // Act
+
+        let claims = login(&user_service, &auth_service, "newpassword123").await;
+
+        // Assert
+
+        assert!(!user_service
+            .revocation_store
+            .is_revoked(&claims)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn change_password_should_accept_token_issued_right_after_when_session_is_kept() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        register_test_user(&user_service, &auth_service).await;
+        let current_claims = login(&user_service, &auth_service, "password123").await;
+        user_service
+            .change_password(
+                auth_service.clone(),
+                &current_claims,
+                get_change_password_request("password123", true),
+            )
+            .await
+            .unwrap();
+
+        // Act
+
+        let claims = login(&user_service, &auth_service, "newpassword123").await;
+
+        // Assert
+
+        assert!(!user_service
+            .revocation_store
+            .is_revoked(&claims)
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn change_password_should_return_error_when_current_password_is_wrong() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        let user = register_test_user(&user_service, &auth_service).await;
+        let current_claims = login(&user_service, &auth_service, "password123").await;
+
+        // Act
+
+        let actual = user_service
+            .change_password(
+                auth_service.clone(),
+                &current_claims,
+                get_change_password_request("wrongpassword", true),
+            )
+            .await;
+
+        // Assert
+
+        assert!(matches!(actual, Err(Error::IncorrectCurrentPassword)));
+        let stored = user_service.repo.get_user(user.id).await.unwrap().unwrap();
+        assert_eq!(stored.password_hash, user.password_hash);
+    }
+
+    #[actix_web::test]
+    async fn change_password_should_return_conflict_when_password_was_changed_concurrently() {
+        // Arrange
+
+        let (user_service, _) = get_test_user_service();
+        let auth_service = get_test_auth_service();
+        register_test_user(&user_service, &auth_service).await;
+        let current_claims = login(&user_service, &auth_service, "password123").await;
+
+        // Act
+
+        // both changes check the same current password before either of them stores a new one
+        let (first, second) = futures::join!(
+            user_service.change_password(
+                auth_service.clone(),
+                &current_claims,
+                get_change_password_request("password123", true),
+            ),
+            user_service.change_password(
+                auth_service.clone(),
+                &current_claims,
+                get_change_password_request("password123", true),
+            ),
+        );
+
+        // Assert
+
+        let results = [first, second];
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert!(results
+            .iter()
+            .any(|result| matches!(result, Err(Error::PasswordChangedConcurrently))));
+    }
+
+    async fn login(
+        user_service: &UserService,
+        auth_service: &Arc<AuthService>,
+        password: &str,
+    ) -> Claims {
+        let outcome = user_service
+            .login(auth_service.clone(), get_login_request(password))
+            .await
+            .unwrap();
+
+        match outcome {
+            LoginOutcome::Authenticated(tokens) => {
+                auth_service.verify_token(tokens.token).unwrap().claims
+            }
+            LoginOutcome::MfaRequired(_) => panic!("expected tokens"),
+        }
+    }
+
+    #[actix_web::test]
+    async fn send_mail_should_not_wait_for_password_hashing() {
+        // Arrange
+
+        let (user_service, mailer) = get_test_user_service();
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let hashing_pool = user_service.hashing_pool.clone();
+        let hashing = actix_web::rt::spawn(async move {
+            hashing_pool
+                .run(move || {
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Ok(())
+                })
+                .await
+        });
+        started_rx.await.unwrap();
+
+        // Act
+
+        let actual = actix_web::rt::time::timeout(
+            Duration::from_secs(5),
+            user_service.send_mail(
+                "john.doe@example.com".to_string(),
+                MailTemplate::VerifyEmail {
+                    name: "John Doe".to_string(),
+                    link: "http://localhost/verify-email?token=abc".to_string(),
+                    expires_in_hours: 1,
+                },
+            ),
+        )
+        .await;
+
+        // Assert
+
+        release_tx.send(()).unwrap();
+        hashing.await.unwrap().unwrap();
+        assert!(actual.unwrap().is_ok());
+        assert_eq!(mailer.get_sent().len(), 1);
+    }
+
+    fn get_change_password_request(
+        current_password: &str,
+        keep_current_session: bool,
+    ) -> contracts::ChangePasswordRequest {
+        contracts::ChangePasswordRequest {
+            current_password: current_password.to_string(),
+            new_password: "newpassword123".to_string(),
+            keep_current_session,
+        }
+    }
+
+    fn get_test_auth_service() -> Arc<AuthService> {
+        Arc::new(AuthService::new(get_test_auth_options()))
+    }
+
+    fn get_login_request(password: &str) -> contracts::LoginUserRequest {
+        contracts::LoginUserRequest {
+            email: "john.doe@example.com".to_string(),
+            password: password.to_string(),
+        }
     }
 }
@@ -0,0 +1,276 @@
+use crate::api::routes::{
+    change_password, confirm_totp, delete_user, disable_totp, enroll_totp, forgot_password,
+    get_current_user, get_jwks, get_recovery_codes, get_user, get_users, login, login_mfa, logout,
+    logout_everywhere, promote_signing_key, refresh, regenerate_recovery_codes, register,
+    resend_verification_email, reset_password, update_current_user, update_user, verify_email,
+};
+use crate::configuration::Settings;
+use crate::errors::handle_path_error;
+use crate::middleware::requires_authentication::RequiresAuthentication;
+use crate::middleware::requires_role::RequiresRole;
+use crate::migrations::{self, MigrationCommand};
+use crate::models::role::ADMIN_ROLE;
+use crate::service::blocking::BlockingPool;
+use crate::service::cipher::SecretCipher;
+use crate::service::revocation::RevocationStore;
+use crate::service::signing_keys;
+use crate::service::{AuthOptions, AuthService, UserService};
+use crate::tls::redirect;
+use crate::tls::server::{self, ReloadingCertificate};
+use actix_web::dev::Server;
+use actix_web::middleware::Logger;
+use actix_web::web::Data;
+use actix_web::{web, App, HttpServer};
+use anyhow::Context;
+use futures::future::try_join;
+use log::info;
+use rustls::ServerConfig;
+use secrecy::ExposeSecret;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The whole service, bound to its address but not yet serving requests. Setting
+/// `application.port` to `0` binds it to a free port, which `address` reports.
+pub struct Application {
+    address: SocketAddr,
+    server: Server,
+    /// Redirects plain HTTP to `server`, when it serves HTTPS and `redirect_http_port` is set.
+    redirect: Option<(SocketAddr, Server)>,
+}
+
+impl Application {
+    pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
+        if configuration.database.migrate_on_startup {
+            let applied = migrations::run(&configuration.database, MigrationCommand::Up)
+                .await
+                .context("failed to apply migrations")?;
+            for migration in applied {
+                info!("applied migration {}", migration.name);
+            }
+        }
+
+        let (repository, revocation_store) = configuration
+            .database
+            .get_repositories()
+            .await
+            .context("failed to connect to the database")?;
+
+        let mailer = configuration
+            .mail
+            .get_mailer()
+            .context("failed to set up mailer")?;
+        let user_service = UserService::new(
+            repository,
+            revocation_store.clone(),
+            mailer,
+            BlockingPool::new(configuration.application.max_hashing_tasks),
+            BlockingPool::new(configuration.application.max_mail_tasks),
+        );
+        let auth_service = AuthService::new(AuthOptions {
+            keyring: configuration
+                .authentication
+                .get_keyring()
+                .context("failed to load token signing keys")?,
+            admin_api_key: configuration
+                .authentication
+                .admin_api_key
+                .map(|key| key.expose_secret().to_string()),
+            totp_cipher: SecretCipher::from_base64(
+                configuration
+                    .authentication
+                    .totp_encryption_key
+                    .expose_secret(),
+            )
+            .context("failed to load TOTP encryption key")?,
+            totp_issuer: configuration.authentication.totp_issuer,
+            mfa_challenge_expiration_in_seconds: configuration
+                .authentication
+                .mfa_challenge_expiration_in_seconds,
+            password_reset_url: configuration.authentication.password_reset_url,
+            password_reset_expiration_in_seconds: configuration
+                .authentication
+                .password_reset_expiration_in_seconds,
+            email_verification_url: configuration.authentication.email_verification_url,
+            email_verification_expiration_in_seconds: configuration
+                .authentication
+                .email_verification_expiration_in_seconds,
+            require_verified_email: configuration.authentication.require_verified_email,
+            audience: configuration.authentication.audience,
+            token_expiration_in_seconds: configuration.authentication.token_expiration_in_seconds,
+            refresh_token_expiration_in_seconds: configuration
+                .authentication
+                .refresh_token_expiration_in_seconds,
+            password_hash_scheme: configuration.authentication.password_hashing,
+        });
+        user_service
+            .reload_signing_keys(&auth_service)
+            .await
+            .context("failed to load signing key promotions")?;
+        actix_web::rt::spawn(signing_keys::reload_signing_keys_periodically(
+            user_service.clone(),
+            auth_service.clone(),
+            Duration::from_secs(
+                configuration
+                    .authentication
+                    .signing_key_reload_interval_in_seconds,
+            ),
+        ));
+
+        let host = configuration.application.host.as_str();
+        let listener = bind(host, configuration.application.port)?;
+        let address = listener.local_addr()?;
+
+        let (tls_config, redirect) = match &configuration.application.tls {
+            Some(tls) => {
+                let certificate = Arc::new(
+                    ReloadingCertificate::new(tls).context("failed to load TLS certificate")?,
+                );
+                actix_web::rt::spawn(server::reload_periodically(
+                    certificate.clone(),
+                    Duration::from_secs(tls.reload_interval_in_seconds),
+                ));
+
+                let redirect = match tls.redirect_http_port {
+                    Some(port) => {
+                        let listener = bind(host, port)?;
+                        Some((
+                            listener.local_addr()?,
+                            redirect::run(listener, address.port())?,
+                        ))
+                    }
+                    None => None,
+                };
+
+                (Some(server::get_server_config(certificate)?), redirect)
+            }
+            None => (None, None),
+        };
+
+        let server = run(
+            listener,
+            tls_config,
+            user_service,
+            auth_service,
+            revocation_store,
+        )?;
+
+        Ok(Self {
+            address,
+            server,
+            redirect,
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn redirect_address(&self) -> Option<SocketAddr> {
+        self.redirect.as_ref().map(|(address, _)| *address)
+    }
+
+    pub async fn run_until_stopped(self) -> std::io::Result<()> {
+        match self.redirect {
+            Some((_, redirect)) => try_join(self.server, redirect).await.map(|_| ()),
+            None => self.server.await,
+        }
+    }
+}
+
+fn bind(host: &str, port: u16) -> Result<TcpListener, anyhow::Error> {
+    TcpListener::bind((host, port)).with_context(|| format!("failed to bind to {}:{}", host, port))
+}
+
+/// Serves HTTPS instead of plain HTTP when `tls_config` is set.
+fn run(
+    listener: TcpListener,
+    tls_config: Option<ServerConfig>,
+    user_service: UserService,
+    auth_service: AuthService,
+    revocation_store: Arc<dyn RevocationStore>,
+) -> Result<Server, anyhow::Error> {
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(Logger::default())
+            .app_data(web::PathConfig::default().error_handler(handle_path_error))
+            .route("/.well-known/jwks.json", web::get().to(get_jwks))
+            .service(
+                web::scope("/api")
+                    .service(
+                        web::scope("users")
+                            .wrap(RequiresAuthentication)
+                            .service(
+                                web::resource("/")
+                                    .wrap(RequiresRole(ADMIN_ROLE))
+                                    .route(web::get().to(get_users)),
+                            )
+                            .service(
+                                web::resource("/me")
+                                    .route(web::get().to(get_current_user))
+                                    .route(web::patch().to(update_current_user)),
+                            )
+                            .route("/me/password", web::post().to(change_password))
+                            // after "/me", which would match it as well
+                            .service(
+                                web::resource("/{id}")
+                                    .wrap(RequiresRole(ADMIN_ROLE))
+                                    .route(web::get().to(get_user))
+                                    .route(web::patch().to(update_user))
+                                    .route(web::delete().to(delete_user)),
+                            ),
+                    )
+                    .service(
+                        web::scope("admin")
+                            .route("/keys/promote", web::post().to(promote_signing_key)),
+                    )
+                    .service(
+                        web::scope("auth")
+                            .route("/register", web::post().to(register))
+                            .route("/login", web::post().to(login))
+                            .route("/login/mfa", web::post().to(login_mfa))
+                            .route("/refresh", web::post().to(refresh))
+                            .route("/password/forgot", web::post().to(forgot_password))
+                            .route("/password/reset", web::post().to(reset_password))
+                            .route("/verify-email", web::post().to(verify_email))
+                            .route(
+                                "/verify-email/resend",
+                                web::post().to(resend_verification_email),
+                            )
+                            .service(
+                                web::resource("/logout")
+                                    .wrap(RequiresAuthentication)
+                                    .route(web::post().to(logout)),
+                            )
+                            .service(
+                                web::resource("/logout/all")
+                                    .wrap(RequiresAuthentication)
+                                    .route(web::post().to(logout_everywhere)),
+                            )
+                            .service(
+                                web::scope("/mfa/totp")
+                                    .wrap(RequiresAuthentication)
+                                    .route("/enroll", web::post().to(enroll_totp))
+                                    .route("/confirm", web::post().to(confirm_totp))
+                                    .route("/disable", web::post().to(disable_totp)),
+                            )
+                            .service(
+                                web::resource("/mfa/recovery-codes")
+                                    .wrap(RequiresAuthentication)
+                                    .route(web::get().to(get_recovery_codes))
+                                    .route(web::post().to(regenerate_recovery_codes)),
+                            ),
+                    ),
+            )
+            .app_data(Data::new(user_service.clone()))
+            .app_data(Data::new(auth_service.clone()))
+            .app_data(Data::from(revocation_store.clone()))
+    });
+
+    let server = match tls_config {
+        Some(tls_config) => server.listen_rustls_0_23(listener, tls_config)?,
+        None => server.listen(listener)?,
+    };
+
+    Ok(server.run())
+}
@@ -0,0 +1,46 @@
+use anyhow::anyhow;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::path::Path;
+use std::sync::Arc;
+
+pub mod redirect;
+pub mod server;
+
+/// Every TLS connection, to clients and to Postgres, uses the same cryptography.
+pub fn get_crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Reads every certificate in a PEM file, e.g. a certificate followed by its intermediates.
+pub fn read_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, anyhow::Error> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            anyhow!(
+                "failed to read certificates from {}. reason: {}",
+                path.display(),
+                e
+            )
+        })?;
+
+    if certs.is_empty() {
+        return Err(anyhow!(
+            "{} does not contain any certificates",
+            path.display()
+        ));
+    }
+
+    Ok(certs)
+}
+
+pub fn read_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, anyhow::Error> {
+    PrivateKeyDer::from_pem_file(path).map_err(|e| {
+        anyhow!(
+            "failed to read private key from {}. reason: {}",
+            path.display(),
+            e
+        )
+    })
+}
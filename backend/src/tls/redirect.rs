@@ -0,0 +1,74 @@
+use actix_web::dev::Server;
+use actix_web::http::header::LOCATION;
+use actix_web::middleware::Logger;
+use actix_web::web::Data;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use std::net::TcpListener;
+
+#[derive(Clone, Copy)]
+struct HttpsPort(u16);
+
+/// Serves plain HTTP on `listener` and answers every request with a permanent redirect to the same
+/// URL over HTTPS, on `https_port`.
+pub fn run(listener: TcpListener, https_port: u16) -> Result<Server, anyhow::Error> {
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(Logger::default())
+            .app_data(Data::new(HttpsPort(https_port)))
+            .default_service(web::to(redirect_to_https))
+    })
+    .listen(listener)?
+    .run();
+
+    Ok(server)
+}
+
+async fn redirect_to_https(req: HttpRequest, https_port: Data<HttpsPort>) -> HttpResponse {
+    let path_and_query = req.uri().path_and_query().map_or("/", |p| p.as_str());
+    let location = get_https_url(req.connection_info().host(), https_port.0, path_and_query);
+
+    // unlike 301, 308 makes clients repeat the request with the same method and body
+    HttpResponse::PermanentRedirect()
+        .insert_header((LOCATION, location))
+        .finish()
+}
+
+fn get_https_url(host: &str, https_port: u16, path_and_query: &str) -> String {
+    // the port of the plain HTTP listener is replaced. IPv6 addresses are enclosed in brackets
+    let host = match host.rfind(']') {
+        Some(end) => &host[..=end],
+        None => host.split(':').next().unwrap_or(host),
+    };
+
+    match https_port {
+        443 => format!("https://{}{}", host, path_and_query),
+        port => format!("https://{}:{}{}", host, port, path_and_query),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_https_url_should_replace_port_of_host() {
+        // Act
+
+        let actual = get_https_url("example.com:8080", 8443, "/api/users/?page=2");
+
+        // Assert
+
+        assert_eq!(actual, "https://example.com:8443/api/users/?page=2");
+    }
+
+    #[test]
+    fn get_https_url_should_omit_default_port() {
+        // Act
+
+        let actual = get_https_url("[::1]:80", 443, "/");
+
+        // Assert
+
+        assert_eq!(actual, "https://[::1]/");
+    }
+}
@@ -0,0 +1,144 @@
+use super::{get_crypto_provider, read_certificates, read_private_key};
+use crate::configuration::TlsSettings;
+use anyhow::Context;
+use log::{error, info};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+/// Hands out the certificate of the HTTPS listener, which can be swapped while the server is running.
+/// Connections that are already established keep the certificate they were set up with.
+pub struct ReloadingCertificate {
+    certificate_path: PathBuf,
+    private_key_path: PathBuf,
+    current: RwLock<LoadedCertificate>,
+}
+
+struct LoadedCertificate {
+    key: Arc<CertifiedKey>,
+    /// When the certificate and the private key files were last modified, as of loading them.
+    modified_at: (SystemTime, SystemTime),
+}
+
+impl Debug for ReloadingCertificate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReloadingCertificate")
+            .field("certificate_path", &self.certificate_path)
+            .field("private_key_path", &self.private_key_path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReloadingCertificate {
+    pub fn new(settings: &TlsSettings) -> Result<Self, anyhow::Error> {
+        let current = load_certificate(&settings.certificate_path, &settings.private_key_path)?;
+
+        Ok(Self {
+            certificate_path: settings.certificate_path.clone(),
+            private_key_path: settings.private_key_path.clone(),
+            current: RwLock::new(current),
+        })
+    }
+
+    /// Loads the files again if either of them was modified since they were last loaded. Returns
+    /// `false` when nothing changed. The current certificate stays in use when loading fails, e.g.
+    /// because the certificate was replaced but its key wasn't yet, and loading is retried on the next call.
+    pub fn reload_if_modified(&self) -> Result<bool, anyhow::Error> {
+        let modified_at = get_modified_at(&self.certificate_path, &self.private_key_path)?;
+        if self.read().modified_at == modified_at {
+            return Ok(false);
+        }
+
+        let reloaded = load_certificate(&self.certificate_path, &self.private_key_path)?;
+        *self
+            .current
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = reloaded;
+
+        Ok(true)
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, LoadedCertificate> {
+        self.current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertificate {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.read().key.clone())
+    }
+}
+
+fn load_certificate(
+    certificate_path: &Path,
+    private_key_path: &Path,
+) -> Result<LoadedCertificate, anyhow::Error> {
+    // taken before reading the files, so that a file replaced in the meantime is loaded again on the next check
+    let modified_at = get_modified_at(certificate_path, private_key_path)?;
+    let key = CertifiedKey::from_der(
+        read_certificates(certificate_path)?,
+        read_private_key(private_key_path)?,
+        &get_crypto_provider(),
+    )
+    .with_context(|| {
+        format!(
+            "failed to use the private key in {} with the certificate in {}",
+            private_key_path.display(),
+            certificate_path.display()
+        )
+    })?;
+
+    Ok(LoadedCertificate {
+        key: Arc::new(key),
+        modified_at,
+    })
+}
+
+fn get_modified_at(
+    certificate_path: &Path,
+    private_key_path: &Path,
+) -> Result<(SystemTime, SystemTime), anyhow::Error> {
+    let get = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("failed to read metadata of {}", path.display()))
+    };
+
+    Ok((get(certificate_path)?, get(private_key_path)?))
+}
+
+pub fn get_server_config(
+    certificate: Arc<ReloadingCertificate>,
+) -> Result<ServerConfig, anyhow::Error> {
+    Ok(ServerConfig::builder_with_provider(get_crypto_provider())
+        .with_safe_default_protocol_versions()
+        .context("failed to set up TLS")?
+        .with_no_client_auth()
+        .with_cert_resolver(certificate))
+}
+
+/// Checks the certificate for changes every `interval`, until the runtime shuts down.
+pub async fn reload_periodically(certificate: Arc<ReloadingCertificate>, interval: Duration) {
+    let mut interval = actix_web::rt::time::interval(interval);
+    // the first tick completes immediately, right after the certificate was loaded
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        match certificate.reload_if_modified() {
+            Ok(true) => info!(
+                "reloaded TLS certificate from {}",
+                certificate.certificate_path.display()
+            ),
+            Ok(false) => {}
+            Err(e) => error!("failed to reload TLS certificate. reason: {:#}", e),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::helpers::{get_register_request, spawn_app_with};
+use backend::api::contracts::LoginUserRequest;
+use backend::service::passwords::PasswordHashScheme;
+use futures::future::join_all;
+use std::time::{Duration, Instant};
+
+/// How long the JWKS may take while every hashing slot is busy. Far below how long the logins
+/// queued up behind each other take.
+const MAX_JWKS_LATENCY: Duration = Duration::from_millis(500);
+
+#[actix_web::test]
+async fn get_jwks_should_respond_while_logins_use_every_hashing_slot() {
+    // Arrange
+
+    let app = spawn_app_with(|c| {
+        c.application.max_hashing_tasks = 1;
+        // slow enough for the logins below to keep the only hashing slot busy for a while
+        c.authentication.password_hashing = PasswordHashScheme::Argon2id {
+            memory_cost_in_kib: 8192,
+            iterations: 2,
+            parallelism: 1,
+        };
+    })
+    .await;
+    let request = get_register_request();
+    app.register(&request).await;
+    let login_request = LoginUserRequest {
+        email: request.email,
+        password: request.password,
+    };
+    let started_at = Instant::now();
+    let logins = join_all((0..4).map(|_| app.post_login(&login_request)));
+
+    // Act
+
+    let jwks = async {
+        // lets the logins reach the server first
+        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+        let sent_at = Instant::now();
+        let response = app.get_jwks().await;
+
+        (response, sent_at.elapsed())
+    };
+    let (logins, (actual, latency)) = futures::join!(logins, jwks);
+
+    // Assert
+
+    let logins_took = started_at.elapsed();
+    assert!(logins.iter().all(|login| login.status() == 200));
+    assert_eq!(actual.status(), 200);
+    assert!(
+        latency < MAX_JWKS_LATENCY,
+        "JWKS took {:?} while logins took {:?}",
+        latency,
+        logins_took
+    );
+    assert!(
+        logins_took > MAX_JWKS_LATENCY,
+        "logins took {:?}, too short to keep the hashing slot busy",
+        logins_took
+    );
+}
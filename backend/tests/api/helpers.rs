@@ -0,0 +1,300 @@
+use backend::api::contracts::{
+    LoginUserRequest, LoginUserResponse, LogoutEverywhereRequest, RegisterUserRequest,
+    RegisterUserResponse, Response,
+};
+use backend::configuration::{
+    get_configuration, DatabaseBackendSettings, DatabaseSettings, MailTransportSettings, Settings,
+};
+use backend::mail::in_memory::InMemoryMailer;
+use backend::models::role::ADMIN_ROLE;
+use backend::service::blocking::BlockingPool;
+use backend::service::passwords::PasswordHashScheme;
+use backend::service::UserService;
+use backend::startup::Application;
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct TestApp {
+    pub address: String,
+    /// Where plain HTTP is redirected to HTTPS, if the application was configured to.
+    pub redirect_address: Option<String>,
+    pub client: reqwest::Client,
+    database: DatabaseSettings,
+}
+
+impl TestApp {
+    pub async fn post_register(&self, body: &impl Serialize) -> reqwest::Response {
+        self.client
+            .post(format!("{}/api/auth/register", self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("failed to send register request")
+    }
+
+    pub async fn post_login(&self, body: &impl Serialize) -> reqwest::Response {
+        self.client
+            .post(format!("{}/api/auth/login", self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("failed to send login request")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.client
+            .get(format!("{}/.well-known/jwks.json", self.address))
+            .send()
+            .await
+            .expect("failed to send JWKS request")
+    }
+
+    pub async fn post_logout_everywhere(&self, token: &str) -> reqwest::Response {
+        self.client
+            .post(format!("{}/api/auth/logout/all", self.address))
+            .bearer_auth(token)
+            .json(&LogoutEverywhereRequest { before: None })
+            .send()
+            .await
+            .expect("failed to send logout everywhere request")
+    }
+
+    pub async fn get_users(&self, token: Option<&str>) -> reqwest::Response {
+        let mut request = self.client.get(format!("{}/api/users/", self.address));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+
+        request
+            .send()
+            .await
+            .expect("failed to send get users request")
+    }
+
+    pub async fn get_current_user(&self, token: Option<&str>) -> reqwest::Response {
+        let mut request = self.client.get(format!("{}/api/users/me", self.address));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+
+        request
+            .send()
+            .await
+            .expect("failed to send get current user request")
+    }
+
+    pub async fn patch_current_user(
+        &self,
+        token: &str,
+        body: &impl Serialize,
+    ) -> reqwest::Response {
+        self.client
+            .patch(format!("{}/api/users/me", self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("failed to send update current user request")
+    }
+
+    pub async fn post_change_password(
+        &self,
+        token: &str,
+        body: &impl Serialize,
+    ) -> reqwest::Response {
+        self.client
+            .post(format!("{}/api/users/me/password", self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("failed to send change password request")
+    }
+
+    /// Takes the id as a string, so that malformed ones can be sent as well.
+    pub async fn get_user(&self, token: &str, user_id: &str) -> reqwest::Response {
+        self.client
+            .get(format!("{}/api/users/{}", self.address, user_id))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("failed to send get user request")
+    }
+
+    pub async fn patch_user(
+        &self,
+        token: &str,
+        user_id: &str,
+        body: &impl Serialize,
+    ) -> reqwest::Response {
+        self.client
+            .patch(format!("{}/api/users/{}", self.address, user_id))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("failed to send update user request")
+    }
+
+    pub async fn delete_user(&self, token: &str, user_id: &str) -> reqwest::Response {
+        self.client
+            .delete(format!("{}/api/users/{}", self.address, user_id))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("failed to send delete user request")
+    }
+
+    /// Assigns `role` the way `backend roles grant` does, through a connection of its own.
+    pub async fn grant_role(&self, email: &str, role: &str) {
+        let (repository, revocation_store) = self
+            .database
+            .get_repositories()
+            .await
+            .expect("failed to connect to the test database");
+        let user_service = UserService::new(
+            repository,
+            revocation_store,
+            Arc::new(InMemoryMailer::default()),
+            BlockingPool::new(1),
+            BlockingPool::new(1),
+        );
+
+        user_service
+            .grant_role(email, role)
+            .await
+            .expect("failed to grant role");
+    }
+
+    /// Registers `request`, returning the id of the new user.
+    pub async fn register(&self, request: &RegisterUserRequest) -> Uuid {
+        let response = self.post_register(request).await;
+        assert_eq!(response.status(), 200);
+
+        let body: Response<RegisterUserResponse> = response.json().await.unwrap();
+        body.data.unwrap().user.id
+    }
+
+    /// Registers `request` and logs in with it, returning the access token.
+    pub async fn register_and_login(&self, request: &RegisterUserRequest) -> String {
+        self.register(request).await;
+
+        self.login(request).await
+    }
+
+    /// Registers `request` as an administrator and logs in with it, returning the access token.
+    pub async fn register_admin_and_login(&self, request: &RegisterUserRequest) -> String {
+        self.register(request).await;
+        self.grant_role(&request.email, ADMIN_ROLE).await;
+
+        self.login(request).await
+    }
+
+    /// Logs in as the user registered with `request`, returning the access token.
+    pub async fn login(&self, request: &RegisterUserRequest) -> String {
+        let response = self
+            .post_login(&LoginUserRequest {
+                email: request.email.clone(),
+                password: request.password.clone(),
+            })
+            .await;
+        assert_eq!(response.status(), 200);
+
+        let body: Response<LoginUserResponse> = response.json().await.unwrap();
+        body.data.unwrap().token
+    }
+}
+
+/// Starts the application on a free port, on a database of its own, so tests can run in parallel.
+pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Like `spawn_app`, with `configure` making changes to the settings last.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
+    let configuration = {
+        let mut c = get_configuration().expect("failed to read configuration");
+        c.application.port = 0;
+        c.database = get_test_database(c.database).await;
+        c.mail.transport = MailTransportSettings::InMemory;
+        // the default cost is meant for production and makes the tests crawl
+        c.authentication.password_hashing = PasswordHashScheme::Argon2id {
+            memory_cost_in_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        configure(&mut c);
+        c
+    };
+    let database = configuration.database.clone();
+    let scheme = match configuration.application.tls {
+        Some(_) => "https",
+        None => "http",
+    };
+
+    let application = Application::build(configuration)
+        .await
+        .expect("failed to build application");
+    let address = format!("{}://{}", scheme, application.address());
+    let redirect_address = application
+        .redirect_address()
+        .map(|address| format!("http://{}", address));
+    actix_web::rt::spawn(application.run_until_stopped());
+
+    TestApp {
+        address,
+        redirect_address,
+        client: reqwest::Client::new(),
+        database,
+    }
+}
+
+pub fn get_register_request() -> RegisterUserRequest {
+    RegisterUserRequest {
+        name: "John Doe".to_string(),
+        email: format!("{}@example.com", Uuid::new_v4()),
+        password: "password123".to_string(),
+    }
+}
+
+/// A file rather than `:memory:`, so that the tests can open the same database as the application.
+#[cfg(feature = "sqlite")]
+async fn get_test_database(settings: DatabaseSettings) -> DatabaseSettings {
+    let path = std::env::temp_dir().join(format!("{}.db", Uuid::new_v4()));
+
+    DatabaseSettings {
+        backend: DatabaseBackendSettings::Sqlite {
+            path: path.to_string_lossy().into_owned(),
+        },
+        ..settings
+    }
+}
+
+/// Connects the same way as the application, so the tests also run against a Postgres that requires TLS.
+#[cfg(not(feature = "sqlite"))]
+async fn get_test_database(settings: DatabaseSettings) -> DatabaseSettings {
+    use diesel_async::RunQueryDsl;
+
+    let settings = DatabaseSettings {
+        backend: DatabaseBackendSettings::Postgres,
+        database_name: Uuid::new_v4().to_string(),
+        migrate_on_startup: true,
+        ..settings
+    };
+
+    let maintenance_settings = DatabaseSettings {
+        database_name: "postgres".to_string(),
+        ..settings.clone()
+    };
+    let mut conn = maintenance_settings
+        .establish_connection()
+        .await
+        .expect("failed to connect to Postgres");
+    diesel::sql_query(format!(r#"CREATE DATABASE "{}""#, settings.database_name))
+        .execute(&mut conn)
+        .await
+        .expect("failed to create test database");
+
+    settings
+}
@@ -0,0 +1,116 @@
+use crate::helpers::spawn_app_with;
+use backend::configuration::TlsSettings;
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[actix_web::test]
+async fn https_should_serve_requests_with_certificate_reloaded_from_disk() {
+    // Arrange
+
+    let directory = get_test_directory();
+    let first_certificate = write_self_signed_certificate(&directory);
+    let app =
+        spawn_app_with(|c| c.application.tls = Some(get_tls_settings(&directory, None))).await;
+    let jwks_url = format!("{}/.well-known/jwks.json", app.address);
+
+    let response = get_client(&first_certificate)
+        .get(&jwks_url)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Act
+
+    let second_certificate = write_self_signed_certificate(&directory);
+    actix_web::rt::time::sleep(Duration::from_millis(2500)).await;
+
+    // Assert
+
+    let response = get_client(&second_certificate).get(&jwks_url).send().await;
+    let stale_response = get_client(&first_certificate).get(&jwks_url).send().await;
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(response.unwrap().status(), 200);
+    assert!(stale_response.is_err());
+}
+
+#[actix_web::test]
+async fn http_should_redirect_to_https_when_redirect_port_is_set() {
+    // Arrange
+
+    let directory = get_test_directory();
+    write_self_signed_certificate(&directory);
+    let app = spawn_app_with(|c| {
+        c.application.tls = Some(get_tls_settings(&directory, Some(0)));
+    })
+    .await;
+    let client = Client::builder().redirect(Policy::none()).build().unwrap();
+
+    // Act
+
+    let response = client
+        .post(format!(
+            "{}/api/auth/login?source=test",
+            app.redirect_address.unwrap()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(response.status(), 308);
+    assert_eq!(
+        response.headers()["location"],
+        format!("{}/api/auth/login?source=test", app.address).as_str()
+    );
+}
+
+fn get_test_directory() -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("tls-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+fn get_tls_settings(directory: &Path, redirect_http_port: Option<u16>) -> TlsSettings {
+    TlsSettings {
+        certificate_path: directory.join("server.crt"),
+        private_key_path: directory.join("server.key"),
+        reload_interval_in_seconds: 1,
+        redirect_http_port,
+    }
+}
+
+/// Replaces the certificate in `directory` with a new one, which is returned.
+fn write_self_signed_certificate(directory: &Path) -> Certificate {
+    let certified_key =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string(), "127.0.0.1".to_string()])
+            .unwrap();
+    let certificate_pem = certified_key.cert.pem();
+
+    // the key first, so that the certificate never gets paired with the key of the previous one
+    std::fs::write(
+        directory.join("server.key"),
+        certified_key.signing_key.serialize_pem(),
+    )
+    .unwrap();
+    std::fs::write(directory.join("server.crt"), &certificate_pem).unwrap();
+
+    Certificate::from_pem(certificate_pem.as_bytes()).unwrap()
+}
+
+/// A client that only trusts `certificate`.
+fn get_client(certificate: &Certificate) -> Client {
+    Client::builder()
+        .tls_built_in_root_certs(false)
+        .add_root_certificate(certificate.clone())
+        .build()
+        .unwrap()
+}
@@ -0,0 +1,89 @@
+use crate::helpers::{get_register_request, spawn_app};
+use backend::api::contracts::{LoginUserRequest, LoginUserResponse, Response};
+
+#[actix_web::test]
+async fn login_should_return_tokens_when_credentials_are_valid() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    app.post_register(&request).await;
+
+    // Act
+
+    let actual = app
+        .post_login(&LoginUserRequest {
+            email: request.email,
+            password: request.password,
+        })
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 200);
+    let body: Response<LoginUserResponse> = actual.json().await.unwrap();
+    let tokens = body.data.unwrap();
+    assert!(!tokens.token.is_empty());
+    assert!(!tokens.refresh_token.is_empty());
+}
+
+#[actix_web::test]
+async fn login_should_return_unauthorized_when_password_is_wrong() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    app.post_register(&request).await;
+
+    // Act
+
+    let actual = app
+        .post_login(&LoginUserRequest {
+            email: request.email,
+            password: "wrong-password".to_string(),
+        })
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 401);
+}
+
+#[actix_web::test]
+async fn login_should_return_unauthorized_when_user_does_not_exist() {
+    // Arrange
+
+    let app = spawn_app().await;
+
+    // Act
+
+    let actual = app
+        .post_login(&LoginUserRequest {
+            email: "nobody@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 401);
+}
+
+#[actix_web::test]
+async fn login_should_return_accepted_token_right_after_logout_everywhere() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    let old_token = app.register_and_login(&request).await;
+    assert_eq!(app.post_logout_everywhere(&old_token).await.status(), 204);
+
+    // Act
+
+    let new_token = app.login(&request).await;
+
+    // Assert
+
+    assert_eq!(app.get_current_user(Some(&old_token)).await.status(), 401);
+    assert_eq!(app.get_current_user(Some(&new_token)).await.status(), 200);
+}
@@ -0,0 +1,6 @@
+mod blocking;
+mod helpers;
+mod https;
+mod login;
+mod register;
+mod users;
@@ -0,0 +1,72 @@
+use crate::helpers::{get_register_request, spawn_app};
+use backend::api::contracts::{RegisterUserRequest, RegisterUserResponse, Response};
+
+#[actix_web::test]
+async fn register_should_return_ok_and_the_user_when_request_is_valid() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+
+    // Act
+
+    let actual = app.post_register(&request).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 200);
+    let body: Response<RegisterUserResponse> = actual.json().await.unwrap();
+    let user = body.data.unwrap().user;
+    assert_eq!(user.email, request.email);
+    assert_eq!(user.name, request.name);
+}
+
+#[actix_web::test]
+async fn register_should_return_conflict_when_email_already_exists() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    app.post_register(&request).await;
+
+    // Act
+
+    let actual = app.post_register(&request).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 409);
+}
+
+#[actix_web::test]
+async fn register_should_return_bad_request_when_request_is_invalid() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let cases = [
+        ("", "john.doe@example.com", "password123", "empty name"),
+        ("John Doe", "not-an-email", "password123", "invalid email"),
+        (
+            "John Doe",
+            "john.doe@example.com",
+            "short",
+            "short password",
+        ),
+    ];
+
+    for (name, email, password, description) in cases {
+        // Act
+
+        let actual = app
+            .post_register(&RegisterUserRequest {
+                name: name.to_string(),
+                email: email.to_string(),
+                password: password.to_string(),
+            })
+            .await;
+
+        // Assert
+
+        assert_eq!(actual.status(), 400, "{}", description);
+    }
+}
@@ -0,0 +1,433 @@
+use crate::helpers::{get_register_request, spawn_app};
+use backend::api::contracts::{
+    ChangePasswordRequest, GetUserResponse, GetUsersResponse, LoginUserRequest,
+    RegisterUserRequest, Response, UpdateUserRequest, UpdateUserResponse,
+};
+use backend::models::role::ADMIN_ROLE;
+use uuid::Uuid;
+
+#[actix_web::test]
+async fn get_users_should_return_unauthorized_when_no_token_is_sent() {
+    // Arrange
+
+    let app = spawn_app().await;
+
+    // Act
+
+    let actual = app.get_users(None).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 401);
+}
+
+#[actix_web::test]
+async fn get_users_should_return_forbidden_when_user_is_not_admin() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let token = app.register_and_login(&get_register_request()).await;
+
+    // Act
+
+    let actual = app.get_users(Some(&token)).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 403);
+    let body: Response<()> = actual.json().await.unwrap();
+    assert_eq!(
+        body.errors.unwrap()[0].message,
+        "you are not allowed to perform this action"
+    );
+}
+
+#[actix_web::test]
+async fn get_users_should_return_registered_users_to_admin() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    assert_eq!(app.post_register(&request).await.status(), 200);
+    app.grant_role(&request.email, ADMIN_ROLE).await;
+    let token = app.login(&request).await;
+
+    // Act
+
+    let actual = app.get_users(Some(&token)).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 200);
+    let body: Response<GetUsersResponse> = actual.json().await.unwrap();
+    let users = body.data.unwrap().users;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].email, request.email);
+}
+
+#[actix_web::test]
+async fn get_current_user_should_return_unauthorized_when_no_token_is_sent() {
+    // Arrange
+
+    let app = spawn_app().await;
+
+    // Act
+
+    let actual = app.get_current_user(None).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 401);
+}
+
+#[actix_web::test]
+async fn get_current_user_should_return_caller() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    let token = app.register_and_login(&request).await;
+
+    // Act
+
+    let actual = app.get_current_user(Some(&token)).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 200);
+    let body: Response<GetUserResponse> = actual.json().await.unwrap();
+    let user = body.data.unwrap().user;
+    assert_eq!(user.name, request.name);
+    assert_eq!(user.email, request.email);
+}
+
+#[actix_web::test]
+async fn update_current_user_should_change_name() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    let token = app.register_and_login(&request).await;
+
+    // Act
+
+    let actual = app
+        .patch_current_user(
+            &token,
+            &UpdateUserRequest {
+                name: Some("Jane Doe".to_string()),
+            },
+        )
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 200);
+    let body: Response<UpdateUserResponse> = actual.json().await.unwrap();
+    assert_eq!(body.data.unwrap().user.name, "Jane Doe");
+
+    let body: Response<GetUserResponse> = app
+        .get_current_user(Some(&token))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let user = body.data.unwrap().user;
+    assert_eq!(user.name, "Jane Doe");
+    assert_eq!(user.email, request.email);
+}
+
+#[actix_web::test]
+async fn update_current_user_should_return_bad_request_when_name_is_empty() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let token = app.register_and_login(&get_register_request()).await;
+
+    // Act
+
+    let actual = app
+        .patch_current_user(
+            &token,
+            &UpdateUserRequest {
+                name: Some(String::new()),
+            },
+        )
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 400);
+    let body: Response<()> = actual.json().await.unwrap();
+    assert_eq!(
+        body.errors.unwrap()[0].message,
+        "name must be at least 1 character long"
+    );
+}
+
+#[actix_web::test]
+async fn change_password_should_return_forbidden_when_current_password_is_wrong() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let token = app.register_and_login(&get_register_request()).await;
+
+    // Act
+
+    let actual = app
+        .post_change_password(
+            &token,
+            &ChangePasswordRequest {
+                current_password: "wrongpassword".to_string(),
+                new_password: "newpassword123".to_string(),
+                keep_current_session: true,
+            },
+        )
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 403);
+}
+
+#[actix_web::test]
+async fn change_password_should_return_bad_request_when_new_password_is_too_short() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    let token = app.register_and_login(&request).await;
+
+    // Act
+
+    let actual = app
+        .post_change_password(
+            &token,
+            &ChangePasswordRequest {
+                current_password: request.password,
+                new_password: "short".to_string(),
+                keep_current_session: true,
+            },
+        )
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 400);
+    let body: Response<()> = actual.json().await.unwrap();
+    assert_eq!(
+        body.errors.unwrap()[0].message,
+        "password must be at least 8 characters long"
+    );
+}
+
+#[actix_web::test]
+async fn change_password_should_sign_out_other_sessions_and_keep_current_one() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    let current_token = app.register_and_login(&request).await;
+    let other_token = app.login(&request).await;
+
+    // Act
+
+    let actual = app
+        .post_change_password(
+            &current_token,
+            &ChangePasswordRequest {
+                current_password: request.password.clone(),
+                new_password: "newpassword123".to_string(),
+                keep_current_session: true,
+            },
+        )
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 204);
+    assert_eq!(
+        app.get_current_user(Some(&current_token)).await.status(),
+        200
+    );
+    assert_eq!(app.get_current_user(Some(&other_token)).await.status(), 401);
+
+    let old_password_login = app
+        .post_login(&LoginUserRequest {
+            email: request.email.clone(),
+            password: request.password,
+        })
+        .await;
+    assert_eq!(old_password_login.status(), 401);
+    let new_password_login = app
+        .post_login(&LoginUserRequest {
+            email: request.email,
+            password: "newpassword123".to_string(),
+        })
+        .await;
+    assert_eq!(new_password_login.status(), 200);
+}
+
+#[actix_web::test]
+async fn change_password_should_accept_login_right_after() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    let token = app.register_and_login(&request).await;
+    let change_password = app
+        .post_change_password(
+            &token,
+            &ChangePasswordRequest {
+                current_password: request.password.clone(),
+                new_password: "newpassword123".to_string(),
+                keep_current_session: false,
+            },
+        )
+        .await;
+    assert_eq!(change_password.status(), 204);
+
+    // Act
+
+    let new_token = app
+        .login(&RegisterUserRequest {
+            password: "newpassword123".to_string(),
+            ..request
+        })
+        .await;
+
+    // Assert
+
+    assert_eq!(app.get_current_user(Some(&token)).await.status(), 401);
+    assert_eq!(app.get_current_user(Some(&new_token)).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn get_user_should_return_forbidden_when_user_is_not_admin() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let request = get_register_request();
+    let token = app.register_and_login(&request).await;
+    let user_id = app.register(&get_register_request()).await;
+
+    // Act
+
+    let actual = app.get_user(&token, &user_id.to_string()).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 403);
+}
+
+#[actix_web::test]
+async fn get_user_should_return_user_to_admin() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let token = app.register_admin_and_login(&get_register_request()).await;
+    let request = get_register_request();
+    let user_id = app.register(&request).await;
+
+    // Act
+
+    let actual = app.get_user(&token, &user_id.to_string()).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 200);
+    let body: Response<GetUserResponse> = actual.json().await.unwrap();
+    let user = body.data.unwrap().user;
+    assert_eq!(user.id, user_id);
+    assert_eq!(user.email, request.email);
+}
+
+#[actix_web::test]
+async fn get_user_should_return_not_found_when_user_does_not_exist() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let token = app.register_admin_and_login(&get_register_request()).await;
+
+    // Act
+
+    let actual = app.get_user(&token, &Uuid::new_v4().to_string()).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 404);
+    let body: Response<()> = actual.json().await.unwrap();
+    assert_eq!(body.errors.unwrap()[0].message, "user not found");
+}
+
+#[actix_web::test]
+async fn get_user_should_return_bad_request_when_id_is_malformed() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let token = app.register_admin_and_login(&get_register_request()).await;
+
+    // Act
+
+    let actual = app.get_user(&token, "not-a-uuid").await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 400);
+    let body: Response<()> = actual.json().await.unwrap();
+    assert_eq!(
+        body.errors.unwrap()[0].message,
+        "invalid path parameter supplied"
+    );
+}
+
+#[actix_web::test]
+async fn update_user_should_change_name_of_given_user() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let token = app.register_admin_and_login(&get_register_request()).await;
+    let user_id = app.register(&get_register_request()).await;
+
+    // Act
+
+    let actual = app
+        .patch_user(
+            &token,
+            &user_id.to_string(),
+            &UpdateUserRequest {
+                name: Some("Jane Doe".to_string()),
+            },
+        )
+        .await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 200);
+    let body: Response<UpdateUserResponse> = actual.json().await.unwrap();
+    let user = body.data.unwrap().user;
+    assert_eq!(user.id, user_id);
+    assert_eq!(user.name, "Jane Doe");
+}
+
+#[actix_web::test]
+async fn delete_user_should_remove_user_and_sign_them_out() {
+    // Arrange
+
+    let app = spawn_app().await;
+    let token = app.register_admin_and_login(&get_register_request()).await;
+    let request = get_register_request();
+    let user_id = app.register(&request).await.to_string();
+    let user_token = app.login(&request).await;
+
+    // Act
+
+    let actual = app.delete_user(&token, &user_id).await;
+
+    // Assert
+
+    assert_eq!(actual.status(), 204);
+    assert_eq!(app.get_user(&token, &user_id).await.status(), 404);
+    assert_eq!(app.get_current_user(Some(&user_token)).await.status(), 401);
+    assert_eq!(app.delete_user(&token, &user_id).await.status(), 404);
+}